@@ -0,0 +1,574 @@
+//! 命令行参数模块
+//! 提供 --version、--self-check 与 devices，供脚本和健康检查在不启动 TUI 的情况下调用；--import-scrcpy 导入已有的 scrcpy 设置
+
+use std::path::Path;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Config;
+use crate::console::escape_json;
+use crate::device_monitor::{DeviceMonitor, ScrcpyFailure, Transport};
+use crate::scrcpy_compat;
+use crate::scrcpy_import;
+use crate::session::SessionKind;
+use crate::tui::{self, DeviceInfo, DeviceStatus};
+
+/// 启动器版本号
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 命令行指定的运行方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// 默认：启动 TUI 并监控设备；json 为 true 时不启动 TUI，以 JSON 行输出日志
+    Run { json: bool },
+    /// 输出版本信息后退出
+    Version,
+    /// 执行自检后退出，退出码表示是否健康
+    SelfCheck,
+    /// 查找已有的 scrcpy 脚本、快捷方式与环境变量，确认后导入配置文件
+    ImportScrcpy,
+    /// 查询一次设备列表并输出后退出；json 为 true 时输出 JSON 数组
+    Devices { json: bool },
+    /// 按配置启动一次 scrcpy 并在前台等待，以 scrcpy 的退出码退出
+    Launch(LaunchOptions),
+    /// 输出用法后退出
+    Help,
+}
+
+/// launch 子命令的参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// 目标设备，不指定时按 priority 选择已连接的设备
+    pub serial: Option<String>,
+    /// 使用的配置档，不指定时与监控模式相同
+    pub preset: Option<String>,
+    /// -- 之后原样附加的 scrcpy 参数
+    pub args: Vec<String>,
+}
+
+impl LaunchOptions {
+    /// 解析 launch 之后的参数：[序列号] [--preset 名称] [-- scrcpy 参数...]
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--" => {
+                    options.args = iter.cloned().collect();
+                    break;
+                }
+                "--preset" => match iter.next() {
+                    Some(name) => options.preset = Some(name.clone()),
+                    None => return Err("--preset 需要指定配置档名称".to_string()),
+                },
+                other if other.starts_with('-') => {
+                    return Err(format!("launch 不支持参数 {}，scrcpy 参数请写在 -- 之后", other));
+                }
+                serial if options.serial.is_none() => options.serial = Some(serial.to_string()),
+                other => return Err(format!("launch 只能指定一台设备，多余的参数: {}", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+impl Command {
+    /// 解析命令行参数（不含程序名），无法识别时返回错误信息
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|arg| arg.as_ref().to_string()).collect();
+        // launch 之后的参数属于子命令，其中 -- 之后的内容原样交给 scrcpy
+        if args.first().is_some_and(|arg| arg == "launch") {
+            return LaunchOptions::parse(&args[1..]).map(Command::Launch);
+        }
+
+        let mut command = None;
+        let mut json = false;
+        for arg in &args {
+            let next = match arg.as_str() {
+                "--json" => {
+                    json = true;
+                    continue;
+                }
+                "-V" | "--version" => Command::Version,
+                "--self-check" => Command::SelfCheck,
+                "--import-scrcpy" => Command::ImportScrcpy,
+                "devices" => Command::Devices { json: false },
+                "-h" | "--help" => Command::Help,
+                other => return Err(format!("未知参数: {}", other)),
+            };
+            if command.as_ref().is_some_and(|command| *command != next) {
+                return Err("devices、--version、--self-check、--import-scrcpy 与 --help 不能同时使用".to_string());
+            }
+            command = Some(next);
+        }
+        match command {
+            None => Ok(Command::Run { json }),
+            Some(Command::Devices { .. }) => Ok(Command::Devices { json }),
+            Some(_) if json => Err("--json 只能在监控模式或 devices 命令中使用".to_string()),
+            Some(command) => Ok(command),
+        }
+    }
+}
+
+/// 命令行用法说明
+pub fn usage() -> String {
+    format!(
+        "scrcpy-launcher {}\n\n用法: scrcpy-launcher [选项]\n      scrcpy-launcher devices [--json]  查询一次设备列表并输出（名称、状态、系统版本、电量等）\n      scrcpy-launcher launch [序列号] [--preset 配置档] [-- scrcpy 参数...]  按配置启动一次 scrcpy，以 scrcpy 的退出码退出\n\n选项:\n  -V, --version     输出启动器与 scrcpy 版本信息\n      --self-check  检查配置、adb 与终端是否可用，失败时退出码为 1\n      --import-scrcpy  从已有的 scrcpy 启动脚本、快捷方式与 SCRCPY_ 环境变量导入参数\n      --json        不启动界面，以 JSON 行输出日志（输出被重定向时自动使用纯文本日志）\n  -h, --help        输出本帮助",
+        VERSION
+    )
+}
+
+/// 输出启动器版本、内置 scrcpy 版本与构建信息
+pub async fn print_version(scrcpy_dir: &Path) {
+    let mut monitor = DeviceMonitor::new(scrcpy_dir);
+    let scrcpy = match monitor.detect_scrcpy_version().await {
+        Some(version) => version.to_string(),
+        None => format!("未找到 ({})", monitor.scrcpy_exe.display()),
+    };
+    let profile = if cfg!(debug_assertions) { "debug" } else { "release" };
+
+    println!("scrcpy-launcher {}", VERSION);
+    println!("scrcpy {}", scrcpy);
+    println!("构建: {}-{} ({})", std::env::consts::OS, std::env::consts::ARCH, profile);
+}
+
+/// 依次检查配置文件、adb 与终端，输出每一项结果，全部通过时返回 true
+pub async fn self_check(scrcpy_dir: &Path) -> bool {
+    let mut healthy = true;
+    let mut report = |name: &str, result: Result<String, String>| match result {
+        Ok(detail) => println!("✅ {}: {}", name, detail),
+        Err(e) => {
+            healthy = false;
+            println!("❌ {}: {}", name, e);
+        }
+    };
+
+    let config = Config::load().map(|(config, _)| config);
+    let adb_config = config.as_ref().map(|c| c.adb.clone()).unwrap_or_default();
+    report(
+        "配置文件",
+        config.map(|_| Config::path().display().to_string()),
+    );
+
+    let mut monitor = DeviceMonitor::new(scrcpy_dir);
+    monitor.set_adb_config(adb_config);
+    report(
+        "scrcpy",
+        monitor
+            .detect_scrcpy_version()
+            .await
+            .map(|version| version.to_string())
+            .ok_or_else(|| format!("无法执行 {}", monitor.scrcpy_exe.display())),
+    );
+    report(
+        "adb",
+        monitor
+            .run_adb(&["version"], 5)
+            .await
+            .map(|output| output.lines().next().unwrap_or_default().trim().to_string()),
+    );
+
+    report(
+        "终端",
+        tui::probe_terminal().map(|(width, height)| format!("{}x{}", width, height)),
+    );
+
+    healthy
+}
+
+/// 读取配置，出错时提示并使用默认配置
+fn load_config() -> Config {
+    match Config::load() {
+        Ok((config, _)) => config,
+        Err(e) => {
+            eprintln!("⚠️ {}，将使用默认配置", e);
+            Config::default()
+        }
+    }
+}
+
+/// 查询一次设备并按监控时相同的方式补全名称、详情与电量后输出，adb 不可用时返回 false
+pub async fn list_devices(scrcpy_dir: &Path, json: bool) -> bool {
+    let config = load_config();
+    let mut monitor = DeviceMonitor::new(scrcpy_dir);
+    monitor.set_adb_config(config.adb.clone());
+    // 摄像头、音频支持情况与 scrcpy 版本有关
+    monitor.detect_scrcpy_version().await;
+
+    let mut devices = match monitor.check_devices().await {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return false;
+        }
+    };
+    if monitor.take_daemon_started() {
+        eprintln!("ℹ️ adb server 未运行，已自动启动");
+    }
+    monitor.load_device_info(&devices).await;
+    monitor.fill_device_names(&mut devices);
+    monitor.fill_details(&mut devices);
+    monitor.fill_battery(&mut devices);
+    for device in devices.iter_mut() {
+        device.nickname = config.nickname(&device.id).map(str::to_string);
+        if device.status.is_ready() && !config.is_device_allowed(&device.id) {
+            device.status = DeviceStatus::Ignored;
+        }
+    }
+
+    if json {
+        println!("{}", devices_json(&config, &devices));
+    } else if devices.is_empty() {
+        println!("暂无设备连接");
+    } else {
+        print!("{}", devices_table(&config, &devices));
+    }
+    true
+}
+
+/// 按监控模式相同的规则生成参数，启动一次 scrcpy 并在前台等待其退出
+/// 返回 scrcpy 的退出码；启动器自身出错（找不到设备、配置无效等）时返回 1，与 scrcpy 启动失败一致
+pub async fn launch(scrcpy_dir: &Path, options: &LaunchOptions) -> i32 {
+    let config = load_config();
+    let mut monitor = DeviceMonitor::new(scrcpy_dir);
+    monitor.set_adb_config(config.adb.clone());
+    monitor.detect_scrcpy_version().await;
+    if !monitor.is_scrcpy_available() {
+        eprintln!("❌ 未找到 scrcpy（已检查 scrcpy 目录、PATH 和常见安装位置）");
+        return 1;
+    }
+
+    let mut devices = match monitor.check_devices().await {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    if monitor.take_daemon_started() {
+        eprintln!("ℹ️ adb server 未运行，已自动启动");
+    }
+    monitor.load_device_info(&devices).await;
+    monitor.fill_device_names(&mut devices);
+    monitor.fill_details(&mut devices);
+    // 指定了序列号时不受允许/排除名单限制
+    let device = match &options.serial {
+        Some(serial) => devices.into_iter().find(|d| &d.id == serial).ok_or_else(|| format!("未找到设备 {}", serial)),
+        None => devices
+            .into_iter()
+            .filter(|d| d.status.is_ready() && config.is_device_allowed(&d.id))
+            .min_by_key(|d| config.priority_rank(&d.id))
+            .ok_or_else(|| "没有可以投屏的设备".to_string()),
+    };
+    let mut device = match device {
+        Ok(device) if device.status.is_ready() => device,
+        Ok(device) => {
+            let hint = device.status.hint().map(|hint| format!("：{}", hint)).unwrap_or_default();
+            eprintln!("❌ 设备 {} 当前为{}状态，无法投屏{}", device.id, device.status.label(), hint);
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    device.nickname = config.nickname(&device.id).map(str::to_string);
+
+    let args = match launch_args(&config, &monitor, &device, options).await {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    match monitor.start_scrcpy(Some(&device.id), &args).await {
+        Ok(unsupported) => {
+            for item in unsupported {
+                eprintln!(
+                    "⚠️ 当前 scrcpy 不支持{}（需要 {} 及以上），已忽略参数: {}",
+                    item.feature.name(),
+                    item.feature.min_version(),
+                    item.arg
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    }
+    eprintln!("🚀 {} ({}): {}", device.display_name(), device.id, monitor.last_args.join(" "));
+
+    // scrcpy 在单独的进程组中，收不到控制台的 Ctrl+C；由启动器请求其正常退出并等待，超时仍未退出则强制结束
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+    let mut stopped = false;
+    loop {
+        for line in monitor.take_scrcpy_errors() {
+            eprintln!("scrcpy: {}", line);
+        }
+        if !monitor.is_scrcpy_running().await {
+            break;
+        }
+        tokio::select! {
+            _ = &mut interrupted => {
+                monitor.stop_scrcpy().await;
+                stopped = true;
+                break;
+            }
+            _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {}
+        }
+    }
+
+    let code = match monitor.last_exit.as_ref() {
+        Some(exit) => exit.code.unwrap_or(1),
+        None if stopped => 0,
+        None => 1,
+    };
+    if code != 0 {
+        if let Some(failure) = ScrcpyFailure::detect(&monitor.scrcpy_stderr_lines()) {
+            eprintln!("❌ {}", failure.message());
+        }
+        if let Some(path) = &monitor.session_log {
+            eprintln!("完整输出见: {}", path.display());
+        }
+    }
+    code
+}
+
+/// 按配置组合 launch 命令的 scrcpy 参数，与监控循环使用同一套规则，-- 之后的参数追加在最后
+async fn launch_args(config: &Config, monitor: &DeviceMonitor, device: &DeviceInfo, options: &LaunchOptions) -> Result<Vec<String>, String> {
+    let serial = device.id.as_str();
+    let kind = config.session_kind(serial);
+    if kind == SessionKind::Record {
+        if let Some(free) = crate::recording_space_low(&config.recording, device).await {
+            return Err(format!(
+                "录像目录所在磁盘剩余空间不足（剩余 {}，至少需要 {} MB）",
+                crate::disk_space::format_size(free),
+                config.recording.min_free_mb
+            ));
+        }
+    }
+    // 命令行明确指定的内容出错时直接报错，配置中的连接方式参数与配置档出错时与监控循环一样忽略并提示
+    if let Some(name) = options.preset.as_deref().filter(|name| !config.profiles.contains_key(*name)) {
+        return Err(format!("配置档 {} 不存在", name));
+    }
+    scrcpy_compat::validate_user_args(&config.scrcpy_args).map_err(|e| format!("scrcpy_args无效（{}）", e))?;
+    scrcpy_compat::validate_user_args(&options.args).map_err(|e| format!("-- 之后的参数无效（{}）", e))?;
+
+    let setup = crate::SessionSetup {
+        kind,
+        audio_enabled: config.audio.enabled,
+        user_args: &config.scrcpy_args,
+        profile: options.preset.clone().or_else(|| config.profile_name(serial, device.server.is_some()).map(String::from)),
+        show_fps: false,
+        presentation: false,
+        window: None,
+        rejected_options: &[],
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let printer = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let crate::TuiMessage::Log(level, message) = msg {
+                eprintln!("{} {}", level.icon(), message);
+            }
+        }
+    });
+    let mut args = crate::build_launch_args(config, monitor, device, &setup, &tx).await;
+    drop(tx);
+    let _ = printer.await;
+
+    args.extend(options.args.iter().cloned());
+    Ok(args)
+}
+
+/// 设备表格的一行：序列号、名称、状态、连接方式、系统版本、电量、形态、配置档
+fn device_row(config: &Config, device: &DeviceInfo) -> [String; 8] {
+    let details = device.details.as_ref();
+    let transport = match &device.server {
+        Some(server) => format!("{} @{}", Transport::from_serial(&device.id).label(), server),
+        None => Transport::from_serial(&device.id).label().to_string(),
+    };
+    [
+        device.id.clone(),
+        device.display_name(),
+        device.status.label().to_string(),
+        transport,
+        details.map(|d| format!("Android {}", d.android_version)).unwrap_or_default(),
+        device
+            .battery
+            .map(|b| format!("{}%{}", b.level, if b.charging { " 充电中" } else { "" }))
+            .unwrap_or_default(),
+        config.form_factor(&device.id, details.map(|d| d.form_factor)).label().to_string(),
+        config.profile_name(&device.id, device.server.is_some()).unwrap_or_default().to_string(),
+    ]
+}
+
+/// 按显示宽度对齐的设备表格，每行以换行结尾
+fn devices_table(config: &Config, devices: &[DeviceInfo]) -> String {
+    let header = ["序列号", "名称", "状态", "连接方式", "系统", "电量", "形态", "配置档"].map(str::to_string);
+    let rows: Vec<[String; 8]> = std::iter::once(header).chain(devices.iter().map(|d| device_row(config, d))).collect();
+    let widths: Vec<usize> = (0..8).map(|i| rows.iter().map(|row| row[i].width()).max().unwrap_or(0)).collect();
+
+    let mut table = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// 设备列表的 JSON 数组，字段值使用英文名称，便于脚本判断
+fn devices_json(config: &Config, devices: &[DeviceInfo]) -> String {
+    let string = |value: Option<&str>| value.map(|v| format!("\"{}\"", escape_json(v))).unwrap_or_else(|| "null".to_string());
+    let items: Vec<String> = devices
+        .iter()
+        .map(|device| {
+            let details = device.details.as_ref();
+            let remote = device.server.is_some();
+            format!(
+                r#"{{"serial":{},"name":{},"nickname":{},"status":"{}","transport":"{}","server":{},"android":{},"sdk":{},"battery":{},"charging":{},"form_factor":"{}","profile":{}}}"#,
+                string(Some(&device.id)),
+                string(Some(&device.name)),
+                string(device.nickname.as_deref()),
+                device.status.name(),
+                Transport::from_serial(&device.id).name(),
+                string(device.server.as_deref()),
+                string(details.map(|d| d.android_version.as_str())),
+                details.and_then(|d| d.sdk).map_or("null".to_string(), |sdk| sdk.to_string()),
+                device.battery.map_or("null".to_string(), |b| b.level.to_string()),
+                device.battery.map_or("null".to_string(), |b| b.charging.to_string()),
+                config.form_factor(&device.id, details.map(|d| d.form_factor)).name(),
+                string(config.profile_name(&device.id, remote)),
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+/// 列出找到的 scrcpy 设置，用户确认后写入配置文件，返回是否成功
+pub fn import_scrcpy(scrcpy_dir: &Path) -> bool {
+    let sources = scrcpy_import::find_sources(scrcpy_dir);
+    let env = scrcpy_import::scrcpy_env_vars();
+    if sources.is_empty() && env.is_empty() {
+        println!("未找到带参数启动 scrcpy 的脚本或快捷方式，也没有 SCRCPY_ 环境变量");
+        return true;
+    }
+
+    for source in &sources {
+        println!("📄 {}: {}", source.path.display(), source.args.join(" "));
+    }
+    for (name, value) in &env {
+        println!("🔧 {}={}", name, value);
+    }
+    let args = scrcpy_import::merge_args(&sources);
+    if let Err(e) = crate::scrcpy_compat::validate_user_args(&args) {
+        println!("❌ 参数无法导入: {}", e);
+        return false;
+    }
+    println!("\n将追加到 {}:", Config::path().display());
+    if !args.is_empty() {
+        println!("  scrcpy_args: {}", args.join(" "));
+    }
+    if !env.is_empty() {
+        println!("  [adb.env]: {}", env.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", "));
+    }
+    print!("确认导入？[y/N] ");
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        println!("已取消");
+        return true;
+    }
+    match Config::import_scrcpy_settings(&args, &env) {
+        Ok(()) => {
+            println!("✅ 已导入");
+            true
+        }
+        Err(e) => {
+            println!("❌ {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_command_line() {
+        assert_eq!(Command::parse(Vec::<String>::new()), Ok(Command::Run { json: false }));
+        assert_eq!(Command::parse(["--json"]), Ok(Command::Run { json: true }));
+        assert_eq!(Command::parse(["--version"]), Ok(Command::Version));
+        assert_eq!(Command::parse(["-V", "--version"]), Ok(Command::Version));
+        assert_eq!(Command::parse(["--self-check"]), Ok(Command::SelfCheck));
+        assert_eq!(Command::parse(["--import-scrcpy"]), Ok(Command::ImportScrcpy));
+        assert!(Command::parse(["--version", "--self-check"]).is_err());
+        assert!(Command::parse(["--verbose"]).is_err());
+        assert!(Command::parse(["--json", "--version"]).is_err());
+        assert_eq!(Command::parse(["devices"]), Ok(Command::Devices { json: false }));
+        assert_eq!(Command::parse(["devices", "--json"]), Ok(Command::Devices { json: true }));
+        assert!(Command::parse(["devices", "--self-check"]).is_err());
+
+        let launch = Command::parse(["launch", "R58M123456", "--preset", "低延迟", "--", "--max-fps=30", "-s"]).unwrap();
+        assert_eq!(launch, Command::Launch(LaunchOptions {
+            serial: Some("R58M123456".to_string()),
+            preset: Some("低延迟".to_string()),
+            args: vec!["--max-fps=30".to_string(), "-s".to_string()],
+        }));
+        assert_eq!(Command::parse(["launch"]), Ok(Command::Launch(LaunchOptions::default())));
+        assert!(Command::parse(["launch", "--max-fps=30"]).is_err());
+        assert!(Command::parse(["launch", "a", "b"]).is_err());
+        assert!(Command::parse(["launch", "--preset"]).is_err());
+    }
+
+    #[test]
+    fn formats_device_list() {
+        let config = Config::parse(
+            r#"
+            [devices."R58M123456"]
+            profile = "演示"
+            "#,
+        )
+        .unwrap();
+        let device = |id: &str, status: DeviceStatus| DeviceInfo {
+            id: id.to_string(),
+            name: "Galaxy S21".to_string(),
+            status,
+            battery: None,
+            nickname: None,
+            details: None,
+            product: None,
+            transport_id: None,
+            server: None,
+            last_error: None,
+        };
+        let mut phone = device("R58M123456", DeviceStatus::Connected);
+        phone.nickname = Some("工作机".to_string());
+        phone.battery = Some(crate::device_monitor::BatteryStatus { level: 80, charging: true });
+        let devices = [phone, device("192.168.1.5:5555", DeviceStatus::Unauthorized)];
+
+        let table = devices_table(&config, &devices);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("R58M123456        Galaxy S21 (工作机)  已连接"));
+        assert!(lines[1].contains("80% 充电中"));
+        assert!(lines[2].contains("无线 (adb connect)"));
+
+        let json = devices_json(&config, &devices[..1]);
+        assert!(json.starts_with(r#"[{"serial":"R58M123456","name":"Galaxy S21","nickname":"工作机","status":"device","transport":"usb","server":null"#));
+        assert!(json.ends_with(r#""battery":80,"charging":true,"form_factor":"phone","profile":"演示"}]"#));
+        assert_eq!(devices_json(&config, &[]), "[]");
+    }
+}
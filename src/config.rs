@@ -0,0 +1,801 @@
+//! 配置模块
+//! 从用户配置目录读取 config.toml，缺失的字段使用默认值
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::device_monitor::{is_emulator_serial, ExitKind, Transport, RESTART_LIMIT_PER_HOUR};
+use crate::form_factor::FormFactor;
+use crate::hooks::HookEvent;
+use crate::log_sink::LogSinkConfig;
+use crate::migration::{add_version, Schema, VERSION_KEY};
+use crate::polling::PollingConfig;
+use crate::port_forward::ForwardRule;
+use crate::scrcpy_import::{group_options, option_name};
+use crate::session::{AudioConfig, PresentationConfig, RecordingConfig, SessionKind, VirtualDisplayConfig};
+use crate::window_layout::DeviceGroup;
+
+/// config.toml 的格式版本与迁移步骤，修改已有字段的含义或位置时在末尾追加迁移
+pub const SCHEMA: Schema = Schema { name: "配置文件", migrations: &[add_version] };
+
+/// 启动器配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 只对这些序列号自动启动 scrcpy（为空表示不限制）
+    pub allowlist: Vec<String>,
+    /// 不自动启动 scrcpy 的序列号，优先级高于 allowlist
+    pub blocklist: Vec<String>,
+    /// 不对模拟器（emulator-5554 等）自动启动 scrcpy
+    pub skip_emulators: bool,
+    /// 同时连接多台设备时按此顺序选择投屏设备，未列出的设备排在最后
+    pub priority: Vec<String>,
+    /// 启动 scrcpy 时附带 --print-fps 并在界面上显示帧率统计
+    pub show_fps: bool,
+    /// 默认会话类型：mirror / record / audio_only / otg_control / camera / virtual_display
+    pub session: SessionKind,
+    /// scrcpy 退出后的重启策略：always / on_crash / never
+    pub restart_policy: RestartPolicy,
+    /// 每台设备每小时最多自动重启的次数，超过后停止重试（不填时为 10，设为 0 表示不限制）
+    pub max_restarts_per_hour: Option<u32>,
+    /// 正在投屏的设备从 adb 设备列表中消失后，保留 scrcpy 等待同一设备重新出现的秒数（0 表示立即结束）
+    pub disconnect_grace_secs: u64,
+    /// 启动时发现上次遗留的 scrcpy 进程如何处理：ask / adopt / terminate
+    pub stray_scrcpy: StrayAction,
+    /// 每次启动 scrcpy 时附加的参数，例如 ["--max-size", "1280", "--video-bit-rate=4M"]
+    pub scrcpy_args: Vec<String>,
+    /// 启动时自动恢复上次退出时仍在进行的会话，关闭时只在日志中提示，按 r 恢复
+    pub resume_session: bool,
+    /// 检测到 scrcpy 被更新后自动重启正在进行的会话（录屏中则等录屏结束），关闭时只提示，按 u 重启
+    pub restart_on_update: bool,
+    /// 未单独指定配置档的设备使用的启动配置档
+    pub default_profile: Option<String>,
+    /// 按名称区分的启动配置档，例如 "低延迟"、"录屏"、"演示"
+    pub profiles: BTreeMap<String, LaunchProfile>,
+    /// 按连接方式（USB / 无线 / 远程 adb server）区分的默认设置，优先级低于设备配置
+    pub transports: TransportDefaults,
+    /// 启动 scrcpy 前唤醒设备屏幕，避免休眠中的设备投屏后黑屏
+    pub wake_device: bool,
+    /// 投屏时关闭设备屏幕（scrcpy --turn-screen-off），画面仍正常镜像
+    pub turn_screen_off: bool,
+    /// 投屏期间保持设备唤醒（scrcpy --stay-awake，仅在设备充电时生效）
+    pub stay_awake: bool,
+    /// 投屏开始时在设备上打开的应用包名，如 "com.example.kiosk"（scrcpy --start-app，旧版本改用 adb shell monkey）
+    pub start_app: Option<String>,
+    /// 设备连接时执行的命令，序列号通过 SCRCPY_SERIAL 环境变量传入
+    pub on_connect: Option<String>,
+    /// 设备断开时执行的命令
+    pub on_disconnect: Option<String>,
+    /// 自定义 adb 环境
+    pub adb: AdbConfig,
+    /// 监控循环的轮询间隔与低功耗模式
+    pub polling: PollingConfig,
+    /// 录像保存目录与文件名模板
+    pub recording: RecordingConfig,
+    /// 音频转发开关、编码与来源
+    pub audio: AudioConfig,
+    /// 虚拟显示器会话的分辨率与启动的应用
+    pub virtual_display: VirtualDisplayConfig,
+    /// 演示模式的窗口大小（按 d 开关）
+    pub presentation: PresentationConfig,
+    /// 对所有设备建立的端口转发
+    pub forwards: Vec<ForwardRule>,
+    /// 按序列号区分的设备配置
+    pub devices: BTreeMap<String, DeviceConfig>,
+    /// 按组名区分的设备组，为组内设备指定窗口所在显示器与位置
+    pub groups: BTreeMap<String, DeviceGroup>,
+    /// 界面之外同时写入的日志输出（文件、syslog）
+    pub log_sinks: Vec<LogSinkConfig>,
+}
+
+/// adb 相关配置，作用于每一次 adb / scrcpy 调用
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdbConfig {
+    /// 非默认的 adb server 端口（adb -P），同时通过 ANDROID_ADB_SERVER_PORT 传给 scrcpy
+    pub server_port: Option<u16>,
+    /// 额外的环境变量，例如 ADB_VENDOR_KEYS
+    pub env: BTreeMap<String, String>,
+    /// 远程 adb server（host:port，端口默认 5037），与本机 server 同时查询设备
+    pub remote_servers: Vec<String>,
+}
+
+impl AdbConfig {
+    /// 传给 adb / scrcpy 进程的环境变量
+    pub fn envs(&self) -> Vec<(String, String)> {
+        let mut envs: Vec<(String, String)> = self.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        if let Some(port) = self.server_port {
+            envs.push(("ANDROID_ADB_SERVER_PORT".to_string(), port.to_string()));
+        }
+        envs
+    }
+}
+
+/// 一组命名的 scrcpy 参数，在 scrcpy_args 之后追加
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchProfile {
+    pub args: Vec<String>,
+}
+
+/// 按连接方式区分的默认设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportDefaults {
+    /// USB 连接的设备与模拟器
+    pub usb: TransportProfile,
+    /// adb connect 或 mDNS 连接的无线设备
+    pub wireless: TransportProfile,
+    /// 通过远程 adb server 访问的设备，不论其在远程主机上如何连接
+    pub remote: TransportProfile,
+}
+
+/// 某种连接方式的默认设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportProfile {
+    /// 未单独指定配置档的设备使用的配置档，优先于 default_profile
+    pub profile: Option<String>,
+    /// 在 scrcpy_args 之后、配置档参数之前附加的参数，例如较低的码率与帧率
+    pub args: Vec<String>,
+}
+
+/// 单个设备的配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// 指定视频编码器（名称见 scrcpy --list-encoders），部分三星/联发科设备默认编码器会花屏
+    pub video_encoder: Option<String>,
+    /// 覆盖默认会话类型
+    pub session: Option<SessionKind>,
+    /// 覆盖默认的启动配置档
+    pub profile: Option<String>,
+    /// 设备昵称，显示在设备列表中
+    pub nickname: Option<String>,
+    /// 覆盖全局的连接钩子命令
+    pub on_connect: Option<String>,
+    /// 覆盖全局的断开钩子命令
+    pub on_disconnect: Option<String>,
+    /// 该设备额外的端口转发
+    pub forwards: Vec<ForwardRule>,
+    /// 覆盖全局的唤醒设置
+    pub wake_device: Option<bool>,
+    /// 唤醒后执行的解锁命令（adb shell 中执行），例如滑动解锁并输入 PIN
+    pub unlock_command: Option<String>,
+    /// 覆盖全局的虚拟显示器设置
+    pub virtual_display: Option<VirtualDisplayConfig>,
+    /// 覆盖自动判断的设备形态（phone / tv / watch），决定默认附加的 scrcpy 参数
+    pub form_factor: Option<FormFactor>,
+    /// 覆盖全局的关闭屏幕设置
+    pub turn_screen_off: Option<bool>,
+    /// 覆盖全局的保持唤醒设置
+    pub stay_awake: Option<bool>,
+    /// 覆盖全局的启动应用，设为空字符串表示该设备不启动应用
+    pub start_app: Option<String>,
+}
+
+/// scrcpy 退出后的自动重启策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// 无论何种原因退出都重启
+    Always,
+    /// 用户关闭窗口时不重启，异常退出或连接断开时重启
+    #[default]
+    OnCrash,
+    /// 从不自动重启
+    Never,
+}
+
+/// 启动时发现上次启动器异常退出遗留的 scrcpy 进程时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrayAction {
+    /// 在日志中提示，按 K 结束；结束或它们自行退出前不自动投屏
+    #[default]
+    Ask,
+    /// 继续沿用，它们退出前不自动投屏
+    Adopt,
+    /// 直接结束
+    Terminate,
+}
+
+impl RestartPolicy {
+    /// 按退出原因判断是否需要自动重启
+    pub fn should_restart(self, exit: ExitKind) -> bool {
+        match self {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnCrash => exit != ExitKind::UserClosed,
+            RestartPolicy::Never => false,
+        }
+    }
+}
+
+impl Config {
+    /// 配置文件路径：%APPDATA%\scrcpy-launcher\config.toml
+    pub fn path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+            .join("scrcpy-launcher")
+            .join("config.toml")
+    }
+
+    /// 读取配置，文件不存在时返回默认配置
+    /// 旧格式的文件会先升级并写回，同时返回原文件的备份位置
+    pub fn load() -> Result<(Self, Option<PathBuf>), String> {
+        match SCHEMA.load(&Self::path())? {
+            Some(loaded) => Ok((Self::parse(&loaded.content)?, loaded.backup)),
+            None => Ok((Self::default(), None)),
+        }
+    }
+
+    /// 解析配置文件内容
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("配置文件格式错误: {}", e))
+    }
+
+    /// 获取设备配置
+    pub fn device(&self, serial: &str) -> Option<&DeviceConfig> {
+        self.devices.get(serial)
+    }
+
+    /// 设置设备昵称并写回配置文件，None 表示清除
+    pub fn set_nickname(&mut self, serial: &str, nickname: Option<String>) -> Result<(), String> {
+        save_device_value(&Self::path(), serial, "nickname", nickname.as_deref())?;
+        self.devices.entry(serial.to_string()).or_default().nickname = nickname;
+        Ok(())
+    }
+
+    /// 把从 scrcpy 脚本与快捷方式导入的参数追加到 scrcpy_args，环境变量写入 [adb.env]，已有的项不重复添加
+    pub fn import_scrcpy_settings(args: &[String], env: &[(String, String)]) -> Result<(), String> {
+        save_imported(&Self::path(), args, env)
+    }
+
+    /// 设备昵称
+    pub fn nickname(&self, serial: &str) -> Option<&str> {
+        self.device(serial).and_then(|d| d.nickname.as_deref())
+    }
+
+    /// 设备使用的会话类型
+    pub fn session_kind(&self, serial: &str) -> SessionKind {
+        self.device(serial)
+            .and_then(|d| d.session)
+            .unwrap_or(self.session)
+    }
+
+    /// 设备事件对应的钩子命令，设备单独配置的优先于全局配置
+    pub fn hook(&self, serial: &str, event: HookEvent) -> Option<&str> {
+        let device = self.device(serial);
+        let command = match event {
+            HookEvent::Connect => device.and_then(|d| d.on_connect.as_ref()).or(self.on_connect.as_ref()),
+            HookEvent::Disconnect => device.and_then(|d| d.on_disconnect.as_ref()).or(self.on_disconnect.as_ref()),
+        };
+        command.map(String::as_str).filter(|c| !c.trim().is_empty())
+    }
+
+    /// 设备所用连接方式的默认设置，remote 表示设备来自远程 adb server
+    pub fn transport(&self, serial: &str, remote: bool) -> &TransportProfile {
+        if remote {
+            return &self.transports.remote;
+        }
+        match Transport::from_serial(serial) {
+            Transport::Tcp | Transport::Mdns => &self.transports.wireless,
+            Transport::Usb | Transport::Emulator => &self.transports.usb,
+        }
+    }
+
+    /// 设备使用的启动配置档名称：设备配置优先，其次是连接方式的默认配置档，最后是 default_profile
+    pub fn profile_name(&self, serial: &str, remote: bool) -> Option<&str> {
+        self.device(serial)
+            .and_then(|d| d.profile.as_deref())
+            .or(self.transport(serial, remote).profile.as_deref())
+            .or(self.default_profile.as_deref())
+    }
+
+    /// 按名称排序的下一个配置档，最后一个之后回到不使用配置档
+    pub fn next_profile(&self, current: Option<&str>) -> Option<String> {
+        let mut names = self.profiles.keys();
+        match current {
+            None => names.next().cloned(),
+            Some(current) => names.skip_while(|name| name.as_str() != current).nth(1).cloned(),
+        }
+    }
+
+    /// 每台设备每小时自动重启次数的上限，不限制时返回 None
+    pub fn restart_limit(&self) -> Option<u32> {
+        Some(self.max_restarts_per_hour.unwrap_or(RESTART_LIMIT_PER_HOUR)).filter(|&limit| limit > 0)
+    }
+
+    /// 启动 scrcpy 前是否唤醒设备
+    pub fn wake_device(&self, serial: &str) -> bool {
+        self.device(serial)
+            .and_then(|d| d.wake_device)
+            .unwrap_or(self.wake_device)
+    }
+
+    /// 设备的解锁命令，只在启用唤醒时执行
+    pub fn unlock_command(&self, serial: &str) -> Option<&str> {
+        self.device(serial)
+            .and_then(|d| d.unlock_command.as_deref())
+            .filter(|c| !c.trim().is_empty() && self.wake_device(serial))
+    }
+
+    /// 设备的虚拟显示器设置，设备单独配置的优先于全局配置
+    pub fn virtual_display(&self, serial: &str) -> &VirtualDisplayConfig {
+        self.device(serial)
+            .and_then(|d| d.virtual_display.as_ref())
+            .unwrap_or(&self.virtual_display)
+    }
+
+    /// 设备屏幕相关的 scrcpy 参数（关闭屏幕、保持唤醒），设备单独配置的优先于全局配置
+    pub fn screen_args(&self, serial: &str) -> Vec<String> {
+        let device = self.device(serial);
+        let mut args = Vec::new();
+        if device.and_then(|d| d.turn_screen_off).unwrap_or(self.turn_screen_off) {
+            args.push("--turn-screen-off".to_string());
+        }
+        if device.and_then(|d| d.stay_awake).unwrap_or(self.stay_awake) {
+            args.push("--stay-awake".to_string());
+        }
+        args
+    }
+
+    /// 设备形态：配置文件中指定的优先，其次按 ro.build.characteristics 判断的结果
+    pub fn form_factor(&self, serial: &str, detected: Option<FormFactor>) -> FormFactor {
+        self.device(serial).and_then(|d| d.form_factor).or(detected).unwrap_or_default()
+    }
+
+    /// 投屏开始时在设备上打开的应用，设备单独配置的优先于全局配置
+    pub fn start_app(&self, serial: &str) -> Option<&str> {
+        self.device(serial)
+            .and_then(|d| d.start_app.as_deref())
+            .or(self.start_app.as_deref())
+            .map(str::trim)
+            .filter(|app| !app.is_empty())
+    }
+
+    /// 设备上线时需要建立的端口转发：全局规则在前，设备规则在后
+    pub fn forwards(&self, serial: &str) -> Vec<ForwardRule> {
+        self.forwards
+            .iter()
+            .chain(self.device(serial).map(|d| d.forwards.as_slice()).unwrap_or_default())
+            .cloned()
+            .collect()
+    }
+
+    /// 设备所属的组：(组名, 组配置, 设备在组内的序号)，同时属于多个组时取组名排序最前的
+    pub fn group(&self, serial: &str) -> Option<(&str, &DeviceGroup, usize)> {
+        self.groups.iter().find_map(|(name, group)| {
+            let index = group.devices.iter().position(|s| s == serial)?;
+            Some((name.as_str(), group, index))
+        })
+    }
+
+    /// 设备在优先级列表中的位置，越小越优先，未列出的设备返回 usize::MAX
+    pub fn priority_rank(&self, serial: &str) -> usize {
+        self.priority
+            .iter()
+            .position(|s| s == serial)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// 该设备是否允许自动启动 scrcpy
+    pub fn is_device_allowed(&self, serial: &str) -> bool {
+        if self.blocklist.iter().any(|s| s == serial) {
+            return false;
+        }
+        if self.skip_emulators && is_emulator_serial(serial) {
+            return false;
+        }
+        self.allowlist.is_empty() || self.allowlist.iter().any(|s| s == serial)
+    }
+}
+
+/// 修改配置文件中某台设备的单个字段，保留用户原有的注释与格式
+fn save_device_value(path: &Path, serial: &str, key: &str, value: Option<&str>) -> Result<(), String> {
+    use toml_edit::{table, value as toml_value, DocumentMut};
+
+    let content = if path.exists() {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("读取配置文件失败 {}: {}", path.display(), e))?
+    } else {
+        String::new()
+    };
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("配置文件格式错误: {}", e))?;
+    // 新建的配置文件直接使用当前格式
+    if content.trim().is_empty() {
+        doc[VERSION_KEY] = toml_value(i64::from(SCHEMA.version()));
+    }
+
+    let devices = doc.entry("devices").or_insert_with(table);
+    let devices = devices
+        .as_table_mut()
+        .ok_or_else(|| "配置文件中的 devices 不是表".to_string())?;
+    // 只有 [devices.xxx] 子表，不单独输出空的 [devices]
+    devices.set_implicit(true);
+
+    let device = devices
+        .entry(serial)
+        .or_insert_with(table)
+        .as_table_mut()
+        .ok_or_else(|| format!("配置文件中的 devices.\"{}\" 不是表", serial))?;
+    match value {
+        Some(value) => device[key] = toml_value(value),
+        None => {
+            device.remove(key);
+        }
+    }
+    if device.is_empty() {
+        devices.remove(serial);
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+    }
+    std::fs::write(path, doc.to_string()).map_err(|e| format!("保存配置文件失败: {}", e))
+}
+
+/// 写入导入的 scrcpy 参数与环境变量，保留用户原有的注释与格式
+fn save_imported(path: &Path, args: &[String], env: &[(String, String)]) -> Result<(), String> {
+    use toml_edit::{table, value as toml_value, Array, DocumentMut};
+
+    let content = if path.exists() {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("读取配置文件失败 {}: {}", path.display(), e))?
+    } else {
+        String::new()
+    };
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("配置文件格式错误: {}", e))?;
+    if content.trim().is_empty() {
+        doc[VERSION_KEY] = toml_value(i64::from(SCHEMA.version()));
+    }
+
+    if !args.is_empty() {
+        let mut merged = match doc.get("scrcpy_args") {
+            None => Array::new(),
+            Some(item) => item
+                .as_array()
+                .cloned()
+                .ok_or_else(|| "配置文件中的 scrcpy_args 不是数组".to_string())?,
+        };
+        // 按选项去重，配置中已有的选项保持原值，避免留下 "--max-size 1280 1024" 这样多出来的值
+        let existing: Vec<String> = merged.iter().filter_map(|v| v.as_str().map(str::to_string)).collect();
+        let existing = group_options(&existing);
+        for group in group_options(args) {
+            if !existing.iter().any(|e| option_name(&e[0]) == option_name(&group[0])) {
+                for arg in &group {
+                    merged.push(arg.as_str());
+                }
+            }
+        }
+        doc["scrcpy_args"] = toml_value(merged);
+    }
+
+    if !env.is_empty() {
+        let adb = doc
+            .entry("adb")
+            .or_insert_with(table)
+            .as_table_mut()
+            .ok_or_else(|| "配置文件中的 adb 不是表".to_string())?;
+        let vars = adb
+            .entry("env")
+            .or_insert_with(table)
+            .as_table_mut()
+            .ok_or_else(|| "配置文件中的 adb.env 不是表".to_string())?;
+        for (name, value) in env {
+            if !vars.contains_key(name) {
+                vars[name.as_str()] = toml_value(value.as_str());
+            }
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+    }
+    std::fs::write(path, doc.to_string()).map_err(|e| format!("保存配置文件失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::port_forward::ForwardDirection;
+
+    #[test]
+    fn empty_config_allows_everything() {
+        let config = Config::parse("").unwrap();
+        assert!(config.is_device_allowed("R58M123456"));
+    }
+
+    #[test]
+    fn blocklist_wins_over_allowlist() {
+        let config = Config::parse(
+            r#"
+            allowlist = ["R58M123456", "emulator-5554"]
+            blocklist = ["emulator-5554"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.is_device_allowed("R58M123456"));
+        assert!(!config.is_device_allowed("emulator-5554"));
+        assert!(!config.is_device_allowed("192.168.1.20:5555"));
+    }
+
+    #[test]
+    fn skips_emulators_when_configured() {
+        assert!(Config::parse("").unwrap().is_device_allowed("emulator-5554"));
+        let config = Config::parse("skip_emulators = true").unwrap();
+        assert!(!config.is_device_allowed("emulator-5554"));
+        assert!(config.is_device_allowed("R58M123456"));
+    }
+
+    #[test]
+    fn ranks_devices_by_priority() {
+        let config = Config::parse(r#"priority = ["192.168.1.5:5555", "R58M123456"]"#).unwrap();
+        assert_eq!(config.priority_rank("192.168.1.5:5555"), 0);
+        assert_eq!(config.priority_rank("R58M123456"), 1);
+        assert_eq!(config.priority_rank("emulator-5554"), usize::MAX);
+    }
+
+    #[test]
+    fn parses_per_device_settings() {
+        let config = Config::parse(
+            r#"
+            session = "audio_only"
+
+            [devices."192.168.1.5:5555"]
+            video_encoder = "c2.android.avc.encoder"
+            session = "record"
+            "#,
+        )
+        .unwrap();
+        let device = config.device("192.168.1.5:5555").unwrap();
+        assert_eq!(device.video_encoder.as_deref(), Some("c2.android.avc.encoder"));
+        assert!(config.device("R58M123456").is_none());
+        assert_eq!(config.session_kind("192.168.1.5:5555"), SessionKind::Record);
+        assert_eq!(config.session_kind("R58M123456"), SessionKind::AudioOnly);
+    }
+
+    #[test]
+    fn device_hooks_override_global_hooks() {
+        let config = Config::parse(
+            r#"
+            on_connect = "echo connected"
+            on_disconnect = "echo disconnected"
+
+            [devices."R58M123456"]
+            on_connect = "set-volume.bat"
+            on_disconnect = ""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.hook("R58M123456", HookEvent::Connect), Some("set-volume.bat"));
+        assert_eq!(config.hook("R58M123456", HookEvent::Disconnect), None);
+        assert_eq!(config.hook("emulator-5554", HookEvent::Disconnect), Some("echo disconnected"));
+    }
+
+    #[test]
+    fn saves_device_value_keeping_comments() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-test-{}.toml", std::process::id()));
+        std::fs::write(&path, "# 我的配置\nshow_fps = true\n").unwrap();
+
+        save_device_value(&path, "192.168.1.5:5555", "nickname", Some("Pro工作机")).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# 我的配置\n"));
+        let config = Config::parse(&content).unwrap();
+        assert!(config.show_fps);
+        assert_eq!(config.nickname("192.168.1.5:5555"), Some("Pro工作机"));
+
+        save_device_value(&path, "192.168.1.5:5555", "nickname", None).unwrap();
+        let config = Config::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(config.device("192.168.1.5:5555").is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn imports_scrcpy_settings() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-import-{}.toml", std::process::id()));
+        std::fs::write(&path, "# 我的配置\nscrcpy_args = [\"--max-size=1280\"]\n").unwrap();
+
+        // 已有的选项保留配置中的值
+        let args = ["--max-size=1024", "--turn-screen-off"].map(String::from);
+        let env = [("SCRCPY_SERVER_PATH".to_string(), "D:\\scrcpy-server".to_string())];
+        save_imported(&path, &args, &env).unwrap();
+        save_imported(&path, &args, &env).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(content.starts_with("# 我的配置\n"));
+        let config = Config::parse(&content).unwrap();
+        assert_eq!(config.scrcpy_args, ["--max-size=1280", "--turn-screen-off"]);
+        assert_eq!(config.adb.env["SCRCPY_SERVER_PATH"], "D:\\scrcpy-server");
+    }
+
+    #[test]
+    fn restart_policy_follows_exit_kind() {
+        assert_eq!(Config::parse("").unwrap().restart_policy, RestartPolicy::OnCrash);
+        let config = Config::parse(r#"restart_policy = "always""#).unwrap();
+        assert!(config.restart_policy.should_restart(ExitKind::UserClosed));
+        assert!(!RestartPolicy::OnCrash.should_restart(ExitKind::UserClosed));
+        assert!(RestartPolicy::OnCrash.should_restart(ExitKind::Disconnected));
+        assert!(!RestartPolicy::Never.should_restart(ExitKind::Crashed));
+        assert_eq!(config.stray_scrcpy, StrayAction::Ask);
+        assert_eq!(Config::parse(r#"stray_scrcpy = "terminate""#).unwrap().stray_scrcpy, StrayAction::Terminate);
+
+        assert_eq!(Config::parse("").unwrap().restart_limit(), Some(RESTART_LIMIT_PER_HOUR));
+        assert_eq!(Config::parse("max_restarts_per_hour = 3").unwrap().restart_limit(), Some(3));
+        assert_eq!(Config::parse("max_restarts_per_hour = 0").unwrap().restart_limit(), None);
+    }
+
+    #[test]
+    fn parses_adb_environment() {
+        let config = Config::parse(
+            r#"
+            [adb]
+            server_port = 5038
+
+            [adb.env]
+            ADB_VENDOR_KEYS = 'C:\keys\adbkey'
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.adb.server_port, Some(5038));
+        assert_eq!(config.adb.envs(), vec![
+            ("ADB_VENDOR_KEYS".to_string(), "C:\\keys\\adbkey".to_string()),
+            ("ANDROID_ADB_SERVER_PORT".to_string(), "5038".to_string()),
+        ]);
+        assert!(Config::parse("").unwrap().adb.envs().is_empty());
+    }
+
+    #[test]
+    fn resolves_wake_settings() {
+        let config = Config::parse(
+            r#"
+            wake_device = true
+
+            [devices."R58M123456"]
+            unlock_command = "input text 1234 && input keyevent 66"
+
+            [devices."emulator-5554"]
+            wake_device = false
+            unlock_command = "input text 0000"
+            "#,
+        )
+        .unwrap();
+        assert!(config.wake_device("192.168.1.5:5555"));
+        assert_eq!(config.unlock_command("R58M123456"), Some("input text 1234 && input keyevent 66"));
+        assert!(!config.wake_device("emulator-5554"));
+        assert_eq!(config.unlock_command("emulator-5554"), None);
+    }
+
+    #[test]
+    fn resolves_screen_options() {
+        let config = Config::parse(
+            r#"
+            turn_screen_off = true
+            start_app = "com.example.kiosk"
+
+            [devices."R58M123456"]
+            stay_awake = true
+            start_app = "org.mozilla.firefox"
+
+            [devices."emulator-5554"]
+            turn_screen_off = false
+            start_app = ""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.screen_args("R58M123456"), ["--turn-screen-off", "--stay-awake"]);
+        assert_eq!(config.screen_args("192.168.1.5:5555"), ["--turn-screen-off"]);
+        assert!(config.screen_args("emulator-5554").is_empty());
+        assert_eq!(config.start_app("R58M123456"), Some("org.mozilla.firefox"));
+        assert_eq!(config.start_app("192.168.1.5:5555"), Some("com.example.kiosk"));
+        assert_eq!(config.start_app("emulator-5554"), None);
+    }
+
+    #[test]
+    fn applies_transport_defaults() {
+        let config = Config::parse(
+            r#"
+            default_profile = "演示"
+
+            [transports.wireless]
+            profile = "低延迟"
+            args = ["--video-bit-rate=4M", "--max-fps=30"]
+
+            [transports.remote]
+            args = ["--video-bit-rate=2M"]
+
+            [devices."192.168.1.5:5555"]
+            profile = "演示"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.transport("192.168.1.6:5555", false).args, ["--video-bit-rate=4M", "--max-fps=30"]);
+        assert_eq!(config.transport("192.168.1.6:5555", true).args, ["--video-bit-rate=2M"]);
+        assert!(config.transport("R58M123456", false).args.is_empty());
+        assert_eq!(config.profile_name("192.168.1.6:5555", false), Some("低延迟"));
+        // 设备配置优先于连接方式的默认设置
+        assert_eq!(config.profile_name("192.168.1.5:5555", false), Some("演示"));
+        assert_eq!(config.profile_name("R58M123456", true), Some("演示"));
+    }
+
+    #[test]
+    fn resolves_and_cycles_profiles() {
+        let config = Config::parse(
+            r#"
+            default_profile = "演示"
+
+            [profiles."低延迟"]
+            args = ["--max-size=1024", "--video-bit-rate=2M"]
+
+            [profiles."演示"]
+            args = ["--stay-awake"]
+
+            [devices."R58M123456"]
+            profile = "低延迟"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profile_name("R58M123456", false), Some("低延迟"));
+        assert_eq!(config.profile_name("emulator-5554", false), Some("演示"));
+        assert_eq!(config.profiles["低延迟"].args.len(), 2);
+
+        assert_eq!(config.next_profile(None).as_deref(), Some("低延迟"));
+        assert_eq!(config.next_profile(Some("低延迟")).as_deref(), Some("演示"));
+        assert_eq!(config.next_profile(Some("演示")), None);
+        // 配置文件中已删除的配置档回到不使用配置档
+        assert_eq!(config.next_profile(Some("录屏")), None);
+    }
+
+    #[test]
+    fn finds_device_group() {
+        let config = Config::parse(
+            r#"
+            [groups.bench]
+            devices = ["R58M123456", "R58M654321"]
+            monitor = 2
+            width = 400
+            "#,
+        )
+        .unwrap();
+        let (name, group, index) = config.group("R58M654321").unwrap();
+        assert_eq!((name, index), ("bench", 1));
+        assert_eq!(group.monitor, Some(2));
+        assert_eq!(group.height, None);
+        assert!(config.group("emulator-5554").is_none());
+    }
+
+    #[test]
+    fn combines_global_and_device_forwards() {
+        let config = Config::parse(
+            r#"
+            [[forwards]]
+            local = "tcp:9222"
+            remote = "localabstract:chrome_devtools_remote"
+
+            [[devices."R58M123456".forwards]]
+            direction = "reverse"
+            local = "tcp:3000"
+            remote = "tcp:3000"
+            "#,
+        )
+        .unwrap();
+        let forwards = config.forwards("R58M123456");
+        assert_eq!(forwards.len(), 2);
+        assert_eq!(forwards[0].direction, ForwardDirection::Forward);
+        assert_eq!(forwards[1].direction, ForwardDirection::Reverse);
+        assert_eq!(config.forwards("emulator-5554").len(), 1);
+    }
+
+    #[test]
+    fn reports_invalid_config() {
+        assert!(Config::parse("allowlist = 1").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! 控制台模式
+//! TUI 无法初始化（没有 TTY、终端不受支持等）或输出被重定向时逐行输出日志，并从标准输入读取简单命令
+
+use std::io::IsTerminal;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+use crate::log_sink::{LogSink, LogSinks};
+use crate::tui::{get_timestamp, parse_pairing_input, LogLevel, StepProgress, UserAction};
+use crate::TuiMessage;
+
+/// 控制台命令说明
+pub const HELP: &str = "可用命令: w 无线切换 | f 帧率统计 | a <IP:配对端口> <配对码> [连接端口] 无线配对 | c 连接 WSA/Waydroid | s <序列号> 切换投屏设备 | o <序列号> 切换配置档 | t <序列号> 切换会话类型 | v 开始/停止录屏 | m 音频转发 | d 演示模式 | p 暂停/恢复自动投屏 | k 结束遗留的 scrcpy 进程 | r 恢复上次会话 | u 切换到更新后的 scrcpy | q 退出";
+
+/// 从标准输入解析出的命令
+#[derive(Debug)]
+pub enum ConsoleCommand {
+    Action(UserAction),
+    Help,
+    Quit,
+}
+
+/// 控制台输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// 交互式终端：带图标的日志
+    Rich,
+    /// 输出被管道或文件接收：不含 emoji 的纯文本日志
+    Plain,
+    /// 每行一个 JSON 对象，便于脚本解析
+    Json,
+}
+
+impl OutputStyle {
+    /// 根据命令行参数与标准输出是否为终端选择输出格式
+    pub fn detect(json: bool) -> Self {
+        if json {
+            OutputStyle::Json
+        } else if std::io::stdout().is_terminal() {
+            OutputStyle::Rich
+        } else {
+            OutputStyle::Plain
+        }
+    }
+
+    /// 格式化一条事件，kind 为 info / warning / status / devices 等
+    fn format(self, kind: &str, icon: &str, message: &str) -> String {
+        let time = get_timestamp();
+        match self {
+            OutputStyle::Rich => format!("[{}] {} {}", time, icon, message),
+            OutputStyle::Plain => format!("[{}] {:<7} {}", time, kind.to_uppercase(), message),
+            OutputStyle::Json => format!(
+                r#"{{"time":"{}","kind":"{}","message":"{}"}}"#,
+                time,
+                kind,
+                escape_json(message)
+            ),
+        }
+    }
+}
+
+/// 转义 JSON 字符串中的特殊字符
+pub fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 输出一条日志
+pub fn print_log(style: OutputStyle, level: LogLevel, message: &str) {
+    println!("{}", style.format(level.name(), level.icon(), message));
+}
+
+/// 控制台作为日志输出
+struct ConsoleSink(OutputStyle);
+
+impl LogSink for ConsoleSink {
+    fn write(&mut self, level: LogLevel, message: &str) -> bool {
+        print_log(self.0, level, message);
+        true
+    }
+}
+
+/// 逐行输出业务逻辑发来的消息，直到收到退出消息
+/// 日志同时写入配置的附加输出
+pub async fn print_messages(mut rx: mpsc::Receiver<TuiMessage>, style: OutputStyle, mut sinks: LogSinks) {
+    let mut console = ConsoleSink(style);
+    let mut last_devices = String::new();
+
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            TuiMessage::Log(level, message) => sinks.publish(&mut console, level, &message),
+            TuiMessage::Device(event) => {
+                // 设备事件在控制台中以事件类型而不是日志级别标注
+                println!("{}", style.format(event.kind(), event.level().icon(), &event.message()));
+                sinks.write(event.level(), &event.message());
+                if let Some(detail) = event.detail() {
+                    sinks.publish(&mut console, LogLevel::Info, &detail);
+                }
+            }
+            TuiMessage::Status(status) => println!("{}", style.format("status", "📋", &status)),
+            TuiMessage::UpdateDevices(devices) => {
+                // 设备列表会定期重发，只在内容变化时输出
+                let summary = if devices.is_empty() {
+                    "暂无设备连接".to_string()
+                } else {
+                    devices
+                        .iter()
+                        .map(|d| format!("{} [{}] ({})", d.display_name(), d.id, d.status.label()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                if summary != last_devices {
+                    println!("{}", style.format("devices", "📱", &summary));
+                    last_devices = summary;
+                }
+            }
+            TuiMessage::Session(Some((serial, kind))) => {
+                println!("{}", style.format("session", kind.icon(), &format!("{} {}", kind.label(), serial)));
+            }
+            // 启动步骤只输出失败项，其余已由日志说明
+            TuiMessage::Startup(step, StepProgress::Failed(e)) => {
+                sinks.publish(&mut console, LogLevel::Error, &format!("{}失败: {}", step.label(), e));
+            }
+            // 帧率每秒刷新一次、历史记录、端口转发列表、启动窗口与扫码配对弹窗只供界面展示，控制台模式下不输出
+            TuiMessage::Fps(_)
+            | TuiMessage::Session(None)
+            | TuiMessage::History(_)
+            | TuiMessage::Forwards(_)
+            | TuiMessage::Startup(..)
+            | TuiMessage::QrPairingFinished => {}
+            TuiMessage::Quit => break,
+        }
+    }
+}
+
+/// 从标准输入读取命令并转交业务逻辑，输入 q 或标准输入关闭时返回
+pub async fn read_commands(actions: mpsc::Sender<UserAction>, style: OutputStyle) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        match parse_command(&line) {
+            Ok(None) => {}
+            Ok(Some(ConsoleCommand::Action(action))) => {
+                let _ = actions.send(action).await;
+            }
+            Ok(Some(ConsoleCommand::Help)) => eprintln!("{}", HELP),
+            Ok(Some(ConsoleCommand::Quit)) => return,
+            Err(e) => print_log(style, LogLevel::Error, &format!("{}（输入 h 查看可用命令）", e)),
+        }
+    }
+
+    // 标准输入关闭（例如被重定向）时不退出，继续监控设备
+    std::future::pending::<()>().await;
+}
+
+/// 解析一行控制台命令，空行返回 None
+pub fn parse_command(line: &str) -> Result<Option<ConsoleCommand>, String> {
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(None);
+    };
+    let args: Vec<&str> = words.collect();
+
+    let command = match (command, args.as_slice()) {
+        ("w", []) => ConsoleCommand::Action(UserAction::ToggleWireless),
+        ("f", []) => ConsoleCommand::Action(UserAction::ToggleFps),
+        ("a", [address, code]) => ConsoleCommand::Action(parse_pairing_input(address, code, "")?),
+        ("a", [address, code, port]) => ConsoleCommand::Action(parse_pairing_input(address, code, port)?),
+        ("a", _) => return Err("用法: a <IP:配对端口> <配对码> [连接端口]".to_string()),
+        ("c", []) => ConsoleCommand::Action(UserAction::ConnectLocalTargets),
+        ("s", [serial]) => ConsoleCommand::Action(UserAction::SelectDevice(serial.to_string())),
+        ("s", _) => return Err("用法: s <序列号>".to_string()),
+        ("o", [serial]) => ConsoleCommand::Action(UserAction::CycleProfile(serial.to_string())),
+        ("o", _) => return Err("用法: o <序列号>".to_string()),
+        ("t", [serial]) => ConsoleCommand::Action(UserAction::CycleSession(serial.to_string())),
+        ("t", _) => return Err("用法: t <序列号>".to_string()),
+        ("p", []) => ConsoleCommand::Action(UserAction::ToggleAutoLaunch),
+        ("k", []) => ConsoleCommand::Action(UserAction::EndStrayScrcpy),
+        ("r", []) => ConsoleCommand::Action(UserAction::ResumeSession),
+        ("v", []) => ConsoleCommand::Action(UserAction::ToggleRecording),
+        ("m", []) => ConsoleCommand::Action(UserAction::ToggleAudio),
+        ("d", []) => ConsoleCommand::Action(UserAction::TogglePresentation),
+        ("u", []) => ConsoleCommand::Action(UserAction::ApplyUpdate),
+        ("h" | "help" | "?", _) => ConsoleCommand::Help,
+        ("q" | "quit" | "exit", _) => ConsoleCommand::Quit,
+        (other, _) => return Err(format!("未知命令: {}", other)),
+    };
+    Ok(Some(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_console_commands() {
+        assert!(parse_command("  ").unwrap().is_none());
+        assert!(matches!(
+            parse_command("w").unwrap(),
+            Some(ConsoleCommand::Action(UserAction::ToggleWireless))
+        ));
+        assert!(matches!(
+            parse_command("a 192.168.1.5:37123 123456 41234").unwrap(),
+            Some(ConsoleCommand::Action(UserAction::Pair { connect_address: Some(_), .. }))
+        ));
+        assert!(matches!(
+            parse_command("s emulator-5554").unwrap(),
+            Some(ConsoleCommand::Action(UserAction::SelectDevice(serial))) if serial == "emulator-5554"
+        ));
+        assert!(matches!(parse_command("p").unwrap(), Some(ConsoleCommand::Action(UserAction::ToggleAutoLaunch))));
+        assert!(matches!(parse_command("q").unwrap(), Some(ConsoleCommand::Quit)));
+        assert!(parse_command("a 192.168.1.5:37123").is_err());
+        assert!(parse_command("a 192.168.1.5:37123 12345").is_err());
+        assert!(parse_command("x").is_err());
+    }
+
+    #[test]
+    fn formats_output_styles() {
+        let plain = OutputStyle::Plain.format("warning", "⚠️", "设备离线");
+        assert!(plain.ends_with("] WARNING 设备离线"));
+
+        let json = OutputStyle::Json.format("info", "ℹ️", "路径 C:\\adb \"x\"\n");
+        assert!(json.starts_with(r#"{"time":""#));
+        assert!(json.ends_with(r#","kind":"info","message":"路径 C:\\adb \"x\"\n"}"#));
+        assert!(!json.contains('ℹ'));
+    }
+}
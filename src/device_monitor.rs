@@ -1,150 +1,2529 @@
 //! 设备监控模块
 //! 处理Android设备连接状态监控和scrcpy进程管理
 
+use std::collections::{HashMap, HashSet};
 use std::process::Child;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::AdbConfig;
+use crate::form_factor::FormFactor;
+use crate::port_forward::{parse_forward_list, parse_reverse_list, ActiveForward, ForwardRule};
+use crate::scrcpy_compat::{self, Feature, ScrcpyVersion, UnsupportedArg};
+use crate::session::{create_session_log, session_log_dir};
+use crate::tui::DeviceStatus;
 
 /// 设备监控器
 pub struct DeviceMonitor {
     pub adb_exe: PathBuf,
     pub scrcpy_exe: PathBuf,
     pub scrcpy_process: Option<Child>,
+    pub scrcpy_version: Option<ScrcpyVersion>,
+    /// 当前 scrcpy 进程最近的 stderr 输出（由后台线程持续读取）
+    scrcpy_stderr: Arc<Mutex<Vec<String>>>,
+    /// 读取线程结束（stderr 已读完）时断开，进程退出后据此等待剩余输出
+    scrcpy_stderr_done: Option<tokio::sync::oneshot::Receiver<()>>,
+    /// 当前会话的帧率统计（仅在带 --print-fps 启动时有数据）
+    scrcpy_fps: Arc<Mutex<Option<FpsStats>>>,
+    /// adb server 端口与额外环境变量
+    adb_config: AdbConfig,
+    /// 尚未转发到日志面板的 scrcpy 错误输出（跨会话共用，重启前的错误也不会丢失）
+    scrcpy_errors: Arc<Mutex<Vec<String>>>,
+    scrcpy_started_at: Option<Instant>,
+    /// 最近一次启动 scrcpy 的完整命令行（第一项为 scrcpy 路径），便于排查参数问题
+    pub last_args: Vec<String>,
+    /// 最近一次 scrcpy 进程退出的信息
+    pub last_exit: Option<ScrcpyExit>,
+    /// 最近一次会话的 scrcpy 输出日志，无法创建日志文件时为 None
+    pub session_log: Option<PathBuf>,
+    /// 各设备的视频编码器（scrcpy --list-encoders 的探测结果）
+    encoders: ScrcpyProbe<Vec<VideoEncoder>>,
+    /// 后台查询的 getprop 信息、屏幕参数与电池状态
+    device_info: DeviceInfoCache,
+    /// 各设备的摄像头数量（scrcpy --list-cameras 的探测结果）
+    cameras: ScrcpyProbe<usize>,
+    /// 远程 adb server 上的设备 -> 所在 server，针对该设备的 adb / scrcpy 调用发往这个 server
+    device_servers: Arc<Mutex<HashMap<String, String>>>,
+    /// 当前无法访问的远程 adb server
+    unreachable_servers: Mutex<HashSet<String>>,
+    /// 尚未转发到日志面板的远程 server 状态变化：(server, 错误信息)，错误为 None 表示恢复
+    server_changes: Mutex<Vec<(String, Option<String>)>>,
+    /// 本机 adb server 未运行、由 adb devices 自动启动后尚未提示
+    daemon_started: AtomicBool,
 }
 
-impl DeviceMonitor {
-    /// 创建新的设备监控器
-    pub fn new(scrcpy_dir: &PathBuf) -> Self {
+/// 设备的系统版本与屏幕参数，设备首次出现时通过 adb 获取
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceDetails {
+    pub android_version: String,
+    pub sdk: Option<u32>,
+    /// 屏幕分辨率（宽, 高），设置了覆盖值时为覆盖值
+    pub resolution: Option<(u32, u32)>,
+    /// 屏幕密度（dpi）
+    pub density: Option<u32>,
+    /// 按 ro.build.characteristics 判断的设备形态
+    pub form_factor: FormFactor,
+    /// 音频转发与摄像头镜像的支持情况，按系统版本与已安装的 scrcpy 判断
+    pub audio: Support,
+    pub camera: Support,
+}
+
+/// 设备对某项功能的支持情况
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Support {
+    /// 系统版本未知，无法判断
+    #[default]
+    Unknown,
+    Supported,
+    /// 不支持，附带原因
+    Unsupported(String),
+}
+
+impl Support {
+    /// 按设备的 SDK 版本与已安装的 scrcpy 版本判断（scrcpy 版本未知时只看设备）
+    pub fn check(feature: Feature, sdk: Option<u32>, version: Option<ScrcpyVersion>) -> Self {
+        if let Some(version) = version.filter(|v| !feature.is_supported_by(*v)) {
+            return Support::Unsupported(format!("需要 scrcpy {} 及以上，当前为 {}", feature.min_version(), version));
+        }
+        match (feature.min_android(), sdk) {
+            (Some((min_sdk, release)), Some(sdk)) if sdk < min_sdk => {
+                Support::Unsupported(format!("需要 Android {} 及以上", release))
+            }
+            (Some(_), None) => Support::Unknown,
+            _ => Support::Supported,
+        }
+    }
+
+    /// 在设备详情中显示的文本
+    pub fn label(&self) -> String {
+        match self {
+            Support::Unknown => "未知".to_string(),
+            Support::Supported => "支持".to_string(),
+            Support::Unsupported(reason) => format!("不支持（{}）", reason),
+        }
+    }
+}
+
+/// 设备的连接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Usb,
+    /// 通过 adb connect ip:port 连接
+    Tcp,
+    /// 通过 mDNS 自动发现的无线调试设备
+    Mdns,
+    Emulator,
+}
+
+impl Transport {
+    /// 根据序列号判断连接方式
+    pub fn from_serial(serial: &str) -> Self {
+        if serial.contains("._adb-tls-connect._tcp") {
+            Transport::Mdns
+        } else if is_tcp_serial(serial) {
+            Transport::Tcp
+        } else if is_emulator_serial(serial) {
+            Transport::Emulator
+        } else {
+            Transport::Usb
+        }
+    }
+
+    /// 英文名称，用于 JSON 输出
+    pub fn name(self) -> &'static str {
+        match self {
+            Transport::Usb => "usb",
+            Transport::Tcp => "tcp",
+            Transport::Mdns => "mdns",
+            Transport::Emulator => "emulator",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Transport::Usb => "USB",
+            Transport::Tcp => "无线 (adb connect)",
+            Transport::Mdns => "无线 (mDNS)",
+            Transport::Emulator => "模拟器",
+        }
+    }
+}
+
+/// 电池状态（来自 `dumpsys battery`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryStatus {
+    /// 电量百分比
+    pub level: u8,
+    pub charging: bool,
+}
+
+/// 电池状态刷新间隔，电量变化缓慢，没必要频繁执行 dumpsys
+const BATTERY_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 通过 getprop 获取的设备信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceMetadata {
+    pub manufacturer: String,
+    pub model: String,
+    pub android_version: String,
+    pub sdk: Option<u32>,
+    /// 完整的属性表，供其他功能按需读取
+    pub props: HashMap<String, String>,
+}
+
+impl DeviceMetadata {
+    /// 从 `adb shell getprop` 的输出构建设备信息
+    pub fn from_getprop(output: &str) -> Self {
+        let props = parse_getprop(output);
+        let get = |key: &str| props.get(key).cloned().unwrap_or_default();
         Self {
-            adb_exe: scrcpy_dir.join("adb.exe"),
-            scrcpy_exe: scrcpy_dir.join("scrcpy.exe"),
-            scrcpy_process: None,
+            manufacturer: get("ro.product.manufacturer"),
+            model: get("ro.product.model"),
+            android_version: get("ro.build.version.release"),
+            sdk: props.get("ro.build.version.sdk").and_then(|v| v.parse().ok()),
+            props,
         }
     }
 
-    /// 检查scrcpy是否可用（实时检测）
-    pub fn is_scrcpy_available(&self) -> bool {
-        self.scrcpy_exe.exists() && self.adb_exe.exists()
+    /// 便于阅读的设备名称，例如 "Xiaomi 2211133C (Android 14)"
+    pub fn display_name(&self) -> Option<String> {
+        if self.model.is_empty() {
+            return None;
+        }
+
+        let mut name = String::new();
+        // 型号已以厂商名开头时（如 "Xiaomi 13"）不再重复厂商名
+        if !self.manufacturer.is_empty()
+            && !self.model.to_lowercase().starts_with(&self.manufacturer.to_lowercase())
+        {
+            let mut chars = self.manufacturer.chars();
+            if let Some(first) = chars.next() {
+                name.extend(first.to_uppercase());
+                name.push_str(chars.as_str());
+                name.push(' ');
+            }
+        }
+        name.push_str(&self.model);
+
+        if !self.android_version.is_empty() {
+            name.push_str(&format!(" (Android {})", self.android_version));
+        }
+        Some(name)
     }
+}
 
-    /// 检查设备连接状态（实时检测，性能优化版本）
-    pub async fn check_devices(&self) -> Result<Vec<crate::tui::DeviceInfo>, String> {
-        use tokio::process::Command;
-        use tokio::time::{timeout, Duration};
-        
-        // 为 adb devices 增加命令级超时，避免 adb 异常挂死
+/// 获取设备信息失败后的重试间隔
+const METADATA_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// scrcpy --print-fps 报告的帧率统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FpsStats {
+    pub current: u32,
+    pub min: u32,
+    pub max: u32,
+    pub average: f64,
+    pub skipped: u64,
+    pub samples: u64,
+}
+
+impl FpsStats {
+    /// 记录一次帧率报告
+    fn record(&mut self, fps: u32, skipped: u32) {
+        if self.samples == 0 {
+            self.min = fps;
+            self.max = fps;
+        } else {
+            self.min = self.min.min(fps);
+            self.max = self.max.max(fps);
+        }
+        self.current = fps;
+        self.skipped += skipped as u64;
+        self.samples += 1;
+        self.average += (fps as f64 - self.average) / self.samples as f64;
+    }
+}
+
+/// scrcpy 自带的设备探测（--list-encoders 等）需要推送 scrcpy-server 到设备，耗时可达十几秒，
+/// 因此在后台任务中执行，不阻塞监控循环；只缓存成功的结果，失败后下次需要时重新探测
+struct ScrcpyProbe<T> {
+    /// scrcpy 的探测参数
+    option: &'static str,
+    parse: fn(&str) -> Result<T, String>,
+    state: Arc<Mutex<ProbeState<T>>>,
+}
+
+struct ProbeState<T> {
+    results: HashMap<String, T>,
+    running: HashSet<String>,
+    /// 已完成但尚未取走的探测结果
+    finished: Vec<(String, Result<T, String>)>,
+}
+
+impl<T: Clone + Send + 'static> ScrcpyProbe<T> {
+    fn new(option: &'static str, parse: fn(&str) -> Result<T, String>) -> Self {
+        let state = ProbeState { results: HashMap::new(), running: HashSet::new(), finished: Vec::new() };
+        Self { option, parse, state: Arc::new(Mutex::new(state)) }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProbeState<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cached(&self, serial: &str) -> Option<T> {
+        self.lock().results.get(serial).cloned()
+    }
+
+    fn start(&self, scrcpy_exe: &Path, envs: Vec<(String, String)>, serial: &str) {
+        {
+            let mut state = self.lock();
+            if state.results.contains_key(serial) || !state.running.insert(serial.to_string()) {
+                return;
+            }
+        }
+        let mut command = tokio::process::Command::new(scrcpy_exe);
+        command.args(["-s", serial, self.option]).envs(envs).kill_on_drop(true);
+        let (option, parse, state, serial) = (self.option, self.parse, Arc::clone(&self.state), serial.to_string());
+        tokio::spawn(async move {
+            let result = match tokio::time::timeout(PROBE_TIMEOUT, command.output()).await {
+                Ok(Ok(output)) => {
+                    // 列表可能打印在 stdout 或 stderr，视 scrcpy 版本而定
+                    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    if output.status.success() {
+                        parse(&text)
+                    } else {
+                        let reason = text.lines().rev().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+                        Err(format!("scrcpy {} 失败: {}", option, reason))
+                    }
+                }
+                Ok(Err(e)) => Err(format!("执行scrcpy {}失败: {}", option, e)),
+                Err(_) => Err(format!("scrcpy {} 超时", option)),
+            };
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            state.running.remove(&serial);
+            if let Ok(value) = &result {
+                state.results.insert(serial.clone(), value.clone());
+            }
+            state.finished.push((serial, result));
+        });
+    }
+
+    fn take_finished(&self) -> Vec<(String, Result<T, String>)> {
+        std::mem::take(&mut self.lock().finished)
+    }
+}
+
+/// 执行 adb 命令的句柄，可以复制到后台任务中；针对远程设备的命令发往其所在的 adb server
+#[derive(Clone)]
+pub struct Adb {
+    exe: PathBuf,
+    config: AdbConfig,
+    /// 与监控器共用的设备 -> 远程 server 表，随 adb devices 的结果更新
+    device_servers: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Adb {
+    /// 创建带有自定义端口与环境变量的 adb 命令，server 为远程 adb server，None 时使用本机 server
+    fn command(&self, server: Option<&str>) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(&self.exe);
+        match server {
+            Some(server) => {
+                let (host, port) = split_server(server);
+                cmd.arg("-H").arg(host).arg("-P").arg(port.to_string());
+            }
+            None => {
+                if let Some(port) = self.config.server_port {
+                    cmd.arg("-P").arg(port.to_string());
+                }
+            }
+        }
+        cmd.envs(self.config.envs());
+        cmd
+    }
+
+    /// 设备所在的远程 adb server，本机设备返回 None
+    fn server_of(&self, serial: &str) -> Option<String> {
+        self.device_servers.lock().unwrap_or_else(|e| e.into_inner()).get(serial).cloned()
+    }
+
+    /// 执行一条 adb 命令并返回 stdout（带超时，避免 adb 异常挂死）
+    pub async fn run(&self, args: &[&str], timeout_secs: u64) -> Result<String, String> {
+        use tokio::time::timeout;
+
+        // 针对单台设备的命令发往设备所在的 adb server
+        let server = match args {
+            ["-s", serial, ..] => self.server_of(serial),
+            _ => None,
+        };
         let output = match timeout(
-            Duration::from_secs(2),
-            Command::new(&self.adb_exe)
-                .arg("devices")
+            Duration::from_secs(timeout_secs),
+            self.command(server.as_deref())
+                .args(args)
+                .kill_on_drop(true)
                 .output(),
         ).await {
             Ok(Ok(output)) => output,
             Ok(Err(e)) => return Err(format!("执行adb命令失败: {}", e)),
-            Err(_) => return Err("adb devices 命令超时".to_string()),
+            Err(_) => return Err(format!("adb {} 命令超时", args.join(" "))),
         };
 
         if !output.status.success() {
-            return Err("adb devices 命令执行失败".to_string());
-        }
-
-        // 预分配容量以减少重新分配
-        let mut devices = Vec::with_capacity(4); // 大多数情况下不会超过4个设备
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        // 更高效的字符串处理，避免collect()
-        for line in output_str.lines().skip(1) { // 跳过第一行 "List of devices attached"
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            // 直接使用split_once避免创建Vec
-            if let Some((device_id, rest)) = line.split_once('\t') {
-                if rest.trim().starts_with("device") {
-                    devices.push(crate::tui::DeviceInfo {
-                        id: device_id.to_string(),
-                        name: "Android设备".to_string(),
-                        status: "已连接".to_string(),
-                    });
-                }
-            } else {
-                // 备用解析方式（空格分隔）
-                let mut parts = line.split_whitespace();
-                if let (Some(device_id), Some(status)) = (parts.next(), parts.next()) {
-                    if status == "device" {
-                        devices.push(crate::tui::DeviceInfo {
-                            id: device_id.to_string(),
-                            name: "Android设备".to_string(),
-                            status: "已连接".to_string(),
-                        });
-                    }
-                }
-            }
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("adb {} 执行失败: {}", args.join(" "), stderr.trim()));
         }
 
-        Ok(devices)
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 
-    /// 启动scrcpy（重定向输出以避免干扰TUI）
-    pub fn start_scrcpy(&mut self, device_id: Option<&str>) -> Result<(), String> {
-        use std::process::{Command, Stdio};
+    /// 轻量的连接检查（adb shell echo ok），用于发现 adb devices 仍列出但实际已无响应的设备
+    /// timeout_secs 为命令超时，响应慢的设备（如手表）可适当延长
+    pub async fn ping(&self, serial: &str, timeout_secs: u64) -> Result<(), String> {
+        let output = self.run(&["-s", serial, "shell", "echo", "ok"], timeout_secs).await?;
+        if output.trim() == "ok" {
+            Ok(())
+        } else {
+            Err(format!("设备返回了意外的输出: {}", output.trim()))
+        }
+    }
 
-        // 停止现有的scrcpy进程
-        self.stop_scrcpy();
+    /// 连接无线设备（adb connect）
+    pub async fn connect_tcp(&self, address: &str) -> Result<(), String> {
+        let output = self.run(&["connect", address], 10).await?;
+        // adb connect 失败时退出码依然为0，只能根据输出判断
+        if output.contains("connected to") {
+            Ok(())
+        } else {
+            Err(format!("连接 {} 失败: {}", address, output.trim()))
+        }
+    }
 
-        let mut cmd = Command::new(&self.scrcpy_exe);
-        
-        if let Some(id) = device_id {
-            cmd.arg("-s").arg(id);
+    /// 无线调试配对（adb pair，Android 11+）
+    pub async fn pair(&self, address: &str, code: &str) -> Result<(), String> {
+        let output = self.run(&["pair", address, code], 15).await?;
+        if output.contains("Successfully paired") {
+            Ok(())
+        } else {
+            Err(format!("配对 {} 失败: {}", address, output.trim()))
         }
+    }
 
-        // 重定向输出以避免干扰TUI界面
-        cmd.stdout(Stdio::null())
-           .stderr(Stdio::null())
-           .stdin(Stdio::null());
+    /// 列出 adb 通过 mDNS 发现的服务（adb mdns services）
+    pub async fn mdns_services(&self) -> Result<String, String> {
+        self.run(&["mdns", "services"], 3).await
+    }
 
-        let child = cmd.spawn()
-            .map_err(|e| format!("启动scrcpy失败: {}", e))?;
+    /// 断开无线设备（adb disconnect）
+    pub async fn disconnect_tcp(&self, address: &str) -> Result<(), String> {
+        self.run(&["disconnect", address], 5).await.map(|_| ())
+    }
 
-        self.scrcpy_process = Some(child);
-        Ok(())
+    /// 重新连接掉线的无线设备：先断开残留的 offline 连接，再以较短超时重连
+    pub async fn reconnect_tcp(&self, address: &str) -> Result<(), String> {
+        let _ = self.disconnect_tcp(address).await;
+        let output = self.run(&["connect", address], 3).await?;
+        if output.contains("connected to") {
+            Ok(())
+        } else {
+            Err(output.trim().to_string())
+        }
     }
 
-    /// 检查scrcpy进程是否还在运行
-    pub fn is_scrcpy_running(&mut self) -> bool {
-        if let Some(ref mut process) = self.scrcpy_process {
-            match process.try_wait() {
-                Ok(Some(_)) => {
-                    // 进程已结束
-                    self.scrcpy_process = None;
-                    false
-                }
-                Ok(None) => {
-                    // 进程仍在运行
-                    true
-                }
-                Err(_) => {
-                    // 出错，假设进程已结束
-                    self.scrcpy_process = None;
-                    false
-                }
+    /// 在设备上建立一条端口转发（adb forward / adb reverse），同名端口已存在时会被覆盖
+    pub async fn apply_forward(&self, serial: &str, rule: &ForwardRule) -> Result<(), String> {
+        let mut args = vec!["-s", serial];
+        args.extend(rule.adb_args());
+        self.run(&args, 5).await.map(|_| ())
+    }
+
+    /// 列出这些设备上生效中的转发，包括用户手动建立的
+    pub async fn list_forwards(&self, serials: &[String]) -> Vec<ActiveForward> {
+        let mut active = match self.run(&["forward", "--list"], 5).await {
+            Ok(output) => parse_forward_list(&output),
+            Err(_) => Vec::new(),
+        };
+        active.retain(|f| serials.contains(&f.serial));
+        for serial in serials {
+            if let Ok(output) = self.run(&["-s", serial, "reverse", "--list"], 5).await {
+                active.extend(parse_reverse_list(serial, &output));
+            }
+        }
+        active
+    }
+
+    /// 查询设备的电池状态（dumpsys battery），失败或无法解析时返回 None
+    async fn battery(&self, serial: &str) -> Option<BatteryStatus> {
+        self.run(&["-s", serial, "shell", "dumpsys", "battery"], 3)
+            .await
+            .ok()
+            .and_then(|output| parse_battery(&output))
+    }
+
+    /// 查询设备的屏幕参数（wm size / density），系统版本与形态取自 getprop 信息
+    async fn details(&self, serial: &str, metadata: &DeviceMetadata) -> DeviceDetails {
+        let wm = |what: &'static str| ["-s", serial, "shell", "wm", what];
+        let resolution = self.run(&wm("size"), 3).await.ok().and_then(|o| parse_wm_size(&o));
+        let density = self
+            .run(&wm("density"), 3)
+            .await
+            .ok()
+            .and_then(|o| wm_value(&o, "density")?.parse().ok());
+        DeviceDetails {
+            android_version: metadata.android_version.clone(),
+            sdk: metadata.sdk,
+            resolution,
+            density,
+            form_factor: FormFactor::from_characteristics(
+                metadata.props.get("ro.build.characteristics").map(String::as_str).unwrap_or_default()
+            ),
+            ..DeviceDetails::default()
+        }
+    }
+}
+
+/// 通过 adb 在后台查询的设备信息，监控循环只读取缓存，不等待 adb 命令
+#[derive(Default)]
+struct DeviceInfoCache {
+    state: Arc<Mutex<DeviceInfoState>>,
+}
+
+#[derive(Default)]
+struct DeviceInfoState {
+    /// 各设备的 getprop 信息
+    metadata: HashMap<String, DeviceMetadata>,
+    /// 最近一次获取 getprop 信息失败的时间，用于控制重试频率
+    metadata_attempts: HashMap<String, Instant>,
+    /// 各设备的屏幕参数与系统版本（每台设备只查询一次）
+    details: HashMap<String, DeviceDetails>,
+    /// 各设备的电池状态及查询时间
+    battery: HashMap<String, (Option<BatteryStatus>, Instant)>,
+    /// 正在后台查询的设备，每台设备同时只有一个查询
+    running: HashSet<String>,
+    /// 有新的查询结果，设备列表需要刷新
+    changed: bool,
+}
+
+impl DeviceInfoState {
+    /// 电池状态是否需要重新查询（按刷新间隔，其余时间使用缓存）
+    fn battery_stale(&self, serial: &str) -> bool {
+        self.battery
+            .get(serial)
+            .is_none_or(|(_, checked)| checked.elapsed() >= BATTERY_REFRESH_INTERVAL)
+    }
+
+    /// getprop 信息是否需要查询；刚连接的设备可能还没准备好，失败后间隔一段时间再试
+    fn metadata_due(&self, serial: &str) -> bool {
+        !self.metadata.contains_key(serial)
+            && self
+                .metadata_attempts
+                .get(serial)
+                .is_none_or(|t| t.elapsed() >= METADATA_RETRY_INTERVAL)
+    }
+
+    fn needs_refresh(&self, serial: &str) -> bool {
+        self.metadata_due(serial) || self.battery_stale(serial)
+    }
+}
+
+impl DeviceInfoCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, DeviceInfoState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 缓存过期且没有正在进行的查询时，在后台查询该设备
+    fn refresh(&self, adb: &Adb, serial: &str) {
+        {
+            let mut state = self.lock();
+            if !state.needs_refresh(serial) || !state.running.insert(serial.to_string()) {
+                return;
+            }
+        }
+        let (adb, state, serial) = (adb.clone(), Arc::clone(&self.state), serial.to_string());
+        tokio::spawn(async move {
+            query_device_info(&adb, &state, &serial).await;
+            state.lock().unwrap_or_else(|e| e.into_inner()).running.remove(&serial);
+        });
+    }
+
+    /// 缓存过期时立即查询并等待结果
+    async fn load(&self, adb: &Adb, serial: &str) {
+        if self.lock().needs_refresh(serial) {
+            query_device_info(adb, &self.state, serial).await;
+        }
+    }
+}
+
+/// 查询设备已过期的信息并写入缓存；adb 命令执行期间不持有锁
+async fn query_device_info(adb: &Adb, state: &Mutex<DeviceInfoState>, serial: &str) {
+    let lock = || state.lock().unwrap_or_else(|e| e.into_inner());
+    if lock().metadata_due(serial) {
+        match adb.run(&["-s", serial, "shell", "getprop"], 3).await {
+            Ok(output) => {
+                let metadata = DeviceMetadata::from_getprop(&output);
+                let details = adb.details(serial, &metadata).await;
+                let mut state = lock();
+                state.metadata_attempts.remove(serial);
+                state.metadata.insert(serial.to_string(), metadata);
+                state.details.insert(serial.to_string(), details);
+                state.changed = true;
+            }
+            Err(_) => {
+                lock().metadata_attempts.insert(serial.to_string(), Instant::now());
             }
+        }
+    }
+    if lock().battery_stale(serial) {
+        let status = adb.battery(serial).await;
+        let mut state = lock();
+        let previous = state.battery.insert(serial.to_string(), (status, Instant::now()));
+        state.changed |= previous.is_none_or(|(old, _)| old != status);
+    }
+}
+
+/// scrcpy 探测命令的超时时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 设备上可用的视频编码器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoEncoder {
+    pub codec: String,
+    pub name: String,
+    pub hardware: bool,
+}
+
+/// scrcpy 进程退出信息
+#[derive(Debug, Clone)]
+pub struct ScrcpyExit {
+    pub runtime: Duration,
+    /// 退出码，被信号终止等情况下没有
+    pub code: Option<i32>,
+}
+
+/// scrcpy 退出的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitKind {
+    /// 用户关闭了窗口（退出码 0）
+    UserClosed,
+    /// 与设备的连接断开（退出码 2）
+    Disconnected,
+    /// 其他错误
+    Crashed,
+}
+
+impl ScrcpyExit {
+    /// 按 scrcpy 的退出码约定判断退出原因
+    pub fn kind(&self) -> ExitKind {
+        match self.code {
+            Some(0) => ExitKind::UserClosed,
+            Some(2) => ExitKind::Disconnected,
+            _ => ExitKind::Crashed,
+        }
+    }
+}
+
+/// 按 scrcpy / adb 的错误输出识别出的常见失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrcpyFailure {
+    Unauthorized,
+    DeviceMissing,
+    ServerPushFailed,
+    PortInUse,
+    EncoderFailed,
+    AudioFailed,
+    RendererFailed,
+}
+
+impl ScrcpyFailure {
+    /// 从 stderr 的错误行中识别失败原因（正常输出如 "scrcpy-server: 1 file pushed" 不参与判断），
+    /// 靠前的原因优先，例如未授权时随后的连接错误只是结果
+    pub fn detect(lines: &[String]) -> Option<Self> {
+        let text = lines
+            .iter()
+            .filter(|line| is_notable_stderr_line(line))
+            .map(|line| line.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let has = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+        if has(&["unauthorized"]) {
+            Some(ScrcpyFailure::Unauthorized)
+        } else if has(&["could not find any adb device", "device not found", "device offline", "no devices/emulators found"]) {
+            Some(ScrcpyFailure::DeviceMissing)
+        } else if has(&["could not push", "adb push", "scrcpy-server"]) {
+            Some(ScrcpyFailure::ServerPushFailed)
+        } else if has(&["could not listen on port", "address already in use"]) {
+            Some(ScrcpyFailure::PortInUse)
+        } else if has(&["audio capture", "could not capture audio", "audiorecord", "audio encoder", "audio encoding"]) {
+            // 音频编码器的错误同样带有 encoder / MediaCodec，先于视频编码器判断
+            Some(ScrcpyFailure::AudioFailed)
+        } else if has(&["encoder", "encoding error", "mediacodec"]) {
+            Some(ScrcpyFailure::EncoderFailed)
+        } else if has(&["could not create renderer", "could not create window", "could not initialize sdl"]) {
+            Some(ScrcpyFailure::RendererFailed)
         } else {
-            false
+            None
         }
     }
 
-    /// 停止scrcpy
-    pub fn stop_scrcpy(&mut self) {
-        if let Some(mut process) = self.scrcpy_process.take() {
-            let _ = process.kill();
-            let _ = process.wait();
+    /// 原因与处理建议，作为错误日志显示
+    pub fn message(self) -> &'static str {
+        match self {
+            ScrcpyFailure::Unauthorized => "设备未授权：请在手机上允许USB调试（勾选“始终允许”），仍无效时在开发者选项中撤销授权后重新插拔",
+            ScrcpyFailure::DeviceMissing => "设备已断开或不可用：请检查数据线或无线连接，确认 adb devices 中设备为 device 状态",
+            ScrcpyFailure::ServerPushFailed => "无法把 scrcpy-server 推送到设备：请检查设备存储空间与数据线，并确认 scrcpy-server 与 scrcpy 版本一致（重新解压 scrcpy 可修复）",
+            ScrcpyFailure::PortInUse => "转发端口被占用：请关闭其他 scrcpy 或占用端口的程序，或用 --port 指定其他端口",
+            ScrcpyFailure::EncoderFailed => "视频编码器初始化失败：可在设备配置中指定其他 video_encoder（可用编码器已列在日志中），或降低 --max-size / --video-bit-rate",
+            ScrcpyFailure::AudioFailed => "音频采集失败：设备可能不支持音频转发，可按 m 关闭音频或在配置中设置 audio.enabled = false",
+            ScrcpyFailure::RendererFailed => "无法创建窗口或渲染器：请更新显卡驱动，或在 scrcpy_args 中加入 --render-driver=opengl",
         }
     }
 }
 
-impl Drop for DeviceMonitor {
-    fn drop(&mut self) {
-        self.stop_scrcpy();
+/// 保留的 stderr 行数上限
+const STDERR_BUFFER_LINES: usize = 50;
+
+/// scrcpy 退出后等待读取剩余 stderr 的最长时间
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 停止会话时等待 scrcpy 自行退出的最长时间
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 等待 scrcpy 退出时检查进程状态的间隔
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 运行时间短于此值的退出视为"启动即失败"
+const IMMEDIATE_EXIT_THRESHOLD: Duration = Duration::from_secs(5);
+
+impl DeviceMonitor {
+    /// 创建新的设备监控器
+    /// scrcpy 目录中缺少 adb.exe / scrcpy.exe 时，依次在 PATH 和常见安装位置中查找
+    pub fn new(scrcpy_dir: &Path) -> Self {
+        Self {
+            adb_exe: locate_executable(scrcpy_dir, "adb.exe", &adb_install_dirs()),
+            scrcpy_exe: locate_executable(scrcpy_dir, "scrcpy.exe", &scrcpy_install_dirs()),
+            scrcpy_process: None,
+            scrcpy_version: None,
+            scrcpy_stderr: Arc::new(Mutex::new(Vec::new())),
+            scrcpy_stderr_done: None,
+            scrcpy_fps: Arc::new(Mutex::new(None)),
+            scrcpy_errors: Arc::new(Mutex::new(Vec::new())),
+            adb_config: AdbConfig::default(),
+            scrcpy_started_at: None,
+            last_args: Vec::new(),
+            last_exit: None,
+            session_log: None,
+            encoders: ScrcpyProbe::new("--list-encoders", parse_encoder_probe),
+            device_info: DeviceInfoCache::default(),
+            cameras: ScrcpyProbe::new("--list-cameras", count_cameras),
+            device_servers: Arc::new(Mutex::new(HashMap::new())),
+            unreachable_servers: Mutex::new(HashSet::new()),
+            server_changes: Mutex::new(Vec::new()),
+            daemon_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 设置 adb server 端口与环境变量，之后的 adb / scrcpy 调用都会使用
+    pub fn set_adb_config(&mut self, adb_config: AdbConfig) {
+        self.adb_config = adb_config;
+    }
+
+    /// 可以移到后台任务中使用的 adb 句柄，与监控器使用相同的 adb、配置与设备所在的 server
+    pub fn adb(&self) -> Adb {
+        Adb {
+            exe: self.adb_exe.clone(),
+            config: self.adb_config.clone(),
+            device_servers: Arc::clone(&self.device_servers),
+        }
+    }
+
+    /// 创建带有自定义端口与环境变量的 adb 命令，server 为远程 adb server，None 时使用本机 server
+    fn adb_command(&self, server: Option<&str>) -> tokio::process::Command {
+        self.adb().command(server)
+    }
+
+    /// 设备所在的远程 adb server，本机设备返回 None
+    fn server_of(&self, serial: &str) -> Option<String> {
+        self.adb().server_of(serial)
+    }
+
+    /// 针对某台设备启动 scrcpy 时的环境变量，远程设备通过 ADB_SERVER_SOCKET 指向其所在的 server
+    fn scrcpy_envs(&self, serial: Option<&str>) -> Vec<(String, String)> {
+        let mut envs = self.adb_config.envs();
+        if let Some(server) = serial.and_then(|serial| self.server_of(serial)) {
+            let (host, port) = split_server(&server);
+            envs.push(("ADB_SERVER_SOCKET".to_string(), format!("tcp:{}:{}", host, port)));
+        }
+        envs
+    }
+
+    /// 记录远程 server 是否可以访问，状态变化时留给监控循环提示
+    fn mark_server(&self, server: &str, error: Option<String>) {
+        let mut unreachable = self.unreachable_servers.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = match error {
+            Some(_) => unreachable.insert(server.to_string()),
+            None => unreachable.remove(server),
+        };
+        if changed {
+            self.server_changes.lock().unwrap_or_else(|e| e.into_inner()).push((server.to_string(), error));
+        }
+    }
+
+    /// 取出远程 adb server 的状态变化（变为无法访问或恢复）
+    pub fn take_server_changes(&self) -> Vec<(String, Option<String>)> {
+        std::mem::take(&mut *self.server_changes.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 本机 adb server 是否刚由 adb devices 自动启动，取出后清除
+    pub fn take_daemon_started(&self) -> bool {
+        self.daemon_started.swap(false, Ordering::Relaxed)
+    }
+
+    /// 检查scrcpy是否可用（实时检测）
+    pub fn is_scrcpy_available(&self) -> bool {
+        self.scrcpy_exe.exists() && self.adb_exe.exists()
+    }
+
+    /// 通过 `scrcpy --version` 检测已安装的 scrcpy 版本，并缓存结果供参数过滤使用
+    pub async fn detect_scrcpy_version(&mut self) -> Option<ScrcpyVersion> {
+        use tokio::process::Command;
+        use tokio::time::{timeout, Duration};
+
+        let output = timeout(
+            Duration::from_secs(3),
+            Command::new(&self.scrcpy_exe)
+                .arg("--version")
+                .output(),
+        ).await.ok()?.ok()?;
+
+        self.scrcpy_version = ScrcpyVersion::parse(&String::from_utf8_lossy(&output.stdout));
+        self.scrcpy_version
+    }
+
+    /// 执行一条 adb 命令并返回 stdout（带超时，避免 adb 异常挂死）
+    pub async fn run_adb(&self, args: &[&str], timeout_secs: u64) -> Result<String, String> {
+        self.adb().run(args, timeout_secs).await
+    }
+
+    /// 启动 adb server
+    pub async fn start_adb_server(&self) -> Result<(), String> {
+        self.run_adb(&["start-server"], 10).await.map(|_| ())
+    }
+
+    /// 关闭 adb server
+    pub async fn kill_adb_server(&self) -> Result<(), String> {
+        self.run_adb(&["kill-server"], 5).await.map(|_| ())
+    }
+
+    /// 重启 adb server，用于 adb 守护进程挂死后恢复
+    pub async fn restart_adb_server(&self) -> Result<(), String> {
+        // 守护进程挂死时 kill-server 本身也可能失败，此时仍尝试重新启动
+        let kill_result = self.kill_adb_server().await;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        self.start_adb_server().await.map_err(|e| match kill_result {
+            Ok(()) => e,
+            Err(kill_error) => format!("{}（关闭旧服务也失败: {}）", e, kill_error),
+        })
+    }
+
+    /// 查询设备的 WLAN IP 地址
+    pub async fn query_wlan_ip(&self, serial: &str) -> Result<String, String> {
+        let output = self.run_adb(&["-s", serial, "shell", "ip", "-f", "inet", "addr", "show", "wlan0"], 3).await?;
+        if let Some(ip) = parse_inet_addr(&output) {
+            return Ok(ip);
+        }
+
+        // 部分设备的无线网卡不叫 wlan0，退而从路由表中取源地址
+        let output = self.run_adb(&["-s", serial, "shell", "ip", "route"], 3).await?;
+        parse_route_src(&output).ok_or_else(|| "未获取到设备的WLAN地址，请确认设备已连接WiFi".to_string())
+    }
+
+    /// 将 USB 设备切换为无线调试：adb tcpip + adb connect，返回无线设备地址
+    pub async fn enable_wireless(&self, serial: &str, port: u16) -> Result<String, String> {
+        let ip = self.query_wlan_ip(serial).await?;
+        self.run_adb(&["-s", serial, "tcpip", &port.to_string()], 5).await?;
+
+        // adbd 切换到 TCP 模式需要一点时间
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        let address = format!("{}:{}", ip, port);
+        self.adb().connect_tcp(&address).await?;
+        Ok(address)
+    }
+
+    /// 点亮设备屏幕（input keyevent KEYCODE_WAKEUP，屏幕已亮时无副作用）
+    pub async fn wake_device(&self, serial: &str) -> Result<(), String> {
+        self.run_adb(&["-s", serial, "shell", "input", "keyevent", "KEYCODE_WAKEUP"], 5).await.map(|_| ())
     }
-}
\ No newline at end of file
+
+    /// 向设备发送一个按键（遥控器使用），keycode 形如 KEYCODE_DPAD_UP
+    pub async fn send_keyevent(&self, serial: &str, keycode: &str) -> Result<(), String> {
+        self.run_adb(&["-s", serial, "shell", "input", "keyevent", keycode], 5).await.map(|_| ())
+    }
+
+    /// 投屏开始时打开应用：scrcpy 3.0+ 返回要附加的 --start-app 参数，旧版本或版本未知时直接通过 adb 打开并返回 None
+    pub async fn start_app(&self, serial: &str, app: &str) -> Result<Option<String>, String> {
+        if self.scrcpy_version.is_some_and(|v| Feature::StartApp.is_supported_by(v)) {
+            return Ok(Some(format!("--start-app={}", app)));
+        }
+        self.launch_app(serial, app).await.map(|_| None)
+    }
+
+    /// 通过 monkey 打开应用的桌面入口，供不支持 --start-app 的 scrcpy 使用
+    /// 与 scrcpy 一致，包名前的 "+" 表示先强制停止；电视应用只有 Leanback 入口时改用它
+    async fn launch_app(&self, serial: &str, app: &str) -> Result<(), String> {
+        if app.starts_with('?') {
+            return Err("按应用名称查找需要 scrcpy 3.0 及以上，请改用包名".to_string());
+        }
+        let package = match app.strip_prefix('+') {
+            Some(package) => {
+                self.run_adb(&["-s", serial, "shell", "am", "force-stop", package], 5).await?;
+                package
+            }
+            None => app,
+        };
+        for category in ["android.intent.category.LAUNCHER", "android.intent.category.LEANBACK_LAUNCHER"] {
+            let output = self.run_adb(&["-s", serial, "shell", "monkey", "-p", package, "-c", category, "1"], 10).await;
+            // 找不到入口时旧系统的 monkey 仍以 0 退出，只在输出中说明
+            if output.is_ok_and(|text| !text.contains("monkey aborted")) {
+                return Ok(());
+            }
+        }
+        Err(format!("找不到应用 {} 的启动入口，请确认包名且应用已安装", package))
+    }
+
+    /// 在设备上执行一条 shell 命令（整条命令交给设备端的 sh 解析）
+    pub async fn run_shell(&self, serial: &str, command: &str) -> Result<String, String> {
+        self.run_adb(&["-s", serial, "shell", command], 15).await
+    }
+
+    /// 用缓存的 getprop 信息替换设备列表中的默认名称，尚未查询到时保留默认名称
+    pub fn fill_device_names(&self, devices: &mut [crate::tui::DeviceInfo]) {
+        let state = self.device_info.lock();
+        // 未授权/离线的设备无法执行 shell 命令
+        for device in devices.iter_mut().filter(|d| d.status.is_ready()) {
+            if let Some(name) = state.metadata.get(&device.id).and_then(|m| m.display_name()) {
+                device.name = name;
+            }
+        }
+    }
+
+    /// 用缓存填充设备详情，尚未查询到时为 None
+    pub fn fill_details(&self, devices: &mut [crate::tui::DeviceInfo]) {
+        let state = self.device_info.lock();
+        for device in devices.iter_mut().filter(|d| d.status.is_ready()) {
+            // 支持情况每次重新判断，scrcpy 可能在运行中被更新
+            device.details = state.details.get(&device.id).cloned().map(|mut details| {
+                details.audio = Support::check(Feature::Audio, details.sdk, self.scrcpy_version);
+                details.camera = match Support::check(Feature::Camera, details.sdk, self.scrcpy_version) {
+                    Support::Supported if self.cameras.cached(&device.id) == Some(0) => {
+                        Support::Unsupported("设备没有可用的摄像头".to_string())
+                    }
+                    support => support,
+                };
+                details
+            });
+        }
+    }
+
+    /// 为缓存缺失或过期的设备在后台查询设备信息与电池状态，监控循环不等待 adb 命令
+    pub fn refresh_device_info(&self, devices: &[crate::tui::DeviceInfo]) {
+        // 未授权/离线的设备无法执行 shell 命令
+        for device in devices.iter().filter(|d| d.status.is_ready()) {
+            self.device_info.refresh(&self.adb(), &device.id);
+        }
+    }
+
+    /// 立即查询缓存过期的设备并等待结果，供命令行的一次性查询使用
+    pub async fn load_device_info(&self, devices: &[crate::tui::DeviceInfo]) {
+        let adb = self.adb();
+        for device in devices.iter().filter(|d| d.status.is_ready()) {
+            self.device_info.load(&adb, &device.id).await;
+        }
+    }
+
+    /// 设备的 getprop 信息是否已查询过（成功或失败），启动参数依赖其中的名称、形态与屏幕参数
+    pub fn device_info_ready(&self, serial: &str) -> bool {
+        let state = self.device_info.lock();
+        state.metadata.contains_key(serial) || state.metadata_attempts.contains_key(serial)
+    }
+
+    /// 后台查询是否带来了新的设备信息，取出后清除
+    pub fn take_device_info_changed(&self) -> bool {
+        std::mem::take(&mut self.device_info.lock().changed)
+    }
+
+    /// 用缓存填充设备列表中的电池状态，尚未查询到时为 None
+    pub fn fill_battery(&self, devices: &mut [crate::tui::DeviceInfo]) {
+        let state = self.device_info.lock();
+        for device in devices.iter_mut().filter(|d| d.status.is_ready()) {
+            device.battery = state.battery.get(&device.id).and_then(|(status, _)| *status);
+        }
+    }
+
+    /// 已探测到的设备视频编码器列表，尚未探测或探测失败时返回 None
+    pub fn video_encoders(&self, serial: &str) -> Option<Vec<VideoEncoder>> {
+        self.encoders.cached(serial)
+    }
+
+    /// 在后台探测设备的视频编码器，已有结果或正在探测时不重复执行
+    pub fn probe_video_encoders(&self, serial: &str) {
+        self.encoders.start(&self.scrcpy_exe, self.scrcpy_envs(Some(serial)), serial);
+    }
+
+    /// 取出自上次调用以来完成的编码器探测
+    pub fn take_encoder_probes(&self) -> Vec<(String, Result<Vec<VideoEncoder>, String>)> {
+        self.encoders.take_finished()
+    }
+
+    /// 已探测到的设备摄像头数量，尚未探测或探测失败时返回 None
+    pub fn camera_count(&self, serial: &str) -> Option<usize> {
+        self.cameras.cached(serial)
+    }
+
+    /// 在后台探测设备的摄像头数量，已有结果或正在探测时不重复执行
+    pub fn probe_cameras(&self, serial: &str) {
+        self.cameras.start(&self.scrcpy_exe, self.scrcpy_envs(Some(serial)), serial);
+    }
+
+    /// 取出自上次调用以来完成的摄像头探测
+    pub fn take_camera_probes(&self) -> Vec<(String, Result<usize, String>)> {
+        self.cameras.take_finished()
+    }
+
+    /// 检查设备连接状态（实时检测，性能优化版本）
+    /// 配置了远程 adb server 时同时查询所有 server 并合并结果，远程设备带上所在 server 的标记
+    /// 用于命令行的一次性查询，adb server 可能需要先启动，因此使用 ADB_STARTUP_TIMEOUT
+    pub async fn check_devices(&self) -> Result<Vec<crate::tui::DeviceInfo>, String> {
+        let outputs = self.start_device_query(ADB_STARTUP_TIMEOUT).outputs().await;
+        self.finish_device_query(outputs)
+    }
+
+    /// 在后台开始一次 adb devices 查询，调用方等待超时也不会取消查询，之后再用 finish_device_query 取结果；
+    /// timeout 为本机查询的超时，首次查询时 adb server 可能尚未运行，应使用 ADB_STARTUP_TIMEOUT
+    pub fn start_device_query(&self, timeout: Duration) -> DeviceQuery {
+        // 为 adb devices 增加命令级超时，避免 adb 异常挂死；
+        // 远程 server 单独使用更短的超时，不可达时也不会拖住本机结果
+        let now = tokio::time::Instant::now();
+        let deadline = now + timeout;
+        let remote_deadline = now + REMOTE_DEVICES_TIMEOUT;
+
+        // 先启动所有 server 的 adb devices 再依次等待，总耗时取决于最慢的 server 而不是各 server 之和
+        let local = self.spawn_device_query(None);
+        let remotes: Vec<_> = self.adb_config.remote_servers
+            .iter()
+            .map(|server| (server.clone(), self.spawn_device_query(Some(server))))
+            .collect();
+
+        DeviceQuery(tokio::spawn(async move {
+            let local = wait_device_query(local, deadline).await;
+            let mut remote_results = Vec::with_capacity(remotes.len());
+            for (server, query) in remotes {
+                remote_results.push((server, wait_device_query(query, remote_deadline).await));
+            }
+            (local, remote_results)
+        }))
+    }
+
+    /// 合并一次查询中本机与各远程 server 的结果，并记录远程 server 的可用状态
+    pub fn finish_device_query(&self, (local, remotes): QueryOutputs) -> Result<Vec<crate::tui::DeviceInfo>, String> {
+        let (mut devices, daemon_started) = local?;
+        if daemon_started {
+            self.daemon_started.store(true, Ordering::Relaxed);
+        }
+        let mut servers = HashMap::new();
+        for (server, result) in remotes {
+            match result {
+                Ok((remote, _)) => {
+                    self.mark_server(&server, None);
+                    for mut device in remote {
+                        // 与本机设备序列号相同时以本机为准
+                        if devices.iter().any(|d| d.id == device.id) {
+                            continue;
+                        }
+                        servers.insert(device.id.clone(), server.clone());
+                        device.server = Some(server.clone());
+                        devices.push(device);
+                    }
+                }
+                Err(e) => self.mark_server(&server, Some(e)),
+            }
+        }
+        *self.device_servers.lock().unwrap_or_else(|e| e.into_inner()) = servers;
+
+        Ok(devices)
+    }
+
+    /// 启动一次 adb devices -l 查询
+    fn spawn_device_query(&self, server: Option<&str>) -> Result<tokio::process::Child, String> {
+        use std::process::Stdio;
+
+        self.adb_command(server)
+            .args(["devices", "-l"])
+            .stdout(Stdio::piped())
+            // 新版 adb 把 server 启动提示写到 stderr
+            .stderr(Stdio::piped())
+            .kill_on_drop(true) // 超时或被取消时不留下挂起的 adb 进程
+            .spawn()
+            .map_err(|e| format!("执行adb命令失败: {}", e))
+    }
+
+    /// 启动scrcpy（重定向输出以避免干扰TUI）
+    /// 额外参数会先按已安装版本过滤，返回被忽略的参数供调用方提示
+    pub async fn start_scrcpy(&mut self, device_id: Option<&str>, extra_args: &[String]) -> Result<Vec<UnsupportedArg>, String> {
+        use std::process::{Command, Stdio};
+
+        // 停止现有的scrcpy进程
+        self.stop_scrcpy().await;
+
+        let mut cmd = Command::new(&self.scrcpy_exe);
+        cmd.envs(self.scrcpy_envs(device_id));
+        // scrcpy 放在单独的进程组中，没有窗口时可以只向它发送 CTRL_BREAK，见 request_session_exit
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(winapi::um::winbase::CREATE_NEW_PROCESS_GROUP);
+        }
+
+        if let Some(id) = device_id {
+            cmd.arg("-s").arg(id);
+        }
+
+        let (args, unsupported) = scrcpy_compat::filter_args(extra_args, self.scrcpy_version);
+        cmd.args(&args);
+        self.last_args = std::iter::once(self.scrcpy_exe.display().to_string())
+            .chain(device_id.map(|id| ["-s".to_string(), id.to_string()]).into_iter().flatten())
+            .chain(args)
+            .collect();
+
+        // 输出写入会话日志以避免干扰TUI界面，stderr 由后台线程读取以便诊断启动失败，同时转写到日志
+        let log = create_session_log(&session_log_dir(), device_id.unwrap_or("default")).ok();
+        self.session_log = log.as_ref().map(|(path, _)| path.clone());
+        let mut log = log.map(|(_, file)| file);
+        if let Some(file) = log.as_mut() {
+            use std::io::Write;
+            let _ = writeln!(file, "# {}", self.last_args.join(" "));
+        }
+        let stdout = log.as_ref().and_then(|file| file.try_clone().ok()).map_or_else(Stdio::null, Stdio::from);
+        cmd.stdout(stdout)
+           .stderr(Stdio::piped())
+           .stdin(Stdio::null());
+
+        let mut child = cmd.spawn()
+            .map_err(|e| format!("启动scrcpy失败: {}", e))?;
+
+        // 每个会话使用独立缓冲区，避免上一个进程的残留输出混入
+        self.scrcpy_stderr = Arc::new(Mutex::new(Vec::new()));
+        self.scrcpy_fps = Arc::new(Mutex::new(None));
+        if let Some(stderr) = child.stderr.take() {
+            let buffer = Arc::clone(&self.scrcpy_stderr);
+            let fps = Arc::clone(&self.scrcpy_fps);
+            let errors = Arc::clone(&self.scrcpy_errors);
+            let (done, done_rx) = tokio::sync::oneshot::channel::<()>();
+            self.scrcpy_stderr_done = Some(done_rx);
+            std::thread::spawn(move || {
+                use std::io::{BufRead, BufReader, Write};
+                let _done = done;
+
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if let Some(file) = log.as_mut() {
+                        let _ = writeln!(file, "{}", line);
+                    }
+
+                    // 帧率报告每秒一行，单独统计，不占用 stderr 缓冲区
+                    if let Some((current, skipped)) = parse_fps_line(&line) {
+                        let mut stats = fps.lock().unwrap_or_else(|e| e.into_inner());
+                        stats.get_or_insert_with(FpsStats::default).record(current, skipped);
+                        continue;
+                    }
+
+                    if is_notable_stderr_line(&line) {
+                        errors.lock().unwrap_or_else(|e| e.into_inner()).push(line.trim().to_string());
+                    }
+
+                    let mut lines = buffer.lock().unwrap_or_else(|e| e.into_inner());
+                    lines.push(line);
+                    if lines.len() > STDERR_BUFFER_LINES {
+                        lines.remove(0);
+                    }
+                }
+            });
+        }
+
+        self.scrcpy_process = Some(child);
+        self.scrcpy_started_at = Some(Instant::now());
+        self.last_exit = None;
+        Ok(unsupported)
+    }
+
+    /// 当前 scrcpy 进程的 PID
+    pub fn scrcpy_pid(&self) -> Option<u32> {
+        self.scrcpy_process.as_ref().map(|process| process.id())
+    }
+
+    /// 检查scrcpy进程是否还在运行
+    pub async fn is_scrcpy_running(&mut self) -> bool {
+        if let Some(ref mut process) = self.scrcpy_process {
+            match process.try_wait() {
+                Ok(Some(status)) => {
+                    // 进程已结束，记录退出信息供后续诊断
+                    self.last_exit = Some(ScrcpyExit {
+                        runtime: self.scrcpy_started_at.map(|t| t.elapsed()).unwrap_or_default(),
+                        code: status.code(),
+                    });
+                    self.scrcpy_process = None;
+                    self.drain_stderr().await;
+                    false
+                }
+                Ok(None) => {
+                    // 进程仍在运行
+                    true
+                }
+                Err(_) => {
+                    // 出错，假设进程已结束
+                    self.scrcpy_process = None;
+                    false
+                }
+            }
+        } else {
+            false
+        }
+    }
+
+    /// 等待读取线程读完已退出进程的剩余 stderr，之后再分析退出原因才不会漏掉最后几行；
+    /// scrcpy 启动的 adb 子进程可能继承并占用管道，因此最多只等 STDERR_DRAIN_TIMEOUT，等待期间让出执行权
+    async fn drain_stderr(&mut self) {
+        if let Some(done) = self.scrcpy_stderr_done.take() {
+            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, done).await;
+        }
+    }
+
+    /// 获取当前（或刚结束的）scrcpy 会话的 stderr 输出
+    pub fn scrcpy_stderr_lines(&self) -> Vec<String> {
+        self.scrcpy_stderr
+            .lock()
+            .map(|lines| lines.clone())
+            .unwrap_or_default()
+    }
+
+    /// 取出自上次调用以来 scrcpy 输出的错误行
+    pub fn take_scrcpy_errors(&self) -> Vec<String> {
+        self.scrcpy_errors
+            .lock()
+            .map(|mut errors| std::mem::take(&mut *errors))
+            .unwrap_or_default()
+    }
+
+    /// 获取当前会话的帧率统计
+    pub fn fps_stats(&self) -> Option<FpsStats> {
+        self.scrcpy_fps.lock().ok().and_then(|stats| stats.clone())
+    }
+
+    /// 若 scrcpy 启动后立即因参数错误退出，返回被拒绝的参数名
+    pub fn rejected_option(&self) -> Option<String> {
+        let exit = self.last_exit.as_ref()?;
+        if exit.runtime >= IMMEDIATE_EXIT_THRESHOLD {
+            return None;
+        }
+        scrcpy_compat::rejected_option_from_stderr(&self.scrcpy_stderr_lines())
+    }
+
+    /// 停止scrcpy：先请求其正常退出（录像才能写完文件尾），超时仍未退出再强制结束；
+    /// 等待期间让出执行权，不阻塞监控循环
+    pub async fn stop_scrcpy(&mut self) {
+        if let Some(mut process) = self.scrcpy_process.take() {
+            let requested = request_session_exit(process.id());
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while requested && Instant::now() < deadline && matches!(process.try_wait(), Ok(None)) {
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+            finish_stop(process);
+        }
+    }
+}
+
+/// 请求本启动器启动的 scrcpy 正常退出：优先关闭窗口，没有窗口（如 --no-window 录屏）时发送 CTRL_BREAK。
+/// scrcpy 以 CREATE_NEW_PROCESS_GROUP 启动，进程组 ID 就是它的 pid，事件只发给 scrcpy，启动器和其他子进程不受影响
+#[cfg(windows)]
+fn request_session_exit(pid: u32) -> bool {
+    use winapi::um::wincon::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+
+    request_exit(pid) || unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0 }
+}
+
+#[cfg(not(windows))]
+fn request_session_exit(pid: u32) -> bool {
+    request_exit(pid)
+}
+
+/// 结束停止流程：仍未退出的强制结束，并回收进程
+fn finish_stop(mut process: std::process::Child) {
+    if matches!(process.try_wait(), Ok(None)) {
+        let _ = process.kill();
+    }
+    let _ = process.wait();
+}
+
+/// 请求进程正常退出：向其主窗口发送 WM_CLOSE，与用户点击关闭按钮相同；没有窗口时返回 false
+#[cfg(windows)]
+pub fn request_exit(pid: u32) -> bool {
+    crate::window_layout::close_process_window(pid)
+}
+
+/// 请求进程正常退出：发送 SIGTERM
+#[cfg(not(windows))]
+pub fn request_exit(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// 优先使用 scrcpy 目录中的可执行文件，找不到时在 PATH（相当于 where 命令）和给定的安装目录中查找；
+/// 都找不到时仍返回 scrcpy 目录中的路径，由调用方报告"未找到"
+fn locate_executable(scrcpy_dir: &Path, name: &str, install_dirs: &[PathBuf]) -> PathBuf {
+    let bundled = scrcpy_dir.join(name);
+    if bundled.exists() {
+        return bundled;
+    }
+
+    let path_dirs = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    find_executable(name, path_dirs.iter().chain(install_dirs)).unwrap_or(bundled)
+}
+
+/// 在给定目录中查找第一个存在的可执行文件
+fn find_executable<'a>(name: &str, dirs: impl IntoIterator<Item = &'a PathBuf>) -> Option<PathBuf> {
+    dirs.into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// adb 的常见安装位置：Android SDK platform-tools
+fn adb_install_dirs() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = ["ANDROID_HOME", "ANDROID_SDK_ROOT"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .map(|sdk| PathBuf::from(sdk).join("platform-tools"))
+        .collect();
+    if let Some(local) = dirs::data_local_dir() {
+        candidates.push(local.join("Android").join("Sdk").join("platform-tools"));
+    }
+    candidates
+}
+
+/// 包管理器或注册表中已安装的 scrcpy 所在目录：scoop、winget、chocolatey 与 App Paths，
+/// 只返回确实含有 scrcpy.exe 的目录，按上述顺序排列
+pub fn scrcpy_install_dirs() -> Vec<PathBuf> {
+    let program_data = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("C:\\ProgramData"));
+    let mut candidates = Vec::new();
+
+    // scoop：用户安装与全局安装
+    let scoop = std::env::var_os("SCOOP")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join("scoop")));
+    let scoop_global = std::env::var_os("SCOOP_GLOBAL")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| program_data.join("scoop"));
+    for root in scoop.into_iter().chain(Some(scoop_global)) {
+        candidates.push(root.join("apps").join("scrcpy").join("current"));
+    }
+
+    // winget：压缩包解压在 Packages\Genymobile.scrcpy_*\scrcpy-win64-* 下，Links 中只有命令别名
+    if let Some(local) = dirs::data_local_dir() {
+        let winget = local.join("Microsoft").join("WinGet");
+        if let Ok(entries) = std::fs::read_dir(winget.join("Packages")) {
+            candidates.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("Genymobile.scrcpy"))),
+            );
+        }
+        candidates.push(winget.join("Links"));
+    }
+
+    // chocolatey：压缩包解压在 lib\scrcpy\tools 下
+    let chocolatey = std::env::var_os("ChocolateyInstall")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| program_data.join("chocolatey"));
+    candidates.push(chocolatey.join("lib").join("scrcpy").join("tools"));
+
+    candidates.extend(registry_scrcpy_dirs());
+    candidates.iter().filter_map(|dir| scrcpy_dir_in(dir)).collect()
+}
+
+/// 目录本身或其下一层子目录含有 scrcpy.exe 时返回该目录；有多个版本子目录时取名称最大（通常最新）的一个
+fn scrcpy_dir_in(dir: &Path) -> Option<PathBuf> {
+    if dir.join("scrcpy.exe").is_file() {
+        return Some(dir.to_path_buf());
+    }
+    let mut nested: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("scrcpy.exe").is_file())
+        .collect();
+    nested.sort();
+    nested.pop()
+}
+
+/// 注册表 App Paths 中登记的 scrcpy.exe 所在目录，依次查当前用户与本机
+#[cfg(windows)]
+fn registry_scrcpy_dirs() -> Vec<PathBuf> {
+    use std::ffi::{OsStr, OsString};
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ};
+
+    let subkey: Vec<u16> = OsStr::new("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths\\scrcpy.exe")
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE]
+        .into_iter()
+        .filter_map(|root| {
+            let mut buffer = [0u16; 1024];
+            let mut size = (buffer.len() * 2) as u32;
+            let status = unsafe {
+                RegGetValueW(
+                    root,
+                    subkey.as_ptr(),
+                    std::ptr::null(),
+                    RRF_RT_REG_SZ,
+                    std::ptr::null_mut(),
+                    buffer.as_mut_ptr().cast(),
+                    &mut size,
+                )
+            };
+            if status != 0 {
+                return None;
+            }
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            let exe = OsString::from_wide(&buffer[..len]);
+            let exe = PathBuf::from(exe.to_string_lossy().trim_matches('"'));
+            exe.parent().map(Path::to_path_buf)
+        })
+        .collect()
+}
+
+/// 注册表 App Paths 中登记的 scrcpy.exe 所在目录：非 Windows 平台没有注册表
+#[cfg(not(windows))]
+fn registry_scrcpy_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// 判断 scrcpy stderr 中需要显示到日志面板的行：错误、编码器异常、server 推送失败等
+/// Java 异常的堆栈行（"at ..."）不转发，只保留异常本身
+fn is_notable_stderr_line(line: &str) -> bool {
+    let line = line.trim();
+    if line.starts_with("at ") {
+        return false;
+    }
+    line.starts_with("ERROR:")
+        || line.starts_with("adb: error")
+        || line.contains("Exception")
+}
+
+/// 判断序列号是否为无线（TCP）连接的设备，例如 "192.168.1.5:5555" 或 mDNS 发现的设备
+pub fn is_tcp_serial(serial: &str) -> bool {
+    serial.contains("._adb-tls-connect._tcp")
+        || serial
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// 二维码配对使用的服务名与配对码
+///
+/// 手机扫描 `WIFI:T:ADB;S:<服务名>;P:<配对码>;;` 后，会以该服务名广播 `_adb-tls-pairing._tcp`，
+/// 电脑从 `adb mdns services` 中找到它的地址后用配对码执行 adb pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrPairing {
+    pub name: String,
+    pub password: String,
+}
+
+impl QrPairing {
+    /// 生成随机的服务名与 6 位配对码，长度保证二维码不超过版本 2（25x25）
+    pub fn generate() -> Self {
+        use std::hash::{BuildHasher, Hasher};
+
+        // RandomState 的种子来自系统随机数，再混入当前时间
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+        );
+        let random = hasher.finish();
+        Self {
+            name: format!("adb-{:04x}", random & 0xffff),
+            password: format!("{:06}", (random >> 16) % 1_000_000),
+        }
+    }
+
+    /// 二维码内容
+    pub fn payload(&self) -> String {
+        format!("WIFI:T:ADB;S:{};P:{};;", self.name, self.password)
+    }
+}
+
+/// 从 `adb mdns services` 输出中找到指定服务名的配对地址，例如
+/// "adb-1a2b  _adb-tls-pairing._tcp.  192.168.1.5:37123"
+pub fn find_pairing_address(output: &str, name: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (service, kind, address) = (fields.next()?, fields.next()?, fields.next()?);
+        (service == name && kind.starts_with("_adb-tls-pairing._tcp")).then(|| address.to_string())
+    })
+}
+
+/// 判断序列号是否为 Android 模拟器，例如 "emulator-5554"
+pub fn is_emulator_serial(serial: &str) -> bool {
+    serial
+        .strip_prefix("emulator-")
+        .is_some_and(|port| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// 从 `ip addr show` 输出中取出 IPv4 地址，例如 "inet 192.168.1.5/24 brd ..."
+fn parse_inet_addr(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("inet ")?;
+        let addr = rest.split(['/', ' ']).next()?;
+        (!addr.starts_with("127.")).then(|| addr.to_string())
+    })
+}
+
+/// 从 `ip route` 输出中取出 wlan 网卡的源地址，例如 "... dev wlan0 proto kernel scope link src 192.168.1.5"
+fn parse_route_src(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("wlan") || line.contains("dev w"))
+        .find_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|w| *w == "src")?;
+            words.next().map(|ip| ip.to_string())
+        })
+}
+
+/// 解析 `adb devices -l` 输出，列出所有状态的设备（包括未授权、离线等）
+///
+/// 状态后的扩展字段形如 "usb:1-1 product:a52qnsxx model:SM_A525F device:a52q transport_id:3"，
+/// 不带 -l 的输出同样可以解析。设备从标题行 "List of devices attached" 之后开始，
+/// 标题行之前的内容（例如 adb server 启动时的提示）不会被当成设备
+pub fn parse_devices_output(output: &str) -> Vec<crate::tui::DeviceInfo> {
+    // 预分配容量以减少重新分配
+    let mut devices = Vec::with_capacity(4); // 大多数情况下不会超过4个设备
+
+    let listing = output
+        .lines()
+        .skip_while(|line| !line.trim().starts_with("List of devices"))
+        .skip(1);
+    for line in listing {
+        let line = line.trim();
+        if line.is_empty() || is_daemon_banner(line) {
+            continue;
+        }
+
+        // 通常以制表符分隔，-l 输出和备用情况按空白分隔
+        let (device_id, rest) = match line.split_once('\t') {
+            Some((id, rest)) => (id, rest.trim()),
+            None => match line.split_once(char::is_whitespace) {
+                Some((id, rest)) => (id, rest.trim()),
+                None => continue,
+            },
+        };
+
+        // 状态可能包含空格（如 "no permissions (...)"），扩展字段从第一个已知的 key:value 开始
+        let mut state_words = Vec::new();
+        let mut model = None;
+        let mut product = None;
+        let mut transport_id = None;
+        let mut in_fields = false;
+        for word in rest.split_whitespace() {
+            match word.split_once(':') {
+                Some(("model", value)) => model = Some(value.replace('_', " ")),
+                Some(("product", value)) => product = Some(value.to_string()),
+                Some(("transport_id", value)) => transport_id = value.parse().ok(),
+                Some(("usb" | "device", _)) => {}
+                _ if !in_fields => {
+                    state_words.push(word);
+                    continue;
+                }
+                _ => {}
+            }
+            in_fields = true;
+        }
+
+        // 模拟器的型号（如 sdk_gphone64_x86_64）没有意义，统一显示为模拟器
+        let name = if is_emulator_serial(device_id) {
+            "Android模拟器".to_string()
+        } else {
+            model.unwrap_or_else(|| "Android设备".to_string())
+        };
+
+        devices.push(crate::tui::DeviceInfo {
+            id: device_id.to_string(),
+            name,
+            status: DeviceStatus::from_adb(&state_words.join(" ")),
+            battery: None,
+            nickname: None,
+            details: None,
+            product,
+            transport_id,
+            server: None,
+            last_error: None,
+        });
+    }
+
+    devices
+}
+
+/// adb devices 命令的超时，超过即视为 adb 无响应
+pub const ADB_DEVICES_TIMEOUT: Duration = Duration::from_secs(2);
+/// 首次 adb devices 的超时：adb server 未运行时会先启动 server，冷启动常常需要数秒
+pub const ADB_STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+/// 远程 adb server 的查询超时，低于配置了远程 server 时每轮等待的下限（1 秒），
+/// server 不可达时本轮仍能拿到本机设备，该 server 记为不可用
+const REMOTE_DEVICES_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// 一次 adb devices 查询的原始结果：本机，以及各远程 server
+type QueryOutputs = (QueryResult, Vec<(String, QueryResult)>);
+type QueryResult = Result<(Vec<crate::tui::DeviceInfo>, bool), String>;
+
+/// 在后台进行的 adb devices 查询
+pub struct DeviceQuery(tokio::task::JoinHandle<QueryOutputs>);
+
+impl DeviceQuery {
+    /// 等待查询结束；在 select! 中等待超时后可以继续等待同一个查询，不会中断正在执行的 adb
+    pub async fn outputs(&mut self) -> QueryOutputs {
+        match (&mut self.0).await {
+            Ok(outputs) => outputs,
+            Err(e) => (Err(format!("adb devices 查询异常结束: {}", e)), Vec::new()),
+        }
+    }
+}
+
+/// 等待 adb devices -l 查询结束并解析结果，同时返回 adb server 是否由本次查询启动
+async fn wait_device_query(
+    query: Result<tokio::process::Child, String>,
+    deadline: tokio::time::Instant,
+) -> Result<(Vec<crate::tui::DeviceInfo>, bool), String> {
+    let output = match tokio::time::timeout_at(deadline, query?.wait_with_output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("执行adb命令失败: {}", e)),
+        Err(_) => return Err("adb devices 命令超时".to_string()),
+    };
+
+    if !output.status.success() {
+        return Err("adb devices 命令执行失败".to_string());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let started = daemon_started(&stdout) || daemon_started(&String::from_utf8_lossy(&output.stderr));
+    Ok((parse_devices_output(&stdout), started))
+}
+
+/// adb server 未运行时 adb 命令先输出的提示行，例如
+/// "* daemon not running; starting now at tcp:5037"、"* daemon started successfully"，
+/// 以及版本不一致时的 "adb server version (41) doesn't match this client (39); killing..."
+fn is_daemon_banner(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("* daemon")
+        || line.starts_with("* failed to start daemon")
+        || (line.starts_with("adb server ") && line.contains("killing"))
+}
+
+/// 输出中是否有 adb server 启动成功的提示
+fn daemon_started(output: &str) -> bool {
+    output.lines().any(|line| is_daemon_banner(line) && line.contains("started successfully"))
+}
+
+/// 把 "host[:port]" 拆成主机与端口，端口默认为 adb server 的 5037
+fn split_server(server: &str) -> (&str, u16) {
+    match server.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => (server, 5037),
+        },
+        None => (server, 5037),
+    }
+}
+
+/// 解析 getprop 输出，行格式为 "[ro.product.model]: [Pixel 7]"
+fn parse_getprop(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once("]: [")?;
+            let key = key.strip_prefix('[')?;
+            let value = value.strip_suffix(']')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// 取出 `wm size` / `wm density` 输出中的值，有 "Override" 行时优先使用覆盖值
+fn wm_value<'a>(output: &'a str, what: &str) -> Option<&'a str> {
+    let find = |prefix: &str| {
+        let prefix = format!("{} {}:", prefix, what);
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(prefix.as_str()).map(str::trim))
+    };
+    find("Override").or_else(|| find("Physical"))
+}
+
+/// 解析 `wm size` 输出，例如 "Physical size: 1080x2400"
+fn parse_wm_size(output: &str) -> Option<(u32, u32)> {
+    let (width, height) = wm_value(output, "size")?.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// 解析 `dumpsys battery` 输出中的电量与充电状态
+fn parse_battery(output: &str) -> Option<BatteryStatus> {
+    let mut level = None;
+    let mut scale = 100u32;
+    let mut status = 0u32;
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "level" => level = value.parse::<u32>().ok(),
+            "scale" => scale = value.parse().ok().filter(|s| *s > 0).unwrap_or(100),
+            "status" => status = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    // status: 2 = BATTERY_STATUS_CHARGING
+    level.map(|level| BatteryStatus {
+        level: (level * 100 / scale).min(100) as u8,
+        charging: status == 2,
+    })
+}
+
+/// 解析 --print-fps 的输出，例如 "INFO: 58 fps (+2 frames skipped)"，返回 (帧率, 跳帧数)
+fn parse_fps_line(line: &str) -> Option<(u32, u32)> {
+    let rest = line.trim().strip_prefix("INFO:")?.trim();
+    let (fps, rest) = rest.split_once(" fps")?;
+    let fps = fps.trim().parse().ok()?;
+    let skipped = rest
+        .trim()
+        .strip_prefix("(+")
+        .and_then(|r| r.split_whitespace().next())
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+    Some((fps, skipped))
+}
+
+/// 解析 `scrcpy --list-encoders` 输出中的视频编码器
+/// 行格式形如 "--video-codec=h264 --video-encoder=c2.qti.avc.encoder   (hw) [vendor]"
+fn parse_video_encoders(output: &str) -> Vec<VideoEncoder> {
+    let value_of = |line: &str, key: &str| -> Option<String> {
+        let start = line.find(key)? + key.len();
+        let value = line[start..].split_whitespace().next()?;
+        Some(value.trim_matches('\'').to_string())
+    };
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let name = value_of(line, "--video-encoder=")?;
+            let codec = value_of(line, "--video-codec=").unwrap_or_else(|| "h264".to_string());
+            Some(VideoEncoder {
+                codec,
+                name,
+                hardware: !line.contains("(sw)"),
+            })
+        })
+        .collect()
+}
+
+/// 编码器探测的结果，scrcpy 正常退出时输出即为完整列表
+fn parse_encoder_probe(output: &str) -> Result<Vec<VideoEncoder>, String> {
+    Ok(parse_video_encoders(output))
+}
+
+/// 统计 `scrcpy --list-cameras` 输出中的摄像头
+/// 行格式形如 "--camera-id=0    (back, 4000x3000, fps=[15, 30])"；
+/// 没有 "List of cameras" 标题说明 scrcpy 未能列出摄像头（例如推送 server 失败），不能当作 0 个
+fn count_cameras(output: &str) -> Result<usize, String> {
+    if !output.contains("List of cameras") {
+        let reason = output.lines().rev().map(str::trim).find(|line| !line.is_empty()).unwrap_or("没有输出");
+        return Err(format!("scrcpy --list-cameras 未返回摄像头列表: {}", reason));
+    }
+    Ok(output.lines().filter(|line| line.trim_start().starts_with("--camera-id=")).count())
+}
+
+/// 运行时间短于此值的退出视为崩溃
+const CRASH_RUNTIME: Duration = Duration::from_secs(10);
+
+/// 连续崩溃达到该次数后停止自动重启
+pub const CRASH_LIMIT: u32 = 5;
+
+/// 默认每台设备每小时最多自动重启的次数
+pub const RESTART_LIMIT_PER_HOUR: u32 = 10;
+
+/// 统计自动重启次数的时间窗口
+const RESTART_WINDOW: Duration = Duration::from_secs(3600);
+
+/// 记录各设备上 scrcpy 的连续崩溃次数与自动重启次数，用于计算重启退避时间并在重启过多时停止重试
+#[derive(Debug, Default)]
+pub struct CrashTracker {
+    crashes: HashMap<String, u32>,
+    /// 最近一小时内每次自动重启的时间
+    restarts: HashMap<String, Vec<Instant>>,
+    /// 因重启过多而停止重试的设备及最后一次的错误
+    blocked: HashMap<String, String>,
+}
+
+impl CrashTracker {
+    /// 记录一次 scrcpy 退出，返回该设备的连续崩溃次数；正常运行一段时间后退出会清零
+    pub fn record_exit(&mut self, serial: &str, runtime: Duration) -> u32 {
+        if runtime >= CRASH_RUNTIME {
+            self.crashes.remove(serial);
+            return 0;
+        }
+        let count = self.crashes.entry(serial.to_string()).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    /// 清除设备的崩溃与重启记录（用户手动重新投屏或设备断开时）
+    pub fn reset(&mut self, serial: &str) {
+        self.crashes.remove(serial);
+        self.restarts.remove(serial);
+        self.blocked.remove(serial);
+    }
+
+    /// 记录一次自动重启，返回该设备最近一小时内的自动重启次数（含本次）
+    pub fn record_restart(&mut self, serial: &str, now: Instant) -> u32 {
+        let restarts = self.restarts.entry(serial.to_string()).or_default();
+        restarts.retain(|t| now.duration_since(*t) < RESTART_WINDOW);
+        restarts.push(now);
+        restarts.len() as u32
+    }
+
+    /// 停止该设备的自动重启，记下最后一次的错误供界面显示
+    pub fn block(&mut self, serial: &str, error: String) {
+        self.blocked.insert(serial.to_string(), error);
+    }
+
+    /// 设备因重启过多而停止重试时返回最后一次的错误
+    pub fn blocked(&self, serial: &str) -> Option<&str> {
+        self.blocked.get(serial).map(String::as_str)
+    }
+
+    /// 连续崩溃若干次后，下次重启前需要等待的时间：1s → 5s → 30s
+    pub fn backoff(crashes: u32) -> Duration {
+        match crashes {
+            0 => Duration::ZERO,
+            1 => Duration::from_secs(1),
+            2 => Duration::from_secs(5),
+            _ => Duration::from_secs(30),
+        }
+    }
+}
+
+/// 投屏设备连接检查的间隔与超时（秒）
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+pub const HEALTH_CHECK_TIMEOUT: u64 = 3;
+/// 连续检查失败达到该次数后认为会话已僵死
+const HEALTH_CHECK_FAILURES: u32 = 2;
+
+/// 连接检查结果引起的状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthEvent {
+    /// 没有变化
+    Unchanged,
+    /// 设备刚被判定为无响应，需要重启会话
+    Degraded,
+    /// 无响应的设备恢复正常
+    Recovered,
+}
+
+/// 定期检查投屏设备是否仍能执行 adb 命令，发现 adb devices 显示在线但会话已僵死的设备
+#[derive(Debug, Default)]
+pub struct HealthCheck {
+    last_check: Option<Instant>,
+    failures: u32,
+    degraded: HashSet<String>,
+}
+
+impl HealthCheck {
+    /// 是否到了下一次检查的时间
+    pub fn due(&self, now: Instant) -> bool {
+        self.last_check.is_none_or(|t| now.duration_since(t) >= HEALTH_CHECK_INTERVAL)
+    }
+
+    /// started 时开始的检查是否仍然有效：检查期间开始了新会话时丢弃旧的结果
+    pub fn is_current(&self, started: Instant) -> bool {
+        self.last_check.is_none_or(|t| started >= t)
+    }
+
+    /// 记录一次检查结果
+    pub fn record(&mut self, serial: &str, healthy: bool, now: Instant) -> HealthEvent {
+        self.last_check = Some(now);
+        if healthy {
+            self.failures = 0;
+            return if self.degraded.remove(serial) { HealthEvent::Recovered } else { HealthEvent::Unchanged };
+        }
+        self.failures += 1;
+        // 已判定为无响应的设备只重启一次会话，之后继续检查直到恢复
+        if self.failures >= HEALTH_CHECK_FAILURES && self.degraded.insert(serial.to_string()) {
+            HealthEvent::Degraded
+        } else {
+            HealthEvent::Unchanged
+        }
+    }
+
+    /// 新会话开始时重新计数，启动后等待一个完整间隔再检查
+    pub fn restart(&mut self, now: Instant) {
+        self.last_check = Some(now);
+        self.failures = 0;
+    }
+
+    /// 设备是否被判定为无响应
+    pub fn is_degraded(&self, serial: &str) -> bool {
+        self.degraded.contains(serial)
+    }
+
+    /// 忘记已经不在设备列表中的设备
+    pub fn retain(&mut self, is_listed: impl Fn(&str) -> bool) {
+        self.degraded.retain(|serial| is_listed(serial));
+    }
+}
+
+/// scrcpy 可执行文件的检查间隔
+const BINARY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 文件的修改时间与大小，用于判断文件是否被替换
+pub type FileStamp = (SystemTime, u64);
+
+/// 读取文件的修改时间与大小，文件不存在或无法读取时返回 None
+pub fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// 定期检查 scrcpy 可执行文件，发现安装了新版本
+///
+/// 安装程序覆盖文件需要一段时间，连续两次检查结果相同才认为替换完成，避免读到写了一半的文件
+#[derive(Debug, Default)]
+pub struct BinaryWatch {
+    known: Option<FileStamp>,
+    pending: Option<FileStamp>,
+    last_check: Option<Instant>,
+}
+
+impl BinaryWatch {
+    /// 是否到了下一次检查的时间
+    pub fn due(&self, now: Instant) -> bool {
+        self.last_check.is_none_or(|t| now.duration_since(t) >= BINARY_CHECK_INTERVAL)
+    }
+
+    /// 记录一次检查结果，文件被替换且已稳定时返回 true
+    pub fn record(&mut self, stamp: Option<FileStamp>, now: Instant) -> bool {
+        self.last_check = Some(now);
+        // 更新过程中文件可能暂时不存在，等它重新出现
+        let Some(stamp) = stamp else {
+            return false;
+        };
+        if self.known.is_none() || self.known == Some(stamp) {
+            self.known = Some(stamp);
+            self.pending = None;
+            false
+        } else if self.pending == Some(stamp) {
+            self.known = Some(stamp);
+            self.pending = None;
+            true
+        } else {
+            self.pending = Some(stamp);
+            false
+        }
+    }
+}
+
+/// 跟踪通过 adb connect 连接的无线设备，掉线后按退避间隔自动重连
+#[derive(Debug, Default)]
+pub struct WirelessReconnector {
+    /// 序列号 -> 掉线后的 (已重连次数, 下次重连时间)，在线时为 None
+    devices: HashMap<String, Option<(u32, Instant)>>,
+    /// 正在后台重连的设备，结果返回前不再安排重连
+    pending: HashSet<String>,
+}
+
+impl WirelessReconnector {
+    /// 记录一台在线的无线设备；mDNS 发现的设备由 adb 自动重连，不需要跟踪
+    pub fn track(&mut self, serial: &str) {
+        if is_tcp_serial(serial) && !serial.contains("._adb-tls-connect._tcp") {
+            self.devices.entry(serial.to_string()).or_insert(None);
+        }
+    }
+
+    /// 不再重连该设备（用户主动断开时）
+    pub fn forget(&mut self, serial: &str) {
+        self.devices.remove(serial);
+        self.pending.remove(serial);
+    }
+
+    /// 根据设备是否在线更新状态，返回刚刚掉线的设备
+    pub fn update(&mut self, is_online: impl Fn(&str) -> bool, now: Instant) -> Vec<String> {
+        let mut dropped = Vec::new();
+        for (serial, state) in self.devices.iter_mut() {
+            if is_online(serial) {
+                *state = None;
+            } else if state.is_none() {
+                *state = Some((0, now));
+                dropped.push(serial.clone());
+            }
+        }
+        dropped
+    }
+
+    /// 取出到期需要重连的设备及其重连次数，重连结束前（见 finish）不会再次取出
+    pub fn due(&mut self, now: Instant) -> Vec<(String, u32)> {
+        let mut due = Vec::new();
+        for (serial, state) in self.devices.iter_mut() {
+            if let Some((attempts, next_at)) = state {
+                if now >= *next_at && !self.pending.contains(serial) {
+                    *attempts += 1;
+                    self.pending.insert(serial.clone());
+                    due.push((serial.clone(), *attempts));
+                }
+            }
+        }
+        due
+    }
+
+    /// 一次重连结束，从结束时起按退避间隔安排下一次重连
+    pub fn finish(&mut self, serial: &str, now: Instant) {
+        self.pending.remove(serial);
+        if let Some(Some((attempts, next_at))) = self.devices.get_mut(serial) {
+            *next_at = now + Self::backoff(*attempts);
+        }
+    }
+
+    /// 第 n 次重连失败后的等待时间：2s → 5s → 10s，之后保持 30s
+    fn backoff(attempts: u32) -> Duration {
+        match attempts {
+            0 | 1 => Duration::from_secs(2),
+            2 => Duration::from_secs(5),
+            3 => Duration::from_secs(10),
+            _ => Duration::from_secs(30),
+        }
+    }
+}
+
+impl Drop for DeviceMonitor {
+    /// 退出时无法异步等待，在当前线程等待 scrcpy 正常退出
+    fn drop(&mut self) {
+        if let Some(mut process) = self.scrcpy_process.take() {
+            let requested = request_session_exit(process.id());
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while requested && Instant::now() < deadline && matches!(process.try_wait(), Ok(None)) {
+                std::thread::sleep(STOP_POLL_INTERVAL);
+            }
+            finish_stop(process);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parses_device_states() {
+        let output = "List of devices attached\r\nR58M123456\tdevice\r\nemulator-5554\toffline\r\n192.168.1.5:5555\tunauthorized\r\nABCDEF  recovery\r\n0123456789\tno permissions (user in plugdev group); see [http://developer.android.com/tools/device.html]\r\n\r\n";
+        let devices = parse_devices_output(output);
+        let states: Vec<(&str, DeviceStatus)> = devices.iter().map(|d| (d.id.as_str(), d.status)).collect();
+        assert_eq!(states, vec![
+            ("R58M123456", DeviceStatus::Connected),
+            ("emulator-5554", DeviceStatus::Offline),
+            ("192.168.1.5:5555", DeviceStatus::Unauthorized),
+            ("ABCDEF", DeviceStatus::Recovery),
+            ("0123456789", DeviceStatus::NoPermissions),
+        ]);
+    }
+
+    #[test]
+    fn falls_back_to_other_install_dirs() {
+        let root = std::env::temp_dir().join(format!("scrcpy-launcher-locate-{}", std::process::id()));
+        let bundled = root.join("scrcpy");
+        let sdk = root.join("platform-tools");
+        std::fs::create_dir_all(&bundled).unwrap();
+        std::fs::create_dir_all(&sdk).unwrap();
+        std::fs::write(sdk.join("adb.exe"), b"").unwrap();
+
+        let dirs = vec![root.join("missing"), sdk.clone()];
+        assert_eq!(locate_executable(&bundled, "adb.exe", &dirs), sdk.join("adb.exe"));
+        // 找不到时返回 scrcpy 目录中的路径
+        assert_eq!(locate_executable(&bundled, "scrcpy.exe", &dirs), bundled.join("scrcpy.exe"));
+
+        // scrcpy 目录中的文件优先
+        std::fs::write(bundled.join("adb.exe"), b"").unwrap();
+        assert_eq!(locate_executable(&bundled, "adb.exe", &dirs), bundled.join("adb.exe"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn picks_notable_stderr_lines() {
+        assert!(is_notable_stderr_line("ERROR: Could not push scrcpy-server"));
+        assert!(is_notable_stderr_line("adb: error: failed to copy 'scrcpy-server' to '/data/local/tmp/scrcpy-server.jar'"));
+        assert!(is_notable_stderr_line("java.lang.IllegalStateException: android.media.MediaCodec$CodecException: Error 0xfffffff4"));
+        assert!(!is_notable_stderr_line("    at android.media.MediaCodec.native_dequeueOutputBuffer(Native Method)"));
+        assert!(!is_notable_stderr_line("INFO: Renderer: direct3d"));
+        assert!(!is_notable_stderr_line("WARN: Demuxer 'audio': stream explicitly disabled by the device"));
+    }
+
+    #[test]
+    fn parses_long_device_listing() {
+        let output = "List of devices attached\nR58M123456             device usb:1-1 product:a52qnsxx model:SM_A525F device:a52q transport_id:3\n192.168.1.5:5555       unauthorized transport_id:7\nemulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1\n0123456789             no permissions (user in plugdev group); see [http://developer.android.com/tools/device.html] usb:1-2 transport_id:4\n";
+        let devices = parse_devices_output(output);
+        assert_eq!(devices.len(), 4);
+
+        assert_eq!(devices[0].status, DeviceStatus::Connected);
+        assert_eq!(devices[0].name, "SM A525F");
+        assert_eq!(devices[0].product.as_deref(), Some("a52qnsxx"));
+        assert_eq!(devices[0].transport_id, Some(3));
+
+        assert_eq!(devices[1].status, DeviceStatus::Unauthorized);
+        assert_eq!(devices[1].name, "Android设备");
+        assert_eq!(devices[1].transport_id, Some(7));
+
+        assert_eq!(devices[2].name, "Android模拟器");
+        assert_eq!(devices[3].status, DeviceStatus::NoPermissions);
+        assert_eq!(devices[3].transport_id, Some(4));
+    }
+
+    #[test]
+    fn skips_daemon_banner() {
+        let output = "* daemon not running; starting now at tcp:5037\r\n* daemon started successfully\r\nList of devices attached\r\nR58M123456\tdevice\r\n\r\n";
+        let devices = parse_devices_output(output);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, "R58M123456");
+        assert!(daemon_started(output));
+
+        // 版本不一致时 adb 会先结束旧 server 再启动，提示行出现在标题行之后也不会被当成设备
+        let output = "List of devices attached\nadb server version (41) doesn't match this client (39); killing...\n* daemon started successfully\nemulator-5554\tdevice\n";
+        let devices = parse_devices_output(output);
+        assert_eq!(devices.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(), vec!["emulator-5554"]);
+        assert!(!daemon_started("List of devices attached\nR58M123456\tdevice\n"));
+    }
+
+    /// 随机生成的序列号：USB、模拟器、IP 地址与 mDNS 无线连接
+    fn generated_serial() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[A-Z0-9]{8,16}",
+            "emulator-55[0-9]{2}",
+            (1u8..=254, 1024u16..).prop_map(|(host, port)| format!("192.168.1.{}:{}", host, port)),
+            "adb-[A-Z0-9]{10}-[A-Za-z0-9]{6}\\._adb-tls-connect\\._tcp",
+        ]
+    }
+
+    /// adb 输出的设备状态与解析后应得到的状态
+    fn generated_state() -> impl Strategy<Value = (&'static str, DeviceStatus)> {
+        prop_oneof![
+            Just(("device", DeviceStatus::Connected)),
+            Just(("offline", DeviceStatus::Offline)),
+            Just(("unauthorized", DeviceStatus::Unauthorized)),
+            Just(("recovery", DeviceStatus::Recovery)),
+            Just(("sideload", DeviceStatus::Sideload)),
+            Just((
+                "no permissions (user in plugdev group); see [http://developer.android.com/tools/device.html]",
+                DeviceStatus::NoPermissions,
+            )),
+        ]
+    }
+
+    proptest! {
+        /// 制表符或空格分隔、带或不带 -l 扩展字段、CRLF 换行以及 adb server 启动提示的各种组合
+        #[test]
+        fn parses_generated_listings(
+            entries in prop::collection::vec(
+                (generated_serial(), generated_state(), "\t| {1,12}", any::<bool>(), prop::option::of(0u32..100)),
+                0..6,
+            ),
+            newline in prop_oneof![Just("\n"), Just("\r\n")],
+            banner in any::<bool>(),
+        ) {
+            let mut output = String::new();
+            if banner {
+                output.push_str(&format!("* daemon not running; starting now at tcp:5037{0}* daemon started successfully{0}", newline));
+            }
+            output.push_str("List of devices attached");
+            output.push_str(newline);
+            for (serial, (state, _), separator, long, transport_id) in &entries {
+                output.push_str(&format!("{}{}{}", serial, separator, state));
+                if *long {
+                    output.push_str(" usb:1-1 product:a52qnsxx model:SM_A525F device:a52q");
+                }
+                if let Some(id) = transport_id {
+                    output.push_str(&format!(" transport_id:{}", id));
+                }
+                output.push_str(newline);
+            }
+            output.push_str(newline);
+
+            let devices = parse_devices_output(&output);
+            prop_assert_eq!(devices.len(), entries.len());
+            for (device, (serial, (_, status), _, long, transport_id)) in devices.iter().zip(&entries) {
+                prop_assert_eq!(&device.id, serial);
+                prop_assert_eq!(device.status, *status);
+                prop_assert_eq!(device.product.is_some(), *long);
+                prop_assert_eq!(device.transport_id, *transport_id);
+            }
+        }
+
+        #[test]
+        fn survives_arbitrary_output(output in "(\\PC*\r?\n){0,8}") {
+            let devices = parse_devices_output(&output);
+            prop_assert!(devices.iter().all(|device| !device.id.is_empty()));
+        }
+    }
+
+    #[test]
+    fn detects_tcp_serials() {
+        assert!(is_tcp_serial("192.168.1.5:5555"));
+        assert!(is_tcp_serial("adb-R58M123456-AbCdEf._adb-tls-connect._tcp"));
+        assert!(!is_tcp_serial("R58M123456"));
+        assert!(!is_tcp_serial("emulator-5554"));
+    }
+
+    #[test]
+    fn detects_emulator_serials() {
+        assert!(is_emulator_serial("emulator-5554"));
+        assert!(!is_emulator_serial("emulator-"));
+        assert!(!is_emulator_serial("emulator-abc"));
+        assert!(!is_emulator_serial("R58M123456"));
+    }
+
+    #[test]
+    fn builds_device_name_from_getprop() {
+        let output = "[ro.build.version.release]: [14]\r\n[ro.build.version.sdk]: [34]\r\n[ro.product.manufacturer]: [Xiaomi]\r\n[ro.product.model]: [2211133C]\r\n[persist.sys.locale]: []\r\n";
+        let metadata = DeviceMetadata::from_getprop(output);
+        assert_eq!(metadata.sdk, Some(34));
+        assert_eq!(metadata.props.get("persist.sys.locale").map(String::as_str), Some(""));
+        assert_eq!(metadata.display_name().as_deref(), Some("Xiaomi 2211133C (Android 14)"));
+
+        let metadata = DeviceMetadata::from_getprop("[ro.product.manufacturer]: [samsung]\n[ro.product.model]: [SM-S9180]\n");
+        assert_eq!(metadata.display_name().as_deref(), Some("Samsung SM-S9180"));
+
+        let metadata = DeviceMetadata::from_getprop("[ro.product.manufacturer]: [Xiaomi]\n[ro.product.model]: [Xiaomi 13]\n");
+        assert_eq!(metadata.display_name().as_deref(), Some("Xiaomi 13"));
+
+        assert_eq!(DeviceMetadata::from_getprop("").display_name(), None);
+    }
+
+    #[test]
+    fn parses_battery_status() {
+        let output = "Current Battery Service state:\r\n  AC powered: false\r\n  USB powered: true\r\n  status: 2\r\n  health: 2\r\n  level: 85\r\n  scale: 100\r\n";
+        assert_eq!(parse_battery(output), Some(BatteryStatus { level: 85, charging: true }));
+
+        let output = "  status: 3\n  level: 40\n  scale: 50\n";
+        assert_eq!(parse_battery(output), Some(BatteryStatus { level: 80, charging: false }));
+
+        assert_eq!(parse_battery("Can't find service: battery"), None);
+    }
+
+    #[test]
+    fn parses_fps_reports() {
+        assert_eq!(parse_fps_line("INFO: 60 fps"), Some((60, 0)));
+        assert_eq!(parse_fps_line("INFO: 58 fps (+2 frames skipped)"), Some((58, 2)));
+        assert_eq!(parse_fps_line("INFO: Renderer: direct3d"), None);
+
+        let mut stats = FpsStats::default();
+        stats.record(60, 0);
+        stats.record(30, 3);
+        assert_eq!((stats.min, stats.max, stats.current, stats.skipped), (30, 60, 30, 3));
+        assert!((stats.average - 45.0).abs() < f64::EPSILON);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn caches_only_successful_probes() {
+        async fn finish(probe: &ScrcpyProbe<Vec<VideoEncoder>>) -> Vec<(String, Result<Vec<VideoEncoder>, String>)> {
+            loop {
+                let finished = probe.take_finished();
+                if !finished.is_empty() {
+                    return finished;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        // 用 false / true 代替 scrcpy：非零退出码视为失败，不缓存，之后可以重新探测
+        let probe = ScrcpyProbe::new("--list-encoders", parse_encoder_probe);
+        probe.start(Path::new("false"), Vec::new(), "R58M123456");
+        assert!(finish(&probe).await[0].1.is_err());
+        assert_eq!(probe.cached("R58M123456"), None);
+
+        probe.start(Path::new("true"), Vec::new(), "R58M123456");
+        assert_eq!(finish(&probe).await[0].1, Ok(Vec::new()));
+        assert_eq!(probe.cached("R58M123456"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn parses_encoder_list() {
+        let output = "[server] INFO: List of video encoders:\n    --video-codec=h264 --video-encoder=c2.qti.avc.encoder         (hw) [vendor]\n    --video-codec=h264 --video-encoder=c2.android.avc.encoder     (sw)\n    --video-codec=h265 --video-encoder='OMX.qcom.video.encoder.hevc'\n[server] INFO: List of audio encoders:\n    --audio-codec=opus --audio-encoder=c2.android.opus.encoder  (sw)\n";
+        let encoders = parse_video_encoders(output);
+        assert_eq!(encoders.len(), 3);
+        assert_eq!(encoders[0].name, "c2.qti.avc.encoder");
+        assert!(encoders[0].hardware);
+        assert!(!encoders[1].hardware);
+        assert_eq!(encoders[2].codec, "h265");
+        assert_eq!(encoders[2].name, "OMX.qcom.video.encoder.hevc");
+    }
+
+    #[test]
+    fn probes_camera_and_audio_support() {
+        let output = "[server] INFO: List of cameras:\n    --camera-id=0    (back, 4000x3000, fps=[15, 30])\n    --camera-id=1    (front, 3264x2448, fps=[15, 30])\n";
+        assert_eq!(count_cameras(output), Ok(2));
+        assert_eq!(count_cameras("[server] INFO: List of cameras:\n    (none)\n"), Ok(0));
+        assert!(count_cameras("ERROR: Could not find any ADB device\n").is_err());
+
+        let v2_4 = Some(ScrcpyVersion::new(2, 4, 0));
+        assert_eq!(Support::check(Feature::Camera, Some(31), v2_4), Support::Supported);
+        assert_eq!(Support::check(Feature::Audio, None, v2_4), Support::Unknown);
+        assert!(Support::check(Feature::Camera, Some(30), v2_4).label().contains("Android 12"));
+        assert!(Support::check(Feature::Audio, Some(33), Some(ScrcpyVersion::new(1, 25, 0))).label().contains("scrcpy 2.0"));
+    }
+
+    #[test]
+    fn parses_wlan_address() {
+        let output = "30: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500\n    inet 192.168.1.5/24 brd 192.168.1.255 scope global wlan0\n";
+        assert_eq!(parse_inet_addr(output).as_deref(), Some("192.168.1.5"));
+        assert_eq!(parse_inet_addr("Device \"wlan0\" does not exist."), None);
+
+        let route = "10.0.0.0/8 dev rmnet0 proto kernel scope link src 10.1.2.3\n192.168.31.0/24 dev wlan1 proto kernel scope link src 192.168.31.77\n";
+        assert_eq!(parse_route_src(route).as_deref(), Some("192.168.31.77"));
+    }
+
+    #[test]
+    fn parses_wm_output() {
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\r\n"), Some((1080, 2400)));
+        assert_eq!(
+            parse_wm_size("Physical size: 1440x3200\nOverride size: 1080x2400\n"),
+            Some((1080, 2400))
+        );
+        assert_eq!(parse_wm_size("error: no devices"), None);
+        assert_eq!(wm_value("Physical density: 560\nOverride density: 480\n", "density"), Some("480"));
+    }
+
+    #[test]
+    fn detects_transport_from_serial() {
+        assert_eq!(Transport::from_serial("R58M123456"), Transport::Usb);
+        assert_eq!(Transport::from_serial("192.168.1.5:5555"), Transport::Tcp);
+        assert_eq!(Transport::from_serial("adb-R58M123456-AbCdEf._adb-tls-connect._tcp"), Transport::Mdns);
+        assert_eq!(Transport::from_serial("emulator-5554"), Transport::Emulator);
+    }
+
+    #[test]
+    fn schedules_wireless_reconnects() {
+        let mut reconnector = WirelessReconnector::default();
+        reconnector.track("192.168.1.5:5555");
+        reconnector.track("R58M123456");
+        reconnector.track("adb-R58M123456-AbCdEf._adb-tls-connect._tcp");
+
+        let start = Instant::now();
+        assert!(reconnector.update(|_| true, start).is_empty());
+        assert!(reconnector.due(start).is_empty());
+
+        // 掉线后立即重连一次，失败后按退避间隔重试
+        assert_eq!(reconnector.update(|_| false, start), vec!["192.168.1.5:5555".to_string()]);
+        assert!(reconnector.update(|_| false, start).is_empty());
+        assert_eq!(reconnector.due(start), vec![("192.168.1.5:5555".to_string(), 1)]);
+        // 重连进行中不会重复取出
+        assert!(reconnector.due(start + Duration::from_secs(5)).is_empty());
+        reconnector.finish("192.168.1.5:5555", start);
+        assert!(reconnector.due(start + Duration::from_secs(1)).is_empty());
+        assert_eq!(reconnector.due(start + Duration::from_secs(2)).len(), 1);
+
+        // 重新上线后停止重连
+        reconnector.update(|_| true, start);
+        assert!(reconnector.due(start + Duration::from_secs(60)).is_empty());
+
+        reconnector.forget("192.168.1.5:5555");
+        assert!(reconnector.update(|_| false, start).is_empty());
+    }
+
+    #[test]
+    fn finds_qr_pairing_service() {
+        let pairing = QrPairing::generate();
+        assert_eq!(pairing.name.len(), 8);
+        assert_eq!(pairing.password.len(), 6);
+        assert!(pairing.payload().len() <= 32);
+
+        let output = "List of discovered mdns services\n\
+            adb-R58M123456-AbCdEf\t_adb-tls-connect._tcp.\t192.168.1.5:41234\n\
+            adb-1a2b\t_adb-tls-pairing._tcp.\t192.168.1.5:37123\n";
+        assert_eq!(find_pairing_address(output, "adb-1a2b").as_deref(), Some("192.168.1.5:37123"));
+        assert_eq!(find_pairing_address(output, "adb-R58M123456-AbCdEf"), None);
+        assert_eq!(find_pairing_address(output, "adb-ffff"), None);
+    }
+
+    #[test]
+    fn detects_unresponsive_sessions() {
+        let mut health = HealthCheck::default();
+        let start = Instant::now();
+        assert!(health.due(start));
+
+        health.restart(start);
+        assert!(!health.due(start + Duration::from_secs(5)));
+        assert!(health.due(start + HEALTH_CHECK_INTERVAL));
+
+        // 连续失败才判定为无响应，且只触发一次重启
+        assert_eq!(health.record("R58M123456", false, start), HealthEvent::Unchanged);
+        assert_eq!(health.record("R58M123456", false, start), HealthEvent::Degraded);
+        assert!(health.is_degraded("R58M123456"));
+        assert_eq!(health.record("R58M123456", false, start), HealthEvent::Unchanged);
+        assert_eq!(health.record("R58M123456", true, start), HealthEvent::Recovered);
+        assert!(!health.is_degraded("R58M123456"));
+
+        health.record("R58M123456", false, start);
+        health.record("R58M123456", false, start);
+        health.retain(|_| false);
+        assert!(!health.is_degraded("R58M123456"));
+
+        // 检查进行中开始了新会话，旧的结果作废
+        let ping_started = start + Duration::from_secs(20);
+        assert!(health.is_current(ping_started));
+        health.restart(start + Duration::from_secs(21));
+        assert!(!health.is_current(ping_started));
+    }
+
+    #[test]
+    fn tracks_remote_servers() {
+        assert_eq!(split_server("192.168.1.20:5038"), ("192.168.1.20", 5038));
+        assert_eq!(split_server("build-box"), ("build-box", 5037));
+
+        let monitor = DeviceMonitor::new(Path::new("."));
+        monitor.mark_server("build-box", None);
+        assert!(monitor.take_server_changes().is_empty());
+        // 只在状态变化时提示一次
+        monitor.mark_server("build-box", Some("adb devices 命令超时".to_string()));
+        monitor.mark_server("build-box", Some("adb devices 命令超时".to_string()));
+        monitor.mark_server("build-box", None);
+        let changes = monitor.take_server_changes();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].1.is_some() && changes[1].1.is_none());
+    }
+
+    #[test]
+    fn detects_replaced_binary() {
+        let mut watch = BinaryWatch::default();
+        let start = Instant::now();
+        let old = (SystemTime::UNIX_EPOCH, 100);
+        let new = (SystemTime::UNIX_EPOCH + Duration::from_secs(60), 120);
+        assert!(watch.due(start));
+        assert!(!watch.record(Some(old), start));
+        assert!(!watch.due(start + Duration::from_secs(5)));
+
+        // 文件暂时消失或仍在写入时不报告，连续两次相同才算替换完成
+        assert!(!watch.record(None, start));
+        assert!(!watch.record(Some((new.0, 60)), start));
+        assert!(!watch.record(Some(new), start));
+        assert!(watch.record(Some(new), start));
+        assert!(!watch.record(Some(new), start));
+
+        // 文件短暂变化后又恢复原状时不报告
+        assert!(!watch.record(Some(old), start));
+        assert!(!watch.record(Some(new), start));
+    }
+
+    #[test]
+    fn classifies_exit_codes() {
+        let exit = |code| ScrcpyExit { runtime: Duration::from_secs(30), code };
+        assert_eq!(exit(Some(0)).kind(), ExitKind::UserClosed);
+        assert_eq!(exit(Some(2)).kind(), ExitKind::Disconnected);
+        assert_eq!(exit(Some(1)).kind(), ExitKind::Crashed);
+        assert_eq!(exit(None).kind(), ExitKind::Crashed);
+    }
+
+    #[test]
+    fn backs_off_on_repeated_crashes() {
+        let mut tracker = CrashTracker::default();
+        let quick = Duration::from_millis(300);
+        assert_eq!(tracker.record_exit("R58M123456", quick), 1);
+        assert_eq!(tracker.record_exit("R58M123456", quick), 2);
+        assert_eq!(tracker.record_exit("emulator-5554", quick), 1);
+        assert_eq!(CrashTracker::backoff(2), Duration::from_secs(5));
+        assert_eq!(CrashTracker::backoff(CRASH_LIMIT), Duration::from_secs(30));
+
+        // 正常运行一段时间后退出不算崩溃，并清零计数
+        assert_eq!(tracker.record_exit("R58M123456", Duration::from_secs(600)), 0);
+        assert_eq!(tracker.record_exit("R58M123456", quick), 1);
+        tracker.reset("R58M123456");
+        assert_eq!(tracker.record_exit("R58M123456", quick), 1);
+    }
+
+    #[test]
+    fn explains_common_failures() {
+        let lines = |text: &str| text.lines().map(str::to_string).collect::<Vec<_>>();
+        let unauthorized = lines("adb: error: failed to get feature set: device unauthorized.\nERROR: Server connection failed");
+        assert_eq!(ScrcpyFailure::detect(&unauthorized), Some(ScrcpyFailure::Unauthorized));
+        let encoder = lines("ERROR: Encoding error: java.lang.IllegalStateException\n    at android.media.MediaCodec.native_dequeueOutputBuffer");
+        assert_eq!(ScrcpyFailure::detect(&encoder), Some(ScrcpyFailure::EncoderFailed));
+        let audio = lines("ERROR: Audio encoder error: java.lang.IllegalStateException\n    at android.media.MediaCodec.native_dequeueOutputBuffer");
+        assert_eq!(ScrcpyFailure::detect(&audio), Some(ScrcpyFailure::AudioFailed));
+        let push = lines("adb: error: failed to copy 'scrcpy-server' to '/data/local/tmp/scrcpy-server.jar'\nERROR: Could not execute \"adb push\"");
+        assert_eq!(ScrcpyFailure::detect(&push), Some(ScrcpyFailure::ServerPushFailed));
+        let healthy = lines("/data/local/tmp/scrcpy-server: 1 file pushed, 0 skipped.\nINFO: Renderer: direct3d");
+        assert_eq!(ScrcpyFailure::detect(&healthy), None);
+    }
+
+    #[test]
+    fn limits_restarts_per_hour() {
+        let mut tracker = CrashTracker::default();
+        let start = Instant::now();
+        for i in 0..3 {
+            assert_eq!(tracker.record_restart("R58M123456", start + Duration::from_secs(i * 600)), i as u32 + 1);
+        }
+        // 一小时前的重启不再计入
+        assert_eq!(tracker.record_restart("R58M123456", start + Duration::from_secs(3700)), 3);
+        assert_eq!(tracker.record_restart("emulator-5554", start), 1);
+
+        tracker.block("R58M123456", "ERROR: Could not open video stream".to_string());
+        assert_eq!(tracker.blocked("R58M123456"), Some("ERROR: Could not open video stream"));
+        assert_eq!(tracker.blocked("emulator-5554"), None);
+        tracker.reset("R58M123456");
+        assert_eq!(tracker.blocked("R58M123456"), None);
+        assert_eq!(tracker.record_restart("R58M123456", start + Duration::from_secs(3700)), 1);
+    }
+
+    #[test]
+    fn finds_versioned_install_dir() {
+        let root = std::env::temp_dir().join(format!("scrcpy-launcher-install-{}", std::process::id()));
+        let tools = root.join("lib").join("scrcpy").join("tools");
+        for version in ["scrcpy-win64-v2.4", "scrcpy-win64-v3.1"] {
+            std::fs::create_dir_all(tools.join(version)).unwrap();
+            std::fs::write(tools.join(version).join("scrcpy.exe"), b"").unwrap();
+        }
+        std::fs::create_dir_all(tools.join("empty")).unwrap();
+
+        let found = scrcpy_dir_in(&tools);
+        let direct = scrcpy_dir_in(&tools.join("scrcpy-win64-v2.4"));
+        let missing = scrcpy_dir_in(&root.join("lib"));
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(found, Some(tools.join("scrcpy-win64-v3.1")));
+        assert_eq!(direct, Some(tools.join("scrcpy-win64-v2.4")));
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn remote_query_finishes_within_one_loop() {
+        // 远程查询必须在主循环一轮的等待内结束，否则不可达的 server 会让每轮都拿不到结果
+        let wait = crate::polling::PollingConfig::default().adb_timeout(true);
+        assert!(REMOTE_DEVICES_TIMEOUT < wait);
+    }
+}
@@ -0,0 +1,75 @@
+//! 磁盘剩余空间模块
+//! 录屏前和录屏期间检查录像目录所在磁盘的剩余空间，空间不足时提前结束录制，
+//! 让 scrcpy 正常写完文件尾，而不是等磁盘写满后得到一个截断无法播放的录像
+
+use std::path::Path;
+
+/// 录像目录所在磁盘的剩余字节数，目录尚未创建时查询最近的已存在上级目录，无法获取时返回 None
+pub fn free_bytes(dir: &Path) -> Option<u64> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let dir = std::path::absolute(dir).ok()?;
+    let existing = dir.ancestors().find(|d| d.is_dir())?;
+    query_free_bytes(existing)
+}
+
+/// 以便于阅读的单位显示字节数
+pub fn format_size(bytes: u64) -> String {
+    const GB: u64 = 1024 * 1024 * 1024;
+    const MB: u64 = 1024 * 1024;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{} MB", bytes / MB)
+    }
+}
+
+#[cfg(windows)]
+fn query_free_bytes(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+    use winapi::um::winnt::ULARGE_INTEGER;
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    unsafe {
+        // 取当前用户可用的空间（考虑磁盘配额），而不是整个磁盘的剩余空间
+        let mut available: ULARGE_INTEGER = std::mem::zeroed();
+        if GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) == 0 {
+            return None;
+        }
+        Some(*available.QuadPart())
+    }
+}
+
+/// 通过 df 查询，-P 保证输出格式固定，-k 以 KB 为单位
+#[cfg(not(windows))]
+fn query_free_bytes(dir: &Path) -> Option<u64> {
+    let output = std::process::Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 `df -Pk` 的输出，取第二行的 Available 列（第 4 列）
+#[cfg_attr(windows, allow(dead_code))]
+fn parse_df(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_df_output() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/nvme0n1p2   491076512 212345678 253730834      46% /\n";
+        assert_eq!(parse_df(output), Some(253730834 * 1024));
+        assert_eq!(parse_df("Filesystem 1024-blocks Used Available Capacity Mounted on\n"), None);
+        assert_eq!(format_size(1536 * 1024 * 1024), "1.5 GB");
+        assert_eq!(format_size(300 * 1024 * 1024), "300 MB");
+    }
+}
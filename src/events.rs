@@ -0,0 +1,123 @@
+//! 设备事件模块
+//! 监控循环只产生结构化的事件，界面、控制台输出与钩子各自决定如何呈现
+
+use crate::hooks::HookEvent;
+use crate::tui::LogLevel;
+
+/// 设备与投屏会话的状态变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// 设备上线（包括掉线后重连）
+    Connected { serial: String, name: String },
+    /// 设备离线或被拔出
+    Disconnected { serial: String },
+    /// 用户在手机上允许了 USB 调试
+    Authorized { serial: String },
+    /// scrcpy 已启动，args 为完整命令行（第一项为 scrcpy 路径）
+    SessionStarted { serial: String, name: String, args: Vec<String> },
+    /// scrcpy 异常退出，code 为进程退出码（被信号结束时为 None）
+    SessionCrashed { serial: String, code: Option<i32> },
+}
+
+impl DeviceEvent {
+    /// 事件相关的设备序列号
+    pub fn serial(&self) -> &str {
+        match self {
+            DeviceEvent::Connected { serial, .. }
+            | DeviceEvent::Disconnected { serial }
+            | DeviceEvent::Authorized { serial }
+            | DeviceEvent::SessionStarted { serial, .. }
+            | DeviceEvent::SessionCrashed { serial, .. } => serial,
+        }
+    }
+
+    /// 事件类型名称，用于 JSON 输出
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DeviceEvent::Connected { .. } => "connected",
+            DeviceEvent::Disconnected { .. } => "disconnected",
+            DeviceEvent::Authorized { .. } => "authorized",
+            DeviceEvent::SessionStarted { .. } => "session_started",
+            DeviceEvent::SessionCrashed { .. } => "session_crashed",
+        }
+    }
+
+    /// 作为日志显示时的级别
+    pub fn level(&self) -> LogLevel {
+        match self {
+            DeviceEvent::Connected { .. } | DeviceEvent::Disconnected { .. } => LogLevel::Device,
+            DeviceEvent::Authorized { .. } | DeviceEvent::SessionStarted { .. } => LogLevel::Success,
+            DeviceEvent::SessionCrashed { .. } => LogLevel::Error,
+        }
+    }
+
+    /// 作为日志显示时的文本
+    pub fn message(&self) -> String {
+        match self {
+            DeviceEvent::Connected { serial, name } if name.is_empty() => format!("设备已连接: {}", serial),
+            DeviceEvent::Connected { serial, name } => format!("设备已连接: {} ({})", name, serial),
+            DeviceEvent::Disconnected { serial } => format!("设备已断开: {}", serial),
+            DeviceEvent::Authorized { serial } => format!("设备 {} 已授权 USB 调试", serial),
+            DeviceEvent::SessionStarted { name, .. } => format!("成功启动scrcpy连接设备: {}", name),
+            DeviceEvent::SessionCrashed { serial, code: Some(code) } => {
+                format!("设备 {} 的 scrcpy 异常退出（退出码 {}）", serial, code)
+            }
+            DeviceEvent::SessionCrashed { serial, code: None } => format!("设备 {} 的 scrcpy 异常退出", serial),
+        }
+    }
+
+    /// 附加说明，显示在事件文本之后的单独一行
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            DeviceEvent::SessionStarted { args, .. } => Some(format!("命令行: {}", format_command_line(args))),
+            _ => None,
+        }
+    }
+
+    /// 事件对应的钩子
+    pub fn hook_event(&self) -> Option<HookEvent> {
+        match self {
+            DeviceEvent::Connected { .. } => Some(HookEvent::Connect),
+            DeviceEvent::Disconnected { .. } => Some(HookEvent::Disconnect),
+            _ => None,
+        }
+    }
+}
+
+/// 拼接用于日志显示的命令行，含空格的参数加上引号
+fn format_command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| if arg.contains(' ') { format!("\"{}\"", arg) } else { arg.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presents_events() {
+        let started = DeviceEvent::SessionStarted {
+            serial: "R58M123456".to_string(),
+            name: "工作机".to_string(),
+            args: ["C:\\Program Files\\scrcpy\\scrcpy.exe", "-s", "R58M123456", "--max-size=1280"]
+                .map(String::from)
+                .to_vec(),
+        };
+        assert_eq!(started.kind(), "session_started");
+        assert_eq!(
+            started.detail().as_deref(),
+            Some("命令行: \"C:\\Program Files\\scrcpy\\scrcpy.exe\" -s R58M123456 --max-size=1280")
+        );
+
+        let connected = DeviceEvent::Connected { serial: "R58M123456".to_string(), name: String::new() };
+        assert_eq!(connected.hook_event(), Some(HookEvent::Connect));
+        assert_eq!(connected.message(), "设备已连接: R58M123456");
+
+        let crashed = DeviceEvent::SessionCrashed { serial: "R58M123456".to_string(), code: Some(1) };
+        assert_eq!(crashed.level(), LogLevel::Error);
+        assert_eq!(crashed.hook_event(), None);
+        assert!(crashed.message().contains("退出码 1"));
+    }
+}
@@ -0,0 +1,102 @@
+//! 设备形态模块
+//! 按 ro.build.characteristics 区分手机、电视盒子、手表等设备，为不同形态应用合适的默认 scrcpy 参数与 adb 超时
+
+use serde::{Deserialize, Serialize};
+
+/// 设备形态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormFactor {
+    /// 手机、平板等（默认）
+    #[default]
+    Phone,
+    /// Android TV / 电视盒子
+    Tv,
+    /// Wear OS 手表
+    Watch,
+}
+
+/// 手表镜像的最大尺寸，默认参数按手机屏幕设计，在手表上窗口过大且编码器容易失败
+const WATCH_MAX_SIZE: u32 = 480;
+
+impl FormFactor {
+    /// 按 `ro.build.characteristics`（逗号分隔，如 "tv" 或 "nosdcard,tablet"）判断
+    pub fn from_characteristics(characteristics: &str) -> Self {
+        let has = |name: &str| characteristics.split(',').any(|c| c.trim() == name);
+        if has("tv") {
+            FormFactor::Tv
+        } else if has("watch") {
+            FormFactor::Watch
+        } else {
+            FormFactor::Phone
+        }
+    }
+
+    /// 配置文件中使用的英文名称，也用于 JSON 输出
+    pub fn name(self) -> &'static str {
+        match self {
+            FormFactor::Phone => "phone",
+            FormFactor::Tv => "tv",
+            FormFactor::Watch => "watch",
+        }
+    }
+
+    /// 中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            FormFactor::Phone => "手机/平板",
+            FormFactor::Tv => "电视",
+            FormFactor::Watch => "手表",
+        }
+    }
+
+    /// 该形态默认附加的 scrcpy 参数，写在 scrcpy_args 之前，可被用户参数覆盖
+    /// resolution 为设备屏幕分辨率（宽, 高），用于手表的裁剪预设
+    pub fn default_args(self, resolution: Option<(u32, u32)>) -> Vec<String> {
+        match self {
+            FormFactor::Phone => Vec::new(),
+            // 电视的声音由电视自己播放；盒子通常不支持 UHID 键盘，用 SDK 注入按键
+            FormFactor::Tv => vec!["--no-audio".to_string(), "--keyboard=sdk".to_string()],
+            FormFactor::Watch => {
+                let mut args = vec![format!("--max-size={}", WATCH_MAX_SIZE), "--no-audio".to_string()];
+                // 圆形表盘的有效内容在居中的正方形内，非正方形的面板（如底部有黑边）裁掉多余部分
+                if let Some((width, height)) = resolution.filter(|(w, h)| w != h) {
+                    let side = width.min(height);
+                    args.push(format!("--crop={}:{}:{}:{}", side, side, (width - side) / 2, (height - side) / 2));
+                }
+                args
+            }
+        }
+    }
+
+    /// 该形态下 adb 命令的超时：手表常通过 WiFi 或蓝牙调试，响应比手机慢得多
+    pub fn adb_timeout(self, secs: u64) -> u64 {
+        match self {
+            FormFactor::Watch => secs * 3,
+            FormFactor::Phone | FormFactor::Tv => secs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_tv_boxes() {
+        assert_eq!(FormFactor::from_characteristics("tv"), FormFactor::Tv);
+        assert_eq!(FormFactor::from_characteristics("nosdcard, tv"), FormFactor::Tv);
+        assert_eq!(FormFactor::from_characteristics("nosdcard,tablet"), FormFactor::Phone);
+        assert_eq!(FormFactor::from_characteristics(""), FormFactor::Phone);
+        assert!(FormFactor::Tv.default_args(None).contains(&"--keyboard=sdk".to_string()));
+    }
+
+    #[test]
+    fn applies_watch_preset() {
+        assert_eq!(FormFactor::from_characteristics("nosdcard,watch"), FormFactor::Watch);
+        assert_eq!(FormFactor::Watch.default_args(Some((454, 454))), ["--max-size=480", "--no-audio"]);
+        assert_eq!(FormFactor::Watch.default_args(Some((360, 400)))[2], "--crop=360:360:0:20");
+        assert_eq!(FormFactor::Watch.adb_timeout(3), 9);
+        assert_eq!(FormFactor::Phone.adb_timeout(3), 3);
+    }
+}
@@ -0,0 +1,224 @@
+//! 设备历史模块
+//! 记录连接过的设备（序列号、名称、最近连接时间、投屏次数、上次的窗口位置），保存在配置目录的 history.toml
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::migration::{add_version, Schema};
+use crate::window_layout::WindowGeometry;
+
+/// 最多保留的历史设备数量，超出时丢弃最久未连接的设备
+const HISTORY_LIMIT: usize = 50;
+
+/// history.toml 的格式版本与迁移步骤
+const SCHEMA: Schema = Schema { name: "设备历史", migrations: &[add_version] };
+
+/// 一台连接过的设备
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub serial: String,
+    pub name: String,
+    /// 最近一次上线的时间（Unix 秒）
+    pub last_seen: u64,
+    /// 累计启动 scrcpy 的次数
+    pub sessions: u32,
+    /// 上次关闭前 scrcpy 窗口的位置与大小
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowGeometry>,
+    /// 最近一次查询设备时是否在线，下次启动时先显示这些设备，等首次 adb 查询完成后替换
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub online: bool,
+}
+
+/// 设备历史，按最近连接时间从新到旧排列
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceHistory {
+    devices: Vec<HistoryEntry>,
+}
+
+impl DeviceHistory {
+    /// 历史文件路径，与 config.toml 位于同一目录
+    pub fn path() -> PathBuf {
+        Config::path().with_file_name("history.toml")
+    }
+
+    /// 读取历史，文件不存在时返回空历史
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&Self::path())
+    }
+
+    /// 写回历史文件
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Result<Self, String> {
+        match SCHEMA.load(path)? {
+            Some(loaded) => toml::from_str(&loaded.content).map_err(|e| format!("设备历史格式错误: {}", e)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|e| format!("序列化设备历史失败: {}", e))?;
+        let content = SCHEMA.stamp(&content);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        }
+        std::fs::write(path, content).map_err(|e| format!("保存设备历史失败: {}", e))
+    }
+
+    /// 所有历史设备，最近连接的在前
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.devices
+    }
+
+    /// 记录设备上线，名称为空时保留原有名称
+    pub fn record_seen(&mut self, serial: &str, name: &str, now: u64) {
+        let entry = self.entry(serial);
+        entry.last_seen = now;
+        if !name.is_empty() {
+            entry.name = name.to_string();
+        }
+        self.sort();
+    }
+
+    /// 记录一次投屏会话
+    pub fn record_session(&mut self, serial: &str, now: u64) {
+        let entry = self.entry(serial);
+        entry.last_seen = now;
+        entry.sessions += 1;
+        self.sort();
+    }
+
+    /// 记录设备的 scrcpy 窗口位置，返回是否与已记录的不同（需要写回文件）
+    pub fn record_window(&mut self, serial: &str, window: WindowGeometry) -> bool {
+        let entry = self.entry(serial);
+        let changed = entry.window != Some(window);
+        entry.window = Some(window);
+        changed
+    }
+
+    /// 记录当前在线的设备，返回是否与已记录的不同（需要写回文件）
+    pub fn set_online(&mut self, serials: &[&str]) -> bool {
+        let mut changed = false;
+        for entry in &mut self.devices {
+            let online = serials.contains(&entry.serial.as_str());
+            changed |= entry.online != online;
+            entry.online = online;
+        }
+        changed
+    }
+
+    /// 上次退出前在线的设备
+    pub fn last_online(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.devices.iter().filter(|e| e.online)
+    }
+
+    /// 设备上次的窗口位置
+    pub fn window(&self, serial: &str) -> Option<WindowGeometry> {
+        self.devices.iter().find(|e| e.serial == serial).and_then(|e| e.window)
+    }
+
+    fn entry(&mut self, serial: &str) -> &mut HistoryEntry {
+        let index = match self.devices.iter().position(|e| e.serial == serial) {
+            Some(index) => index,
+            None => {
+                self.devices.push(HistoryEntry {
+                    serial: serial.to_string(),
+                    name: String::new(),
+                    last_seen: 0,
+                    sessions: 0,
+                    window: None,
+                    online: false,
+                });
+                self.devices.len() - 1
+            }
+        };
+        &mut self.devices[index]
+    }
+
+    fn sort(&mut self) {
+        self.devices.sort_by_key(|e| std::cmp::Reverse(e.last_seen));
+        self.devices.truncate(HISTORY_LIMIT);
+    }
+}
+
+/// 当前 Unix 时间（秒）
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 把距今的秒数格式化为 "3 分钟前" 之类的文本
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => "刚刚".to_string(),
+        60..=3599 => format!("{} 分钟前", secs / 60),
+        3600..=86399 => format!("{} 小时前", secs / 3600),
+        _ => format!("{} 天前", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_most_recent_devices_first() {
+        let mut history = DeviceHistory::default();
+        history.record_seen("R58M123456", "SM-G9910", 100);
+        history.record_seen("192.168.1.5:5555", "Pixel 7", 200);
+        history.record_session("R58M123456", 300);
+        history.record_seen("R58M123456", "", 400);
+
+        let entries = history.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].serial, "R58M123456");
+        assert_eq!(entries[0].name, "SM-G9910");
+        assert_eq!(entries[0].sessions, 1);
+        assert_eq!(entries[1].last_seen, 200);
+
+        for i in 0..HISTORY_LIMIT {
+            history.record_seen(&format!("emulator-{}", 5554 + i * 2), "", 500 + i as u64);
+        }
+        assert_eq!(history.entries().len(), HISTORY_LIMIT);
+        assert!(!history.entries().iter().any(|e| e.serial == "192.168.1.5:5555"));
+    }
+
+    #[test]
+    fn saves_and_loads_history() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-history-{}.toml", std::process::id()));
+        let mut history = DeviceHistory::default();
+        history.record_session("192.168.1.5:5555", 1_700_000_000);
+        history.record_seen("R58M123456", "SM-G9910", 1_700_000_100);
+        let window = WindowGeometry { x: 100, y: 80, width: 400, height: 860 };
+        assert!(history.record_window("192.168.1.5:5555", window));
+        assert!(!history.record_window("192.168.1.5:5555", window));
+        assert!(history.set_online(&["R58M123456"]));
+        assert!(!history.set_online(&["R58M123456", "emulator-5554"]));
+        history.save_to(&path).unwrap();
+
+        let loaded = DeviceHistory::load_from(&path).unwrap();
+        assert_eq!(loaded.entries(), history.entries());
+        assert_eq!(loaded.last_online().map(|e| e.serial.as_str()).collect::<Vec<_>>(), vec!["R58M123456"]);
+        assert_eq!(loaded.window("192.168.1.5:5555"), Some(window));
+        assert_eq!(loaded.window("R58M123456"), None);
+        let _ = std::fs::remove_file(&path);
+        assert!(DeviceHistory::load_from(&path).unwrap().entries().is_empty());
+    }
+
+    #[test]
+    fn formats_relative_age() {
+        assert_eq!(format_age(5), "刚刚");
+        assert_eq!(format_age(125), "2 分钟前");
+        assert_eq!(format_age(7200), "2 小时前");
+        assert_eq!(format_age(3 * 86400), "3 天前");
+    }
+}
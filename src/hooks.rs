@@ -0,0 +1,92 @@
+//! 设备钩子模块
+//! 设备连接或断开时执行用户配置的命令，设备序列号通过环境变量传入
+
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// 钩子命令最长运行时间，超时后结束进程
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 触发钩子的设备事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Connect,
+    Disconnect,
+}
+
+impl HookEvent {
+    /// 事件的中文名称，用于日志提示
+    pub fn label(self) -> &'static str {
+        match self {
+            HookEvent::Connect => "连接",
+            HookEvent::Disconnect => "断开",
+        }
+    }
+
+    /// 传给命令的 SCRCPY_EVENT 环境变量值
+    fn env_value(self) -> &'static str {
+        match self {
+            HookEvent::Connect => "connect",
+            HookEvent::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// 通过系统 shell 执行钩子命令，返回输出中的非空行
+///
+/// 命令可以读取环境变量 SCRCPY_SERIAL（设备序列号）与 SCRCPY_EVENT（connect / disconnect）
+pub async fn run_hook(command: &str, event: HookEvent, serial: &str) -> Result<Vec<String>, String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    let output = match timeout(
+        HOOK_TIMEOUT,
+        cmd.env("SCRCPY_SERIAL", serial)
+            .env("SCRCPY_EVENT", event.env_value())
+            .kill_on_drop(true)
+            .output(),
+    ).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("执行钩子命令失败: {}", e)),
+        Err(_) => return Err(format!("钩子命令超过 {} 秒未结束，已终止", HOOK_TIMEOUT.as_secs())),
+    };
+
+    let lines = output_lines(&output.stdout, &output.stderr);
+    if !output.status.success() {
+        return Err(format!("钩子命令执行失败 ({}): {}", output.status, lines.join(" | ")));
+    }
+    Ok(lines)
+}
+
+/// 合并 stdout 与 stderr 中的非空行
+fn output_lines(stdout: &[u8], stderr: &[u8]) -> Vec<String> {
+    [stdout, stderr]
+        .iter()
+        .flat_map(|bytes| {
+            String::from_utf8_lossy(bytes)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_non_empty_output_lines() {
+        let lines = output_lines(b"volume set\r\n\r\n", b"  warning: muted  \n");
+        assert_eq!(lines, vec!["volume set".to_string(), "warning: muted".to_string()]);
+        assert!(output_lines(b"", b"\n").is_empty());
+    }
+}
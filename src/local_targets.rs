@@ -0,0 +1,111 @@
+//! 本机 Android 环境模块
+//! 发现 Windows 安卓子系统（WSA）与 Waydroid 的 adb 端口，提供一键连接，并为它们应用合适的默认参数
+
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// WSA 开启开发者模式后 adb 监听的固定地址
+pub const WSA_ADDRESS: &str = "127.0.0.1:58526";
+
+/// 运行在本机上的 Android 环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalTarget {
+    /// Windows 安卓子系统
+    Wsa,
+    /// Linux 上的 Waydroid 容器
+    Waydroid,
+}
+
+impl LocalTarget {
+    /// 显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            LocalTarget::Wsa => "Windows 安卓子系统 (WSA)",
+            LocalTarget::Waydroid => "Waydroid",
+        }
+    }
+
+    /// 按序列号与型号识别设备列表中的本机环境
+    pub fn identify(serial: &str, name: &str) -> Option<Self> {
+        if serial == WSA_ADDRESS {
+            Some(LocalTarget::Wsa)
+        } else if name.to_ascii_lowercase().contains("waydroid") {
+            Some(LocalTarget::Waydroid)
+        } else {
+            None
+        }
+    }
+
+    /// 默认附加的 scrcpy 参数，写在 scrcpy_args 之前，可被用户参数覆盖
+    pub fn default_args(self) -> Vec<String> {
+        // 两者的声音本来就由电脑播放，再转发一次会出现回声
+        vec!["--no-audio".to_string()]
+    }
+}
+
+/// 查找本机正在运行、可以通过 adb connect 连接的 Android 环境
+pub async fn discover() -> Vec<(LocalTarget, String)> {
+    let mut found = Vec::new();
+    if wsa_listening().await {
+        found.push((LocalTarget::Wsa, WSA_ADDRESS.to_string()));
+    }
+    if let Some(ip) = waydroid_address().await {
+        found.push((LocalTarget::Waydroid, format!("{}:5555", ip)));
+    }
+    found
+}
+
+/// WSA 的 adb 端口是否在监听
+async fn wsa_listening() -> bool {
+    let Ok(address) = WSA_ADDRESS.parse::<SocketAddr>() else {
+        return false;
+    };
+    tokio::task::spawn_blocking(move || TcpStream::connect_timeout(&address, Duration::from_millis(200)).is_ok())
+        .await
+        .unwrap_or(false)
+}
+
+/// 通过 `waydroid status` 获取正在运行的 Waydroid 容器地址
+async fn waydroid_address() -> Option<String> {
+    // Waydroid 只在 Linux 上运行
+    if cfg!(windows) {
+        return None;
+    }
+    let output = tokio::time::timeout(
+        Duration::from_secs(3),
+        tokio::process::Command::new("waydroid").arg("status").kill_on_drop(true).output(),
+    ).await.ok()?.ok()?;
+    parse_waydroid_status(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 `waydroid status` 的输出，会话未运行时返回 None
+/// 输出形如 "Session:\tRUNNING\nContainer:\tRUNNING\n...\nIP address:\t192.168.240.112"
+fn parse_waydroid_status(output: &str) -> Option<String> {
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            (name.trim() == key).then(|| value.trim().to_string())
+        })
+    };
+    if value("Session")? != "RUNNING" {
+        return None;
+    }
+    value("IP address").filter(|ip| !ip.is_empty() && !ip.eq_ignore_ascii_case("UNKNOWN"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifies_local_targets() {
+        assert_eq!(LocalTarget::identify(WSA_ADDRESS, "Subsystem for Android TM "), Some(LocalTarget::Wsa));
+        assert_eq!(LocalTarget::identify("192.168.240.112:5555", "WayDroid x86 64 Device"), Some(LocalTarget::Waydroid));
+        assert_eq!(LocalTarget::identify("R58M123456", "SM A525F"), None);
+
+        let running = "Session:\tRUNNING\nContainer:\tRUNNING\nVendor type:\tMAINLINE\nIP address:\t192.168.240.112\n";
+        assert_eq!(parse_waydroid_status(running).as_deref(), Some("192.168.240.112"));
+        assert_eq!(parse_waydroid_status("Session:\tSTOPPED\nVendor type:\tMAINLINE\n"), None);
+        assert_eq!(parse_waydroid_status("Session:\tRUNNING\nIP address:\tUNKNOWN\n"), None);
+    }
+}
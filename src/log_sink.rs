@@ -0,0 +1,220 @@
+//! 日志输出模块
+//! 界面日志区、控制台与配置的文件或 syslog 服务器都是日志输出，接收消息的一端把每条日志写入所有输出。
+//! 文件与 syslog 有独立的有界队列和后台线程，输出端变慢或不可用时丢弃日志并计数，不会阻塞监控循环
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::now_secs;
+use crate::session::format_date;
+use crate::tui::LogLevel;
+
+/// 每个输出最多积压的日志条数
+const QUEUE_CAPACITY: usize = 256;
+
+/// 一个日志输出
+pub trait LogSink: Send {
+    /// 写入一条日志，不得阻塞；积压已满时丢弃本条并返回 false
+    fn write(&mut self, level: LogLevel, message: &str) -> bool;
+}
+
+/// 配置文件中的一个日志输出，可同时配置多个
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogSinkConfig {
+    /// 追加写入文本文件
+    File { path: PathBuf },
+    /// 通过 UDP 发送到 syslog 服务器（RFC 3164），address 为 host:port
+    Syslog { address: String },
+}
+
+/// 经由有界队列交给后台线程写出的日志输出
+pub struct QueuedSink {
+    sender: SyncSender<String>,
+    format: fn(LogLevel, &str) -> String,
+    /// 上次成功写入后被丢弃的条数，队列恢复后补一条提示
+    dropped: usize,
+}
+
+impl QueuedSink {
+    /// 创建输出，output 在后台线程中逐行写出
+    fn spawn(format: fn(LogLevel, &str) -> String, mut output: impl FnMut(&str) + Send + 'static) -> Self {
+        let (sender, receiver) = sync_channel::<String>(QUEUE_CAPACITY);
+        std::thread::spawn(move || {
+            for line in receiver {
+                output(&line);
+            }
+        });
+        Self { sender, format, dropped: 0 }
+    }
+
+    /// 追加写入文件，目录不存在时自动创建
+    pub fn file(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("打开日志文件失败 {}: {}", path.display(), e))?;
+        Ok(Self::spawn(file_line, move |line| {
+            let _ = writeln!(file, "{}", line);
+        }))
+    }
+
+    /// 发送到 syslog 服务器，地址无法解析时返回错误
+    pub fn syslog(address: &str) -> Result<Self, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| socket.connect(address).map(|_| socket))
+            .map_err(|e| format!("无法连接 syslog 服务器 {}: {}", address, e))?;
+        Ok(Self::spawn(syslog_line, move |line| {
+            let _ = socket.send(line.as_bytes());
+        }))
+    }
+}
+
+impl LogSink for QueuedSink {
+    fn write(&mut self, level: LogLevel, message: &str) -> bool {
+        if self.dropped > 0 {
+            let notice = format!("输出过慢，丢弃了 {} 条日志", self.dropped);
+            if self.sender.try_send((self.format)(LogLevel::Warning, &notice)).is_ok() {
+                self.dropped = 0;
+            }
+        }
+        let sent = self.sender.try_send((self.format)(level, message)).is_ok();
+        if !sent {
+            self.dropped += 1;
+        }
+        sent
+    }
+}
+
+/// 同时写入的一组日志输出
+#[derive(Default)]
+pub struct LogSinks {
+    sinks: Vec<Box<dyn LogSink>>,
+}
+
+impl LogSinks {
+    /// 按配置打开所有输出，无法打开的输出跳过并返回错误信息
+    pub fn open(configs: &[LogSinkConfig]) -> (Self, Vec<String>) {
+        let mut sinks = Self::default();
+        let mut errors = Vec::new();
+        for config in configs {
+            let sink = match config {
+                LogSinkConfig::File { path } => QueuedSink::file(path),
+                LogSinkConfig::Syslog { address } => QueuedSink::syslog(address),
+            };
+            match sink {
+                Ok(sink) => sinks.attach(Box::new(sink)),
+                Err(e) => errors.push(e),
+            }
+        }
+        (sinks, errors)
+    }
+
+    /// 添加一个输出
+    pub fn attach(&mut self, sink: Box<dyn LogSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// 写入所有输出，某个输出积压不影响其他输出
+    pub fn write(&mut self, level: LogLevel, message: &str) {
+        for sink in &mut self.sinks {
+            sink.write(level, message);
+        }
+    }
+
+    /// 写入显示端（界面日志区或控制台）与所有输出；显示端由接收消息的一端持有，因此单独传入
+    pub fn publish(&mut self, display: &mut dyn LogSink, level: LogLevel, message: &str) {
+        display.write(level, message);
+        self.write(level, message);
+    }
+}
+
+/// 文件中的一行：[20240102-030405] WARNING 消息
+fn file_line(level: LogLevel, message: &str) -> String {
+    format!("[{}] {:<7} {}", format_date(now_secs()), level.name().to_uppercase(), message)
+}
+
+/// syslog 报文：user 设施，按级别映射严重程度
+fn syslog_line(level: LogLevel, message: &str) -> String {
+    let severity = match level {
+        LogLevel::Error => 3,
+        LogLevel::Warning => 4,
+        LogLevel::Success | LogLevel::Device | LogLevel::Launch => 5,
+        LogLevel::Info => 6,
+    };
+    format!("<{}>scrcpy-launcher: {}", 8 + severity, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 同步记录写入内容的输出
+    struct Recorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl LogSink for Recorder {
+        fn write(&mut self, level: LogLevel, message: &str) -> bool {
+            self.0.lock().unwrap().push(file_line(level, message));
+            true
+        }
+    }
+
+    #[test]
+    fn drops_instead_of_blocking() {
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let (lines, received) = std::sync::mpsc::channel::<String>();
+        // 后台线程卡在第一条上，模拟写入缓慢的输出
+        let mut sink = QueuedSink::spawn(syslog_line, move |line| {
+            let _ = wait.recv();
+            let _ = lines.send(line.to_string());
+        });
+        let accepted = (0..QUEUE_CAPACITY + 10).filter(|_| sink.write(LogLevel::Info, "设备已连接")).count();
+        assert!(accepted <= QUEUE_CAPACITY + 1);
+        assert!(sink.dropped > 0);
+
+        drop(release);
+        // 后台线程每写完一条才转发，收齐即说明队列已腾空
+        for _ in 0..accepted {
+            received.recv().unwrap();
+        }
+        // 队列腾空后先补一条丢弃提示
+        assert!(sink.write(LogLevel::Error, "scrcpy 异常退出"));
+        assert_eq!(sink.dropped, 0);
+        assert_eq!(syslog_line(LogLevel::Error, "x"), "<11>scrcpy-launcher: x");
+    }
+
+    #[test]
+    fn opens_configured_sinks() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-sink-{}.log", std::process::id()));
+        let configs = [
+            LogSinkConfig::File { path: path.clone() },
+            // 无效的 IPv4 地址，不依赖 DNS 即可确定无法连接
+            LogSinkConfig::Syslog { address: "256.0.0.1:514".to_string() },
+        ];
+        let (mut sinks, errors) = LogSinks::open(&configs);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("256.0.0.1:514"));
+        assert_eq!(sinks.sinks.len(), 1);
+
+        // 显示端与附加的输出都收到同一条日志
+        let display = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let attached = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        sinks.attach(Box::new(Recorder(std::sync::Arc::clone(&attached))));
+        sinks.publish(&mut Recorder(std::sync::Arc::clone(&display)), LogLevel::Warning, "设备 R58M123456 无响应");
+        for lines in [display, attached] {
+            let lines = lines.lock().unwrap();
+            assert_eq!(lines.len(), 1);
+            assert!(lines[0].ends_with("WARNING 设备 R58M123456 无响应"));
+        }
+    }
+}
@@ -2,13 +2,45 @@
 //! 自动检测设备连接并启动scrcpy
 
 mod single_instance;
+mod cli;
+mod config;
+mod console;
 mod device_monitor;
+mod disk_space;
+mod events;
+mod form_factor;
+mod history;
+mod hooks;
+mod local_targets;
+mod log_sink;
+mod migration;
+mod polling;
+mod port_forward;
+mod qr;
+mod scrcpy_compat;
+mod scrcpy_import;
+mod session;
+mod stray_process;
 mod tui;
+mod usb_hotplug;
+mod window_layout;
 
 use single_instance::SingleInstanceGuard;
-use tui::{TuiApp, LogLevel, DeviceInfo};
-use device_monitor::DeviceMonitor;
+use config::{Config, StrayAction};
+use tui::{TuiApp, LogLevel, DeviceInfo, DeviceStatus, StartupStep, StepProgress, UserAction};
+use device_monitor::{file_stamp, Adb, find_pairing_address, is_tcp_serial, BinaryWatch, CrashTracker, DeviceMonitor, DeviceQuery, ExitKind, ADB_DEVICES_TIMEOUT, ADB_STARTUP_TIMEOUT, FpsStats, HealthCheck, HealthEvent, QrPairing, ScrcpyFailure, HEALTH_CHECK_TIMEOUT, Support, Transport, WirelessReconnector, CRASH_LIMIT};
+use events::DeviceEvent;
+use history::{now_secs, DeviceHistory, HistoryEntry};
+use hooks::HookEvent;
+use local_targets::LocalTarget;
+use log_sink::LogSinks;
+use polling::PollPhase;
+use port_forward::{ActiveForward, ForwardRule};
+use scrcpy_compat::Feature;
+use session::{recording_path, AudioConfig, RecordingConfig, SavedSession, SessionKind};
+use usb_hotplug::UsbHotplug;
 
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::path::PathBuf;
 use tokio::sync::{mpsc, Mutex};
@@ -16,6 +48,39 @@ use tokio::time::{sleep, Duration};
 
 #[tokio::main]
 async fn main() {
+    // 命令行模式不启动 TUI，也不占用单实例锁，便于在启动器运行时执行
+    let json_logs = match cli::Command::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Run { json }) => json,
+        Ok(cli::Command::Version) => {
+            cli::print_version(&get_scrcpy_directory()).await;
+            return;
+        }
+        Ok(cli::Command::SelfCheck) => {
+            let healthy = cli::self_check(&get_scrcpy_directory()).await;
+            std::process::exit(if healthy { 0 } else { 1 });
+        }
+        Ok(cli::Command::ImportScrcpy) => {
+            let imported = cli::import_scrcpy(&get_scrcpy_directory());
+            std::process::exit(if imported { 0 } else { 1 });
+        }
+        Ok(cli::Command::Devices { json }) => {
+            let listed = cli::list_devices(&get_scrcpy_directory(), json).await;
+            std::process::exit(if listed { 0 } else { 1 });
+        }
+        Ok(cli::Command::Launch(options)) => {
+            let code = cli::launch(&get_scrcpy_directory(), &options).await;
+            std::process::exit(code);
+        }
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::usage());
+            return;
+        }
+        Err(e) => {
+            eprintln!("❌ {}\n\n{}", e, cli::usage());
+            std::process::exit(2);
+        }
+    };
+
     // 单实例检查
     let _guard = match SingleInstanceGuard::new("scrcpy-launcher") {
         Ok(guard) => guard,
@@ -25,11 +90,19 @@ async fn main() {
         }
     };
 
-    // 创建TUI应用
+    // 输出被重定向或指定 --json 时不启动 TUI，便于在脚本和计划任务中运行
+    let style = console::OutputStyle::detect(json_logs);
+    if style != console::OutputStyle::Rich {
+        run_console_mode(style).await;
+        return;
+    }
+
+    // 创建TUI应用，终端不支持时退回控制台模式
     let mut app = match TuiApp::new() {
         Ok(app) => app,
         Err(e) => {
-            eprintln!("❌ TUI初始化失败: {}", e);
+            eprintln!("⚠️ TUI初始化失败: {}，将以控制台模式运行", e);
+            run_console_mode(style).await;
             return;
         }
     };
@@ -37,16 +110,35 @@ async fn main() {
     // 添加初始化日志
     app.state_mut().add_log(LogLevel::Success, "单实例检查通过".to_string());
     app.state_mut().add_log(LogLevel::Info, "SCRCPY 智能启动器已启动".to_string());
+    app.state_mut().set_startup_step(StartupStep::SingleInstance, StepProgress::Passed(String::new()));
+
+    let (config, config_notice) = load_config();
+    let config_step = match &config_notice {
+        Some((LogLevel::Warning, e)) => StepProgress::Failed(e.clone()),
+        Some((_, notice)) => StepProgress::Passed(notice.clone()),
+        None => StepProgress::Passed(String::new()),
+    };
+    app.state_mut().set_startup_step(StartupStep::LoadConfig, config_step);
+    if let Some((level, message)) = config_notice {
+        app.state_mut().add_log(level, message);
+    }
+
+    // 界面日志区之外的附加日志输出，无法打开的在共享状态创建前提示
+    let (mut sinks, sink_errors) = LogSinks::open(&config.log_sinks);
+    for e in sink_errors {
+        app.state_mut().add_log(LogLevel::Warning, e);
+    }
 
     // 创建共享状态
     let app_state = Arc::new(Mutex::new(app.state().clone()));
 
     // 创建消息通道
     let (tx, mut rx) = mpsc::channel(100);
+    let (action_tx, action_rx) = mpsc::channel(16);
 
     // 启动业务逻辑任务
     let business_handle = tokio::spawn(async move {
-        run_device_monitor(tx).await;
+        run_device_monitor(tx, action_rx, config).await;
     });
 
 
@@ -58,7 +150,13 @@ async fn main() {
             let mut state = app_state_for_tui.lock().await;
             match msg {
                 TuiMessage::Log(level, message) => {
-                    state.add_log(level, message);
+                    sinks.publish(&mut *state, level, &message);
+                }
+                TuiMessage::Device(event) => {
+                    sinks.publish(&mut *state, event.level(), &event.message());
+                    if let Some(detail) = event.detail() {
+                        sinks.publish(&mut *state, LogLevel::Info, &detail);
+                    }
                 }
                 TuiMessage::Status(status) => {
                     state.set_status(status);
@@ -66,6 +164,27 @@ async fn main() {
                 TuiMessage::UpdateDevices(devices) => {
                     state.update_devices(devices);
                 }
+                TuiMessage::Fps(fps) => {
+                    state.set_fps(fps);
+                }
+                TuiMessage::Session(session) => {
+                    state.set_session(session);
+                }
+                TuiMessage::History(history) => {
+                    state.set_history(history);
+                }
+                TuiMessage::Forwards(forwards) => {
+                    state.set_forwards(forwards);
+                }
+                TuiMessage::QrPairingFinished => {
+                    state.set_qr_pairing(None);
+                }
+                TuiMessage::Startup(step, progress) => {
+                    if let StepProgress::Failed(e) = &progress {
+                        sinks.publish(&mut *state, LogLevel::Error, &format!("{}失败: {}", step.label(), e));
+                    }
+                    state.set_startup_step(step, progress);
+                }
                 TuiMessage::Quit => {
                     state.should_quit = true;
                     break;
@@ -76,7 +195,7 @@ async fn main() {
 
     // 运行TUI主循环
     let result = tokio::select! {
-        result = app.run_with_shared_state(app_state) => result,
+        result = app.run_with_shared_state(app_state, action_tx) => result,
         _ = tokio::signal::ctrl_c() => {
             Ok(())
         }
@@ -91,158 +210,2104 @@ async fn main() {
     }
 }
 
+/// 读取配置，出错时使用默认配置继续运行，并返回需要提示的格式升级（Info）或错误（Warning）
+fn load_config() -> (Config, Option<(LogLevel, String)>) {
+    match Config::load() {
+        Ok((config, None)) => (config, None),
+        Ok((config, Some(backup))) => (
+            config,
+            Some((LogLevel::Info, format!("配置文件已升级为新格式，原文件备份为 {}", backup.display()))),
+        ),
+        Err(e) => (Config::default(), Some((LogLevel::Warning, format!("{}，将使用默认配置", e)))),
+    }
+}
+
+/// 控制台模式：按指定格式逐行输出日志，从标准输入读取命令
+async fn run_console_mode(style: console::OutputStyle) {
+    console::print_log(style, LogLevel::Info, "SCRCPY 智能启动器已启动（控制台模式）");
+    eprintln!("{}", console::HELP);
+
+    let (config, config_notice) = load_config();
+    if let Some((level, message)) = config_notice {
+        console::print_log(style, level, &message);
+    }
+
+    let (tx, rx) = mpsc::channel(100);
+    let (action_tx, action_rx) = mpsc::channel(16);
+    let (sinks, sink_errors) = LogSinks::open(&config.log_sinks);
+    for e in sink_errors {
+        console::print_log(style, LogLevel::Warning, &e);
+    }
+
+    let business_handle = tokio::spawn(async move {
+        run_device_monitor(tx, action_rx, config).await;
+    });
+
+    tokio::select! {
+        _ = console::print_messages(rx, style, sinks) => {}
+        _ = console::read_commands(action_tx, style) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    business_handle.abort();
+}
+
+
 /// TUI消息类型
 #[derive(Debug)]
 pub enum TuiMessage {
     Log(LogLevel, String),
     Status(String),
     UpdateDevices(Vec<DeviceInfo>),
+    Fps(Option<(String, FpsStats)>),
+    Session(Option<(String, SessionKind)>),
+    History(Vec<HistoryEntry>),
+    Forwards(Vec<ActiveForward>),
+    /// 设备与会话事件，由接收方决定如何显示
+    Device(DeviceEvent),
+    /// 二维码配对已结束（成功、失败或超时），关闭弹窗
+    QrPairingFinished,
+    /// 启动步骤的进度
+    Startup(StartupStep, StepProgress),
     Quit,
 }
 
 /// 运行设备监控逻辑（性能优化版本）
-async fn run_device_monitor(tx: mpsc::Sender<TuiMessage>) {
-    let _ = tx.send(TuiMessage::Status("监控设备连接...".to_string())).await;
-    let _ = tx.send(TuiMessage::Log(LogLevel::Info, "开始监控Android设备连接".to_string())).await;
-
-    // 获取scrcpy目录
-    let scrcpy_dir = get_scrcpy_directory();
-    let mut device_monitor = DeviceMonitor::new(&scrcpy_dir);
-    let mut scrcpy_started = false;
-    let mut last_device_id: Option<String> = None;
-    let mut last_status_update = std::time::Instant::now();
-    let mut last_device_count = 0;
-    let mut consecutive_checks = 0;
-    
-    // 预分配字符串以减少内存分配
-    let status_waiting = "等待设备连接中...".to_string();
-
-    loop {
-        consecutive_checks += 1;
-        
-        // 并行执行设备检查和状态更新
-        let device_check_result = tokio::select! {
-            result = check_connected_devices_with_monitor(&device_monitor) => result,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(50)) => {
-                // 50ms超时，如果adb命令太慢就跳过这次检查
-                continue;
+async fn run_device_monitor(
+    tx: mpsc::Sender<TuiMessage>,
+    actions: mpsc::Receiver<UserAction>,
+    config: Config,
+) {
+    MonitorLoop::start(tx, config).await.run(actions).await;
+}
+
+/// 设备在线（可以执行 adb 命令），被名单排除或重启过多的设备同样算在线
+fn is_online(status: &DeviceStatus) -> bool {
+    matches!(status, DeviceStatus::Connected | DeviceStatus::Ignored | DeviceStatus::Degraded | DeviceStatus::Failed)
+}
+
+/// 监控循环的状态；每轮循环依次处理用户操作、后台任务的结果、adb 设备列表与投屏会话
+struct MonitorLoop {
+    tx: mpsc::Sender<TuiMessage>,
+    config: Config,
+    device_monitor: DeviceMonitor,
+    /// 配置中的额外 scrcpy 参数，启动前按已安装版本过滤
+    extra_args: Vec<String>,
+    /// 首次 adb devices 是否已返回结果
+    adb_probed: bool,
+    /// 上次启动器异常退出时遗留的 scrcpy 进程：沿用期间不自动投屏，避免重复打开窗口
+    stray_pids: Vec<u32>,
+    /// 上次检查遗留进程的时间，检查在后台线程中进行，同时只进行一次
+    strays_checked_at: std::time::Instant,
+    stray_check_in_flight: bool,
+    /// 被 scrcpy 拒绝的参数，后续启动时移除
+    rejected_options: Vec<String>,
+    /// 参数错误只自动重试一次，再次失败则暂停自动启动，避免反复拉起必然失败的进程
+    flag_retry_used: bool,
+    launch_suspended: bool,
+    /// 用户暂停了自动投屏：仍监控设备，只有按 Enter / Shift+R 手动投屏时才启动 scrcpy
+    auto_launch_paused: bool,
+    manual_launch: bool,
+    /// scrcpy 启动后很快退出时按退避时间延迟重启，连续崩溃过多则停止自动重启
+    crash_tracker: CrashTracker,
+    restart_at: Option<std::time::Instant>,
+    /// 正在投屏的设备从设备列表中消失的时间，在 disconnect_grace_secs 内重新出现则继续使用原来的 scrcpy
+    disconnected_since: Option<std::time::Instant>,
+    crash_looping: bool,
+    scrcpy_started: bool,
+    last_device_id: Option<String>,
+    /// 用户在界面中选中或切换到无线连接后优先投屏的设备
+    preferred_device: Option<String>,
+    /// 已探测过编码器的设备
+    seen_devices: HashSet<String>,
+    /// 各设备上一次的状态，用于在状态变化时给出提示
+    last_statuses: HashMap<String, DeviceStatus>,
+    /// adb devices 连续失败的次数与最近一次成功的时间，用于判断 adb server 是否挂死
+    adb_failures: u32,
+    last_adb_success: std::time::Instant,
+    /// USB 插拔通知可用时只在设备变化时执行 adb devices，否则退回持续轮询
+    hotplug: Option<UsbHotplug>,
+    /// 上一次 adb devices 的结果，不需要轮询时直接复用
+    last_devices: Vec<DeviceInfo>,
+    last_adb_poll: std::time::Instant,
+    /// 尚未返回的 adb devices 查询，等待超时后留到下次循环继续等待
+    pending_query: Option<DeviceQuery>,
+    /// 收到 USB 插拔事件后持续快速轮询到该时间，adb 往往要过一会儿才列出新设备
+    hotplug_burst_until: Option<std::time::Instant>,
+    /// 用户操作（配对、无线切换）后需要立即刷新设备
+    force_device_check: bool,
+    /// 无线设备掉线后自动重连
+    reconnector: WirelessReconnector,
+    /// 定期检查投屏设备是否仍能响应 adb 命令
+    health_check: HealthCheck,
+    /// 本机正在运行的 WSA / Waydroid 及其 adb 地址，未连接时提示按 c 连接
+    local_targets: Vec<(LocalTarget, String)>,
+    last_target_scan: Option<std::time::Instant>,
+    /// 正在后台查找 WSA / Waydroid，同时只进行一次
+    target_scan_in_flight: bool,
+    /// 按 c 时还没有发现目标，查找结束后立即连接
+    connect_after_scan: bool,
+    /// 定期检查 scrcpy 可执行文件是否被新版本替换
+    binary_watch: BinaryWatch,
+    /// scrcpy 已更新但当前会话仍在运行旧版本
+    update_pending: bool,
+    show_fps: bool,
+    /// 是否转发音频，可在界面中切换
+    audio_enabled: bool,
+    /// 按 d 开启的演示模式，只在本次运行中有效
+    presentation: bool,
+    /// 当前会话类型，决定退出后是否自动重启
+    active_kind: SessionKind,
+    fps_samples: u64,
+    last_status_update: std::time::Instant,
+    last_device_count: usize,
+    consecutive_checks: u32,
+    /// 最近一次设备变化或用户操作的时间，用于判断是否空闲
+    last_activity: std::time::Instant,
+    low_power: bool,
+    /// 昵称修改后需要立即刷新设备列表
+    nicknames_changed: bool,
+    /// 连接过的设备，变化时写回 history.toml
+    history: DeviceHistory,
+    history_changed: bool,
+    /// 首次 adb 查询完成前先显示上次在线的设备，查询完成后无论结果如何都要刷新一次
+    showing_stale: bool,
+    /// 上次读取 scrcpy 窗口位置的时间
+    window_sampled_at: std::time::Instant,
+    disk_checked_at: std::time::Instant,
+    disk_space_warned: bool,
+    /// 在界面中为设备切换的配置档（None 表示不使用配置档），优先于配置文件，重启启动器后恢复为配置文件中的设置
+    profile_overrides: HashMap<String, Option<String>>,
+    /// 在界面中为设备切换的会话类型，优先于配置文件，重启启动器后恢复为配置文件中的设置
+    session_overrides: HashMap<String, SessionKind>,
+    /// 按 v 开始录屏的设备，停止录屏后恢复原来的会话类型
+    recording_device: Option<String>,
+    /// 从上次退出时恢复的会话类型，只作用于该设备
+    resumed_kind: Option<(String, SessionKind)>,
+    /// 上次退出时仍在进行的会话，等待用户确认恢复
+    saved_session: Option<SavedSession>,
+    resume_requested: bool,
+    /// 正在等待手机扫码的二维码配对：(配对信息, 开始时间, 上次查询 mDNS 的时间)
+    qr_pairing: Option<(QrPairing, std::time::Instant, Option<std::time::Instant>)>,
+    /// 正在后台查询 mDNS 服务，同时只进行一次
+    mdns_in_flight: bool,
+    /// 后台 adb 任务的结果，每轮循环取出处理
+    events_tx: mpsc::Sender<BackgroundEvent>,
+    events: mpsc::Receiver<BackgroundEvent>,
+    /// 正在后台进行的连接检查，同时只进行一个
+    ping_in_flight: bool,
+    /// 最近一次在后台建立端口转发的任务，后一次等前一次结束再开始，保证转发列表按顺序更新
+    forwards_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+/// 在后台执行的 adb 命令完成后交回监控循环的结果
+enum BackgroundEvent {
+    /// 连接检查：(设备, 开始时间, 结果)
+    Ping(String, std::time::Instant, Result<(), String>),
+    /// 掉线无线设备的重连：(设备, 第几次重连, 结果)
+    Reconnect(String, u32, Result<(), String>),
+    /// 用户发起的 adb connect：(地址, 成功时的提示, 结果)
+    Connected(String, String, Result<(), String>),
+    /// 无线调试配对（及随后的连接）已结束
+    Paired,
+    /// 本机 WSA / Waydroid 的查找结果
+    LocalTargets(Vec<(LocalTarget, String)>),
+    /// 扫码配对的 mDNS 查询：(二维码中的服务名, 找到的配对地址)
+    PairingService(String, Option<String>),
+    /// 仍在运行的遗留 scrcpy 进程
+    Strays(Vec<u32>),
+}
+
+impl MonitorLoop {
+    /// 定位 scrcpy 与 adb、处理遗留进程、读取历史与会话记录，并把启动步骤报告给界面
+    async fn start(tx: mpsc::Sender<TuiMessage>, config: Config) -> Self {
+        let _ = tx.send(TuiMessage::Status("监控设备连接...".to_string())).await;
+        let _ = tx.send(TuiMessage::Log(LogLevel::Info, "开始监控Android设备连接".to_string())).await;
+
+        // 获取scrcpy目录
+        let _ = tx.send(TuiMessage::Startup(StartupStep::LocateScrcpy, StepProgress::Running)).await;
+        let scrcpy_dir = get_scrcpy_directory();
+        let mut device_monitor = DeviceMonitor::new(&scrcpy_dir);
+        device_monitor.set_adb_config(config.adb.clone());
+        for exe in [&device_monitor.adb_exe, &device_monitor.scrcpy_exe] {
+            if exe.exists() && !exe.starts_with(&scrcpy_dir) {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("scrcpy 目录中未找到，改用: {}", exe.display()))).await;
             }
+        }
+        let version = device_monitor.detect_scrcpy_version().await;
+        if let Some(version) = &version {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("检测到 scrcpy 版本: {}", version))).await;
+        }
+        let located = if !device_monitor.scrcpy_exe.exists() {
+            StepProgress::Failed(format!("未找到 scrcpy: {}", device_monitor.scrcpy_exe.display()))
+        } else if !device_monitor.adb_exe.exists() {
+            StepProgress::Failed(format!("未找到 adb: {}", device_monitor.adb_exe.display()))
+        } else {
+            StepProgress::Passed(version.map(|v| format!("版本 {}", v)).unwrap_or_default())
         };
-        
-        if let Ok(devices) = device_check_result {
-            // 只在设备列表实际变化时更新UI
-            let device_count = devices.len();
-            let device_count_changed = device_count != last_device_count;
-            
-            if device_count_changed || consecutive_checks % 10 == 0 {
-                // 每10次检查或设备变化时更新UI
-                let _ = tx.send(TuiMessage::UpdateDevices(devices.clone())).await;
-            }
-            
-            last_device_count = device_count;
-            
-            if !devices.is_empty() {
-                let current_device_id = &devices[0].id; // 使用引用避免clone
-                
-                // 检查scrcpy进程状态（如果认为已启动）
-                if scrcpy_started {
-                    if !device_monitor.is_scrcpy_running() {
-                        let _ = tx.send(TuiMessage::Log(
-                            LogLevel::Warning,
-                            "检测到scrcpy进程已结束，正在自动重启...".to_string()
-                        )).await;
-                        scrcpy_started = false; // 重置状态以触发重启
+        let _ = tx.send(TuiMessage::Startup(StartupStep::LocateScrcpy, located)).await;
+        let _ = tx.send(TuiMessage::Startup(StartupStep::ProbeAdb, StepProgress::Running)).await;
+
+        let stray_pids = find_strays(&config, &tx).await;
+        // 首次运行时提示可以导入用户原来启动 scrcpy 用的参数
+        if !Config::path().exists() {
+            let sources = scrcpy_import::find_sources(&scrcpy_dir);
+            if !sources.is_empty() {
+                let _ = tx.send(TuiMessage::Log(
+                    LogLevel::Info,
+                    format!("发现 {} 个带参数启动 scrcpy 的脚本或快捷方式，可运行 scrcpy-launcher --import-scrcpy 导入到配置文件", sources.len())
+                )).await;
+            }
+        }
+        let extra_args: Vec<String> = match scrcpy_compat::validate_user_args(&config.scrcpy_args) {
+            Ok(()) => config.scrcpy_args.clone(),
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Error, format!("scrcpy_args 配置无效（{}），已忽略全部自定义参数", e))).await;
+                Vec::new()
+            }
+        };
+        if !extra_args.is_empty() {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("自定义 scrcpy 参数: {}", extra_args.join(" ")))).await;
+        }
+        if !audio_supported(&device_monitor) && config.audio != AudioConfig::default() {
+            let _ = tx.send(TuiMessage::Log(
+                LogLevel::Warning,
+                format!("当前 scrcpy 不支持{}（需要 {} 及以上），已忽略音频设置", Feature::Audio.name(), Feature::Audio.min_version())
+            )).await;
+        }
+        let hotplug = match UsbHotplug::start() {
+            Ok(hotplug) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Info, "已启用 USB 热插拔通知".to_string())).await;
+                Some(hotplug)
+            }
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("{}，将持续轮询设备", e))).await;
+                None
+            }
+        };
+        let history = match DeviceHistory::load() {
+            Ok(history) => history,
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("{}，将重新记录", e))).await;
+                DeviceHistory::default()
+            }
+        };
+        let _ = tx.send(TuiMessage::History(history.entries().to_vec())).await;
+        let stale_devices = stale_devices(&history, &config);
+        let showing_stale = !stale_devices.is_empty();
+        if showing_stale {
+            let _ = tx.send(TuiMessage::UpdateDevices(stale_devices)).await;
+        }
+        let saved_session = match SavedSession::load() {
+            Ok(session) => session,
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, e)).await;
+                None
+            }
+        };
+        if let Some(session) = &saved_session {
+            let message = if config.resume_session {
+                format!("上次退出时正在投屏 {}（{}），设备连接后将自动恢复", session.serial, session.kind.label())
+            } else {
+                format!("上次退出时正在投屏 {}（{}），按 r 在设备连接后恢复", session.serial, session.kind.label())
+            };
+            let _ = tx.send(TuiMessage::Log(LogLevel::Info, message)).await;
+        }
+
+        let now = std::time::Instant::now();
+        let (events_tx, events) = mpsc::channel(32);
+        Self {
+            show_fps: config.show_fps,
+            audio_enabled: config.audio.enabled,
+            resume_requested: config.resume_session && saved_session.is_some(),
+            tx,
+            config,
+            device_monitor,
+            extra_args,
+            adb_probed: false,
+            stray_pids,
+            strays_checked_at: now,
+            stray_check_in_flight: false,
+            rejected_options: Vec::new(),
+            flag_retry_used: false,
+            launch_suspended: false,
+            auto_launch_paused: false,
+            manual_launch: false,
+            crash_tracker: CrashTracker::default(),
+            restart_at: None,
+            disconnected_since: None,
+            crash_looping: false,
+            scrcpy_started: false,
+            last_device_id: None,
+            preferred_device: None,
+            seen_devices: HashSet::new(),
+            last_statuses: HashMap::new(),
+            adb_failures: 0,
+            last_adb_success: now,
+            hotplug,
+            last_devices: Vec::new(),
+            last_adb_poll: now,
+            pending_query: None,
+            hotplug_burst_until: None,
+            force_device_check: false,
+            reconnector: WirelessReconnector::default(),
+            health_check: HealthCheck::default(),
+            local_targets: Vec::new(),
+            last_target_scan: None,
+            target_scan_in_flight: false,
+            connect_after_scan: false,
+            binary_watch: BinaryWatch::default(),
+            update_pending: false,
+            presentation: false,
+            active_kind: SessionKind::default(),
+            fps_samples: 0,
+            last_status_update: now,
+            last_device_count: 0,
+            consecutive_checks: 0,
+            last_activity: now,
+            low_power: false,
+            nicknames_changed: false,
+            history,
+            history_changed: false,
+            showing_stale,
+            window_sampled_at: now,
+            disk_checked_at: now,
+            disk_space_warned: false,
+            profile_overrides: HashMap::new(),
+            session_overrides: HashMap::new(),
+            recording_device: None,
+            resumed_kind: None,
+            saved_session,
+            qr_pairing: None,
+            mdns_in_flight: false,
+            events_tx,
+            events,
+            ping_in_flight: false,
+            forwards_task: None,
+        }
+    }
+
+    /// 监控主循环，不会返回
+    async fn run(mut self, mut actions: mpsc::Receiver<UserAction>) {
+        loop {
+            self.consecutive_checks += 1;
+            self.check_strays();
+
+            // 处理界面上触发的操作
+            while let Ok(action) = actions.try_recv() {
+                self.last_activity = std::time::Instant::now();
+                self.handle_action(action).await;
+            }
+            while let Ok(event) = self.events.try_recv() {
+                self.handle_event(event).await;
+            }
+
+            self.resume_session().await;
+            self.poll_qr_pairing().await;
+            self.scan_local_targets();
+            self.watch_scrcpy_binary().await;
+            self.report_scrcpy_output().await;
+
+            let in_hotplug_burst = self.hotplug_burst();
+            let Some(device_check_result) = self.poll_devices(in_hotplug_burst).await else {
+                continue;
+            };
+            if let Ok(devices) = device_check_result {
+                self.update_devices(devices).await;
+            } else if std::mem::take(&mut self.showing_stale) {
+                // adb 不可用时拿不到设备列表，也要清除"上次在线"的占位行，免得它们一直显示
+                let _ = self.tx.send(TuiMessage::UpdateDevices(Vec::new())).await;
+            }
+
+            self.save_history().await;
+            self.update_power_mode().await;
+            self.wait(in_hotplug_burst).await;
+        }
+    }
+
+    /// 发送一条日志到界面
+    async fn log(&self, level: LogLevel, message: impl Into<String>) {
+        let _ = self.tx.send(TuiMessage::Log(level, message.into())).await;
+    }
+
+    /// 结束当前 scrcpy，下次循环按当前设置重新启动
+    async fn stop_session(&mut self) {
+        self.device_monitor.stop_scrcpy().await;
+        self.scrcpy_started = false;
+    }
+
+    /// 处理后台 adb 任务的结果
+    async fn handle_event(&mut self, event: BackgroundEvent) {
+        match event {
+            BackgroundEvent::Ping(serial, started, result) => {
+                self.ping_in_flight = false;
+                // 检查期间会话已结束、换了设备或重新开始时结果作废
+                let current = self.scrcpy_started && self.last_device_id.as_deref() == Some(serial.as_str());
+                if current && self.health_check.is_current(started) {
+                    self.record_health(&serial, result).await;
+                }
+            }
+            BackgroundEvent::Reconnect(serial, attempt, result) => {
+                self.reconnector.finish(&serial, std::time::Instant::now());
+                match result {
+                    Ok(()) => {
+                        self.log(LogLevel::Success, format!("已重新连接无线设备: {}", serial)).await;
+                        self.force_device_check = true;
                     }
+                    // 只在首次和之后每 10 次失败时提示，避免刷屏
+                    Err(e) if attempt == 1 || attempt % 10 == 0 => {
+                        self.log(LogLevel::Warning, format!("重新连接 {} 失败（第 {} 次），稍后重试: {}", serial, attempt, e)).await;
+                    }
+                    Err(_) => {}
+                }
+            }
+            BackgroundEvent::Connected(address, message, result) => match result {
+                Ok(()) => {
+                    self.log(LogLevel::Success, message).await;
+                    self.preferred_device = Some(address);
+                    self.force_device_check = true;
                 }
-                
-                // 在设备变化、scrcpy未启动或设备数量变化时启动
-                if !scrcpy_started || last_device_id.as_ref() != Some(current_device_id) || device_count_changed {
-                    // 只在设备真正变化时显示发现日志
-                    if last_device_id.as_ref() != Some(current_device_id) || device_count_changed {
-                        for device in &devices {
-                            let _ = tx.send(TuiMessage::Log(
-                                LogLevel::Device,
-                                format!("发现设备: {} ({})", device.name, device.id)
-                            )).await;
-                        }
+                Err(e) => self.log(LogLevel::Error, e).await,
+            },
+            BackgroundEvent::Paired => self.force_device_check = true,
+            BackgroundEvent::LocalTargets(found) => {
+                self.target_scan_in_flight = false;
+                let connect = std::mem::take(&mut self.connect_after_scan);
+                // 新出现且尚未连接的提示一次；按 c 触发的查找随后直接连接，不再提示
+                for (target, address) in &found {
+                    let known = self.local_targets.iter().any(|(_, known)| known == address);
+                    if !connect && !known && !self.last_statuses.contains_key(address) {
+                        self.log(LogLevel::Device, format!("检测到 {} ({})，按 c 连接", target.label(), address)).await;
                     }
-                    
-                    let _ = tx.send(TuiMessage::Log(LogLevel::Launch, "正在启动scrcpy...".to_string())).await;
-                    
-                    if device_monitor.is_scrcpy_available() {
-                        match device_monitor.start_scrcpy(Some(current_device_id)) {
-                            Ok(_) => {
-                                let _ = tx.send(TuiMessage::Log(
-                                    LogLevel::Success,
-                                    format!("成功启动scrcpy连接设备: {}", devices[0].name)
-                                )).await;
-                                scrcpy_started = true;
-                                last_device_id = Some(current_device_id.clone());
-                            }
-                            Err(e) => {
-                                let _ = tx.send(TuiMessage::Log(
-                                    LogLevel::Error,
-                                    format!("启动scrcpy失败: {}", e)
-                                )).await;
-                                scrcpy_started = false;
-                            }
-                        }
+                }
+                self.local_targets = found;
+                if connect {
+                    self.connect_pending_targets().await;
+                }
+            }
+            BackgroundEvent::PairingService(name, address) => {
+                self.mdns_in_flight = false;
+                // 查询期间配对可能已取消、超时或换了新的二维码
+                let Some(address) = address else { return };
+                let Some((pairing, _, _)) = self.qr_pairing.take_if(|(pairing, _, _)| pairing.name == name) else {
+                    return;
+                };
+                let _ = self.tx.send(TuiMessage::QrPairingFinished).await;
+                self.spawn_pair(address, pairing.password, None);
+            }
+            BackgroundEvent::Strays(running) => {
+                self.stray_check_in_flight = false;
+                if self.stray_pids.is_empty() {
+                    return;
+                }
+                // 只移除已退出的，检查期间按 K 结束的进程不会被加回来
+                self.stray_pids.retain(|pid| running.contains(pid));
+                if self.stray_pids.is_empty() {
+                    self.log(LogLevel::Info, "遗留的 scrcpy 进程已全部退出，恢复自动投屏").await;
+                }
+            }
+        }
+    }
+
+    /// 在后台连接无线设备，结果由 handle_event 处理
+    fn spawn_connect(&self, address: String, message: String) {
+        let (adb, events) = (self.device_monitor.adb(), self.events_tx.clone());
+        tokio::spawn(async move {
+            let result = adb.connect_tcp(&address).await;
+            let _ = events.send(BackgroundEvent::Connected(address, message, result)).await;
+        });
+    }
+
+    /// 在后台配对（adb pair 最长等待 15 秒），配对日志直接发往界面，结束后刷新设备
+    fn spawn_pair(&self, address: String, code: String, connect_address: Option<String>) {
+        let (adb, tx, events) = (self.device_monitor.adb(), self.tx.clone(), self.events_tx.clone());
+        tokio::spawn(async move {
+            pair_device(&adb, &address, &code, connect_address.as_deref(), &tx).await;
+            let _ = events.send(BackgroundEvent::Paired).await;
+        });
+    }
+
+    /// 定期在后台线程中检查遗留的 scrcpy，全部退出后由 handle_event 恢复自动投屏
+    fn check_strays(&mut self) {
+        if self.stray_pids.is_empty() || self.stray_check_in_flight || self.strays_checked_at.elapsed() < STRAY_CHECK {
+            return;
+        }
+        self.strays_checked_at = std::time::Instant::now();
+        self.stray_check_in_flight = true;
+        let (pids, events) = (self.stray_pids.clone(), self.events_tx.clone());
+        tokio::spawn(async move {
+            let checked = pids.clone();
+            // 检查线程出错时视为仍在运行，下次再查
+            let running = tokio::task::spawn_blocking(move || stray_process::still_running(&checked))
+                .await
+                .unwrap_or(pids);
+            let _ = events.send(BackgroundEvent::Strays(running)).await;
+        });
+    }
+
+    /// 处理界面或控制台触发的操作
+    async fn handle_action(&mut self, action: UserAction) {
+        match action {
+            UserAction::ToggleWireless => match self.last_device_id.clone() {
+                Some(serial) => {
+                    // 用户主动断开无线连接，不再自动重连
+                    self.reconnector.forget(&serial);
+                    self.preferred_device = toggle_wireless(&self.device_monitor, &serial, &self.tx).await;
+                    self.force_device_check = true;
+                }
+                None => {
+                    self.log(LogLevel::Warning, "当前没有正在投屏的设备").await;
+                }
+            },
+            UserAction::Pair { address, code, connect_address } => self.spawn_pair(address, code, connect_address),
+            UserAction::SelectDevice(serial) => self.select_device(serial).await,
+            UserAction::SetNickname { serial, nickname } => {
+                let message = match &nickname {
+                    Some(nickname) => format!("已将设备 {} 命名为 {}", serial, nickname),
+                    None => format!("已清除设备 {} 的昵称", serial),
+                };
+                match self.config.set_nickname(&serial, nickname) {
+                    Ok(()) => self.log(LogLevel::Success, message).await,
+                    Err(e) => self.log(LogLevel::Error, e).await,
+                }
+                self.nicknames_changed = true;
+            }
+            UserAction::ConnectKnown(serial) => self.connect_known(serial).await,
+            UserAction::ConnectLocalTargets => self.connect_local_targets().await,
+            UserAction::ToggleRecording => match (self.last_device_id.clone(), self.scrcpy_started) {
+                (Some(serial), true) => {
+                    let message = if self.recording_device.as_ref() == Some(&serial) {
+                        self.recording_device = None;
+                        "已停止录屏，正在以原会话类型重启scrcpy..."
                     } else {
-                        let _ = tx.send(TuiMessage::Log(
-                            LogLevel::Error,
-                            "scrcpy或adb未找到，请确保scrcpy已正确安装".to_string()
-                        )).await;
+                        self.recording_device = Some(serial);
+                        "正在以录屏模式重启scrcpy..."
+                    };
+                    self.log(LogLevel::Launch, message).await;
+                    // 录像在 scrcpy 退出时写完，重启会话即可开始或结束录制
+                    self.stop_session().await;
+                }
+                _ => {
+                    self.log(LogLevel::Warning, "当前没有正在投屏的设备").await;
+                }
+            },
+            UserAction::ResumeSession => {
+                self.resume_requested = true;
+            }
+            UserAction::ToggleAudio => {
+                if !audio_supported(&self.device_monitor) {
+                    self.log(
+                        LogLevel::Warning,
+                        format!("当前 scrcpy 不支持{}（需要 {} 及以上）", Feature::Audio.name(), Feature::Audio.min_version())
+                    ).await;
+                } else {
+                    self.audio_enabled = !self.audio_enabled;
+                    self.log(LogLevel::Info, if self.audio_enabled { "已开启音频转发" } else { "已关闭音频转发" }).await;
+                    // 仅音频会话始终转发音频，不需要重启
+                    if self.scrcpy_started && self.active_kind.has_audio() && self.active_kind != SessionKind::AudioOnly {
+                        self.log(LogLevel::Info, "正在重启scrcpy以应用音频设置...").await;
+                        self.stop_session().await;
                     }
                 }
+            }
+            UserAction::TogglePresentation => {
+                self.presentation = !self.presentation;
+                self.log(
+                    LogLevel::Info,
+                    if self.presentation { "已开启演示模式（显示触摸点、保持唤醒、窗口置顶）" } else { "已关闭演示模式" }
+                ).await;
+                if self.scrcpy_started && self.active_kind.has_video() {
+                    self.log(LogLevel::Info, "正在重启scrcpy以应用演示模式...").await;
+                    self.stop_session().await;
+                }
+            }
+            UserAction::RestartSession(serial) => self.restart_session(serial).await,
+            UserAction::ToggleAutoLaunch => {
+                self.auto_launch_paused = !self.auto_launch_paused;
+                let (message, status) = if self.auto_launch_paused {
+                    ("已暂停自动投屏，设备连接后不会打开 scrcpy（正在进行的投屏不受影响，可按 Enter 手动投屏）", "⏸️ 自动投屏已暂停")
+                } else {
+                    ("已恢复自动投屏", "监控设备连接...")
+                };
+                self.log(LogLevel::Info, message).await;
+                let _ = self.tx.send(TuiMessage::Status(status.to_string())).await;
+            }
+            UserAction::EndStrayScrcpy => {
+                if self.stray_pids.is_empty() {
+                    self.log(LogLevel::Info, "没有遗留的 scrcpy 进程").await;
+                } else {
+                    end_stray_scrcpy(&mut self.stray_pids, &self.tx).await;
+                }
+            }
+            UserAction::ApplyUpdate => {
+                if self.update_pending && self.scrcpy_started {
+                    self.log(LogLevel::Launch, "正在重启scrcpy以使用新版本...").await;
+                    self.stop_session().await;
+                } else {
+                    self.log(LogLevel::Info, "当前会话已在使用最新的 scrcpy").await;
+                }
+            }
+            UserAction::CycleProfile(serial) => self.cycle_profile(serial).await,
+            UserAction::CycleSession(serial) => self.cycle_session(serial).await,
+            UserAction::RemoteKey { serial, keycode } => {
+                if let Err(e) = self.device_monitor.send_keyevent(&serial, keycode).await {
+                    self.log(LogLevel::Warning, format!("发送按键失败: {}", e)).await;
+                }
+            }
+            UserAction::StartQrPairing(pairing) => {
+                self.log(LogLevel::Device, "请在手机上打开 无线调试 → 使用二维码配对设备，扫描弹窗中的二维码").await;
+                self.qr_pairing = Some((pairing, std::time::Instant::now(), None));
+            }
+            UserAction::CancelQrPairing => {
+                if self.qr_pairing.take().is_some() {
+                    self.log(LogLevel::Info, "已取消扫码配对").await;
+                }
+            }
+            UserAction::ToggleFps => {
+                self.show_fps = !self.show_fps;
+                self.log(
+                    LogLevel::Info,
+                    if self.show_fps { "已开启帧率统计，正在重启scrcpy..." } else { "已关闭帧率统计，正在重启scrcpy..." }
+                ).await;
+                let _ = self.tx.send(TuiMessage::Fps(None)).await;
+                self.fps_samples = 0;
+                if self.scrcpy_started {
+                    // 参数变化需要重启会话才能生效
+                    self.stop_session().await;
+                }
+            }
+        }
+    }
+
+    /// 按 Enter 将选中的设备切换为投屏目标
+    async fn select_device(&mut self, serial: String) {
+        if self.last_device_id.as_ref() == Some(&serial) && self.scrcpy_started {
+            self.log(LogLevel::Info, format!("设备 {} 已在投屏中", serial)).await;
+        } else if let Some(status) = self.last_statuses.get(&serial).filter(|s| !s.is_ready()) {
+            self.log(LogLevel::Warning, format!("设备 {} 当前为{}状态，无法投屏", serial, status.label())).await;
+        } else {
+            self.log(LogLevel::Info, format!("已将投屏目标切换为 {}", serial)).await;
+            // 用户主动选择时恢复被暂停的自动启动
+            self.crash_tracker.reset(&serial);
+            self.preferred_device = Some(serial);
+            self.launch_suspended = false;
+            self.manual_launch = true;
+        }
+    }
+
+    /// 按 Shift+R 立即重启或启动设备的投屏会话
+    async fn restart_session(&mut self, serial: String) {
+        if let Some(status) = self.last_statuses.get(&serial).filter(|s| !s.is_ready()) {
+            self.log(LogLevel::Warning, format!("设备 {} 当前为{}状态，无法投屏", serial, status.label())).await;
+            return;
+        }
+        if self.last_device_id.as_ref() == Some(&serial) && self.scrcpy_started {
+            self.log(LogLevel::Launch, format!("正在重启设备 {} 的投屏会话...", serial)).await;
+            self.stop_session().await;
+            let _ = self.tx.send(TuiMessage::Fps(None)).await;
+            let _ = self.tx.send(TuiMessage::Session(None)).await;
+        } else {
+            self.log(LogLevel::Launch, format!("正在为设备 {} 启动投屏会话...", serial)).await;
+        }
+        // 手动重启不计入崩溃次数，也不等待退避
+        self.crash_tracker.reset(&serial);
+        self.restart_at = None;
+        self.preferred_device = Some(serial);
+        self.launch_suspended = false;
+        self.manual_launch = true;
+    }
+
+    /// 按 o 切换设备的配置档，正在投屏时以新参数重启
+    async fn cycle_profile(&mut self, serial: String) {
+        let current = active_profile(&self.config, &self.profile_overrides, &serial, is_remote(&self.last_devices, &serial));
+        let next = self.config.next_profile(current.as_deref());
+        let message = match &next {
+            Some(name) => format!("设备 {} 已切换到配置档: {}", serial, name),
+            None if self.config.profiles.is_empty() => format!("配置文件中没有定义配置档（[profiles]），设备 {} 使用默认参数", serial),
+            None => format!("设备 {} 已切换为不使用配置档", serial),
+        };
+        self.log(LogLevel::Info, message).await;
+        if self.scrcpy_started && self.last_device_id.as_ref() == Some(&serial) {
+            // 参数变化需要重启会话才能生效
+            self.log(LogLevel::Info, "正在以新的配置档重启scrcpy...").await;
+            self.stop_session().await;
+        }
+        self.profile_overrides.insert(serial, next);
+    }
+
+    /// 按 t 切换设备的会话类型，正在投屏时以新类型重启
+    async fn cycle_session(&mut self, serial: String) {
+        let current = self
+            .session_overrides
+            .get(&serial)
+            .copied()
+            .unwrap_or_else(|| self.config.session_kind(&serial));
+        let next = current.next();
+        let mut message = format!("设备 {} 的会话类型已切换为: {} {}", serial, next.icon(), next.label());
+        if next == SessionKind::OtgControl {
+            message.push_str("（只作为键盘鼠标使用，不镜像画面，需要 USB 连接）");
+        }
+        self.log(LogLevel::Info, message).await;
+        // 显式选择的类型优先于录屏切换与恢复的会话
+        if self.recording_device.as_ref() == Some(&serial) {
+            self.recording_device = None;
+        }
+        self.resumed_kind = self.resumed_kind.take().filter(|(resumed, _)| *resumed != serial);
+        if self.last_device_id.as_ref() == Some(&serial) {
+            // 之前的类型不支持该设备时暂停了自动启动，换了类型重新尝试
+            self.launch_suspended = false;
+            if self.scrcpy_started {
+                self.log(LogLevel::Launch, "正在以新的会话类型重启scrcpy...").await;
+                self.stop_session().await;
+            }
+        }
+        self.session_overrides.insert(serial, next);
+    }
+
+    /// 历史记录中按 Enter：重新连接通过 IP 连接过的无线设备
+    async fn connect_known(&mut self, serial: String) {
+        if self.last_statuses.contains_key(&serial) {
+            self.log(LogLevel::Info, format!("设备 {} 已连接，可在设备列表中按 Enter 投屏", serial)).await;
+        } else if Transport::from_serial(&serial) == Transport::Tcp {
+            self.log(LogLevel::Device, format!("正在重新连接 {}...", serial)).await;
+            let message = format!("已连接无线设备: {}", serial);
+            self.spawn_connect(serial, message);
+        } else {
+            self.log(LogLevel::Warning, format!("设备 {} 不是通过 IP 连接的无线设备，请插上数据线或重新配对", serial)).await;
+        }
+    }
+
+    /// 按 c 连接本机尚未连接的 WSA / Waydroid
+    async fn connect_local_targets(&mut self) {
+        // 还没扫描过或上次没有发现时立即重新查找，找到后再连接
+        if self.local_targets.is_empty() {
+            self.connect_after_scan = true;
+            self.spawn_target_scan();
+        } else {
+            self.connect_pending_targets().await;
+        }
+    }
+
+    /// 在后台连接已发现但尚未连接的 WSA / Waydroid
+    async fn connect_pending_targets(&mut self) {
+        let pending: Vec<_> = self
+            .local_targets
+            .iter()
+            .filter(|(_, address)| !self.last_statuses.contains_key(address))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            let message = if self.local_targets.is_empty() {
+                "未发现正在运行的 WSA 或 Waydroid（WSA 需要在设置中开启开发者模式）"
+            } else {
+                "本机的 WSA / Waydroid 均已连接"
+            };
+            self.log(LogLevel::Info, message).await;
+        }
+        for (target, address) in pending {
+            self.log(LogLevel::Device, format!("正在连接 {} ({})...", target.label(), address)).await;
+            self.spawn_connect(address, format!("已连接 {}", target.label()));
+        }
+    }
+
+    /// 恢复上次的会话：优先投屏该设备，并沿用当时的会话类型、配置档与帧率统计设置
+    async fn resume_session(&mut self) {
+        if !std::mem::take(&mut self.resume_requested) {
+            return;
+        }
+        let Some(session) = self.saved_session.take() else {
+            self.log(LogLevel::Info, "没有可恢复的会话").await;
+            return;
+        };
+        let mut message = format!("将在设备 {} 连接后恢复{}会话", session.serial, session.kind.label());
+        if let Some(recording) = &session.recording {
+            message.push_str(&format!("（上次的录像保存在 {}，恢复后写入新文件）", recording));
+        }
+        self.log(LogLevel::Info, message).await;
+        if session.show_fps != self.show_fps {
+            self.show_fps = session.show_fps;
+            let _ = self.tx.send(TuiMessage::Fps(None)).await;
+        }
+        self.profile_overrides.insert(session.serial.clone(), session.profile);
+        self.resumed_kind = Some((session.serial.clone(), session.kind));
+        self.crash_tracker.reset(&session.serial);
+        self.preferred_device = Some(session.serial);
+        self.launch_suspended = false;
+    }
+
+    /// 手机扫码后会以二维码中的服务名广播配对服务，在后台查询 mDNS，找到后由 handle_event 配对
+    async fn poll_qr_pairing(&mut self) {
+        let Some((pairing, started, last_poll)) = self.qr_pairing.as_mut() else {
+            return;
+        };
+        if started.elapsed() >= QR_PAIRING_TIMEOUT {
+            self.qr_pairing = None;
+            self.log(LogLevel::Warning, "扫码配对超时，可按 p 重新生成二维码").await;
+            let _ = self.tx.send(TuiMessage::QrPairingFinished).await;
+        } else if !self.mdns_in_flight && last_poll.is_none_or(|t| t.elapsed() >= QR_PAIRING_POLL) {
+            *last_poll = Some(std::time::Instant::now());
+            self.mdns_in_flight = true;
+            let (adb, events, name) = (self.device_monitor.adb(), self.events_tx.clone(), pairing.name.clone());
+            tokio::spawn(async move {
+                let address = adb.mdns_services().await.ok().and_then(|output| find_pairing_address(&output, &name));
+                let _ = events.send(BackgroundEvent::PairingService(name, address)).await;
+            });
+        }
+    }
+
+    /// 定期查找本机的 WSA / Waydroid，结果由 handle_event 处理
+    fn scan_local_targets(&mut self) {
+        if self.last_target_scan.is_none_or(|t| t.elapsed() >= LOCAL_TARGET_SCAN) {
+            self.spawn_target_scan();
+        }
+    }
+
+    /// 在后台查找本机的 WSA / Waydroid（waydroid status 最长等待 3 秒）
+    fn spawn_target_scan(&mut self) {
+        self.last_target_scan = Some(std::time::Instant::now());
+        if std::mem::replace(&mut self.target_scan_in_flight, true) {
+            return;
+        }
+        let events = self.events_tx.clone();
+        tokio::spawn(async move {
+            let found = local_targets::discover().await;
+            let _ = events.send(BackgroundEvent::LocalTargets(found)).await;
+        });
+    }
+
+    /// 安装新版本 scrcpy 后重新检测版本，正在进行的会话按配置自动或由用户确认后重启到新版本
+    async fn watch_scrcpy_binary(&mut self) {
+        if self.binary_watch.due(std::time::Instant::now()) {
+            let stamp = file_stamp(&self.device_monitor.scrcpy_exe);
+            if self.binary_watch.record(stamp, std::time::Instant::now()) {
+                let old_version = self.device_monitor.scrcpy_version;
+                let new_version = self.device_monitor.detect_scrcpy_version().await;
+                let describe = |v: Option<scrcpy_compat::ScrcpyVersion>| v.map(|v| v.to_string()).unwrap_or_else(|| "未知".to_string());
+                self.log(LogLevel::Info, format!("检测到 scrcpy 已更新: {} → {}", describe(old_version), describe(new_version))).await;
+                // 新版本可能支持之前被拒绝的参数
+                self.rejected_options.clear();
+                self.flag_retry_used = false;
+                self.launch_suspended = false;
+                self.update_pending = self.scrcpy_started;
+                if self.update_pending && !self.config.restart_on_update {
+                    self.log(LogLevel::Info, "当前会话仍在使用旧版本，按 u 重启投屏会话").await;
+                } else if self.update_pending && self.active_kind == SessionKind::Record {
+                    self.log(LogLevel::Info, "正在录屏，录屏结束后再切换到新版本").await;
+                }
+            }
+        }
+        // 录屏中途重启会把录像拆成两个文件，等录屏结束后再切换
+        if self.update_pending && self.config.restart_on_update && self.scrcpy_started && self.active_kind != SessionKind::Record {
+            self.log(LogLevel::Launch, "正在重启scrcpy以使用新版本...").await;
+            self.stop_session().await;
+        }
+    }
+
+    /// 把 scrcpy 的错误输出与后台探测的结果转发到日志面板，便于诊断启动失败或编码器异常
+    async fn report_scrcpy_output(&mut self) {
+        for line in self.device_monitor.take_scrcpy_errors() {
+            self.log(LogLevel::Error, format!("scrcpy: {}", line)).await;
+        }
+        log_video_encoders(&self.device_monitor, &mut self.seen_devices, &self.tx).await;
+        for (serial, result) in self.device_monitor.take_camera_probes() {
+            match result {
+                Ok(count) => self.log(LogLevel::Info, format!("设备 {} 有 {} 个摄像头", serial, count)).await,
+                // 失败不缓存，下次以摄像头模式投屏时重新探测
+                Err(e) => self.log(LogLevel::Warning, format!("探测摄像头失败: {}", e)).await,
+            }
+        }
+    }
+
+    /// 收到 USB 插拔事件后的一段时间内是否按启动初期的频率轮询
+    fn hotplug_burst(&mut self) -> bool {
+        if self.hotplug.as_ref().is_some_and(|hotplug| hotplug.take_changed()) {
+            self.hotplug_burst_until = Some(std::time::Instant::now() + HOTPLUG_BURST);
+        }
+        self.hotplug_burst_until.is_some_and(|until| std::time::Instant::now() < until)
+    }
+
+    /// 需要时执行 adb devices，返回本轮使用的设备列表；查询尚未返回时返回 None，下次循环继续等待
+    async fn poll_devices(&mut self, in_hotplug_burst: bool) -> Option<Result<Vec<DeviceInfo>, String>> {
+        // 有插拔事件、刚启动、存在未就绪设备（等待授权等不会产生 USB 事件）或超过兜底间隔时才执行 adb devices；
+        // 上次的查询尚未返回时继续等待它
+        let poll_adb = self.pending_query.is_some() || match &self.hotplug {
+            Some(_) => {
+                in_hotplug_burst
+                    || std::mem::take(&mut self.force_device_check)
+                    || self.consecutive_checks < 50
+                    || self.last_devices.iter().any(|d| !d.status.is_ready())
+                    || self.last_adb_poll.elapsed() >= HOTPLUG_FALLBACK_POLL
+            }
+            None => true,
+        };
+
+        let device_check_result = if poll_adb && !self.device_monitor.adb_exe.exists() {
+            Some(Err("ADB未找到，请确保scrcpy已正确安装".to_string()))
+        } else if poll_adb {
+            // 首次查询可能要等 adb server 启动，使用更长的超时，启动提示才能在查询完成后显示
+            let timeout = if self.adb_probed { ADB_DEVICES_TIMEOUT } else { ADB_STARTUP_TIMEOUT };
+            let query = self.pending_query.get_or_insert_with(|| self.device_monitor.start_device_query(timeout));
+            let outputs = tokio::select! {
+                outputs = query.outputs() => Some(outputs),
+                // 超时（默认 50ms，有远程 adb server 时至少 1s）先处理其他事务，查询在后台继续，下次循环再取结果
+                _ = tokio::time::sleep(self.config.polling.adb_timeout(!self.config.adb.remote_servers.is_empty())) => None,
+            };
+            outputs.map(|outputs| {
+                self.pending_query = None;
+                self.device_monitor.finish_device_query(outputs)
+            })
+        } else {
+            Some(Ok(self.last_devices.clone()))
+        };
+
+        for (server, error) in self.device_monitor.take_server_changes() {
+            match error {
+                Some(e) => self.log(LogLevel::Warning, format!("远程 adb server {} 无法访问（{}），其上的设备暂不可用", server, e)).await,
+                None => self.log(LogLevel::Success, format!("已连接远程 adb server: {}", server)).await,
+            }
+        }
+        if self.device_monitor.take_daemon_started() {
+            self.log(LogLevel::Info, "adb server 未运行，已自动启动").await;
+        }
+        // 首次拿到 adb 结果后更新启动窗口；超时的轮询不算，继续等待
+        if poll_adb && !self.adb_probed {
+            if let Some(result) = &device_check_result {
+                self.adb_probed = true;
+                let progress = match result {
+                    Ok(devices) => StepProgress::Passed(format!("{} 台设备", devices.len())),
+                    Err(e) => StepProgress::Failed(e.to_string()),
+                };
+                let _ = self.tx.send(TuiMessage::Startup(StartupStep::ProbeAdb, progress)).await;
+            }
+        }
+
+        // 复用的结果不计入 adb 健康检查
+        match device_check_result.as_ref().filter(|_| poll_adb) {
+            Some(Ok(devices)) => {
+                self.last_devices = devices.clone();
+                self.last_adb_poll = std::time::Instant::now();
+                self.adb_failures = 0;
+                self.last_adb_success = std::time::Instant::now();
+            }
+            Some(Err(_)) => self.record_adb_failure().await,
+            None => {}
+        }
+        device_check_result
+    }
+
+    /// 只有 adb devices 出错或超过它自己的超时才算失败，等待超时的查询仍在后台进行
+    async fn record_adb_failure(&mut self) {
+        self.adb_failures += 1;
+        // 偶尔慢一次很正常，只有持续失败才认为 adb server 挂死
+        if self.adb_failures < ADB_RESTART_FAILURES || self.last_adb_success.elapsed() < ADB_RESTART_AFTER {
+            return;
+        }
+        self.log(
+            LogLevel::Warning,
+            format!("adb 已连续 {} 秒无响应，正在重启 adb server...", self.last_adb_success.elapsed().as_secs())
+        ).await;
+        match self.device_monitor.restart_adb_server().await {
+            Ok(()) => self.log(LogLevel::Success, "adb server 已重启").await,
+            Err(e) => self.log(LogLevel::Error, format!("重启 adb server 失败: {}", e)).await,
+        }
+        // 无论成功与否都重新计时，避免反复重启
+        self.adb_failures = 0;
+        self.last_adb_success = std::time::Instant::now();
+    }
+
+    /// 处理本轮的设备列表：补全信息、提示状态变化、更新界面，并按目标设备启动或维护投屏会话
+    async fn update_devices(&mut self, mut devices: Vec<DeviceInfo>) {
+        self.annotate_devices(&mut devices).await;
+        let statuses_changed = self.track_statuses(&devices).await;
+        self.reconnect_wireless(&devices).await;
+
+        // 只在设备列表实际变化时更新UI
+        let device_count = devices.len();
+        let device_count_changed = device_count != self.last_device_count;
+        let info_changed = self.device_monitor.take_device_info_changed();
+        if device_count_changed || statuses_changed || info_changed || self.nicknames_changed || self.showing_stale || self.consecutive_checks.is_multiple_of(10) {
+            // 每10次检查或设备变化时更新UI
+            let _ = self.tx.send(TuiMessage::UpdateDevices(devices.clone())).await;
+            self.nicknames_changed = false;
+            self.showing_stale = false;
+        }
+        self.last_device_count = device_count;
+
+        // 用户选择的设备优先，其次按配置的优先级，同级时保持 adb 的顺序
+        let allowed = || devices.iter().filter(|d| d.status.is_ready());
+        let target = allowed()
+            .find(|d| self.preferred_device.as_ref() == Some(&d.id))
+            .or_else(|| allowed().min_by_key(|d| self.config.priority_rank(&d.id)));
+
+        let active_missing = self.scrcpy_started
+            && self.last_device_id.as_ref().is_some_and(|id| !allowed().any(|d| &d.id == id));
+        if self.hold_disconnected(active_missing).await {
+            // 等待设备重新出现，保持当前会话不变
+        } else if let Some(target) = target {
+            self.drive_session(&devices, target, device_count_changed).await;
+        } else {
+            self.reset_without_target().await;
+        }
+    }
+
+    /// 用 getprop 等信息补全设备，并按名单、连接检查与重启记录标记状态
+    async fn annotate_devices(&mut self, devices: &mut [DeviceInfo]) {
+        // getprop、屏幕参数与电量都在后台查询，这里只读取缓存，查询完成后下一轮再显示
+        self.device_monitor.refresh_device_info(devices);
+        self.device_monitor.fill_device_names(devices);
+        self.device_monitor.fill_battery(devices);
+        self.device_monitor.fill_details(devices);
+        for device in devices.iter_mut() {
+            device.nickname = self.config.nickname(&device.id).map(str::to_string);
+            // 被名单排除的设备仍然显示，但不自动启动
+            if device.status.is_ready() && !self.config.is_device_allowed(&device.id) {
+                device.status = DeviceStatus::Ignored;
+            }
+        }
+
+        // 连接检查判定为无响应的设备单独标记
+        self.health_check.retain(|serial| devices.iter().any(|d| d.id == serial));
+        for device in devices.iter_mut() {
+            if device.status == DeviceStatus::Connected && self.health_check.is_degraded(&device.id) {
+                device.status = DeviceStatus::Degraded;
+            }
+            // 重启过多而停止重试的设备标红，并显示最后的错误
+            if let Some(error) = self.crash_tracker.blocked(&device.id).filter(|_| device.status.is_ready()) {
+                device.status = DeviceStatus::Failed;
+                device.last_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// 与上一轮的状态比较：给出处理建议、发送上线与离线事件、重建端口转发；返回状态是否有变化
+    async fn track_statuses(&mut self, devices: &[DeviceInfo]) -> bool {
+        let statuses_changed = devices.len() != self.last_statuses.len()
+            || devices.iter().any(|d| self.last_statuses.get(&d.id) != Some(&d.status));
+        if statuses_changed {
+            self.last_activity = std::time::Instant::now();
+        }
+
+        // 设备进入未授权、离线等状态时给出处理建议，而不是默默忽略
+        for device in devices {
+            if self.last_statuses.get(&device.id) != Some(&device.status) {
+                if let Some(hint) = device.status.hint() {
+                    self.log(LogLevel::Warning, format!("设备 {} {}: {}", device.id, device.status.label(), hint)).await;
+                }
+            }
+        }
+
+        // 设备上线或离线时执行钩子命令（被名单排除的设备同样触发）
+        let mut newly_online = Vec::new();
+        for device in devices.iter().filter(|d| is_online(&d.status)) {
+            if self.last_statuses.get(&device.id) == Some(&DeviceStatus::Unauthorized) {
+                emit_event(&self.config, DeviceEvent::Authorized { serial: device.id.clone() }, &self.tx).await;
+            }
+            if !self.last_statuses.get(&device.id).is_some_and(is_online) {
+                let event = DeviceEvent::Connected { serial: device.id.clone(), name: device.display_name() };
+                emit_event(&self.config, event, &self.tx).await;
+                self.history.record_seen(&device.id, &device.name, now_secs());
+                self.history_changed = true;
+                newly_online.push(device.id.clone());
+            }
+        }
+        let online: Vec<&str> = devices.iter().filter(|d| is_online(&d.status)).map(|d| d.id.as_str()).collect();
+        self.history_changed |= self.history.set_online(&online);
+        let mut forwards_changed = !newly_online.is_empty();
+        for (serial, status) in &self.last_statuses {
+            if is_online(status) && !devices.iter().any(|d| &d.id == serial && is_online(&d.status)) {
+                // 重新连接后给设备新的重启机会
+                self.crash_tracker.reset(serial);
+                emit_event(&self.config, DeviceEvent::Disconnected { serial: serial.clone() }, &self.tx).await;
+                forwards_changed = true;
+            }
+        }
+        if forwards_changed {
+            self.update_forwards(devices, &newly_online);
+        }
+        self.last_statuses = devices.iter().map(|d| (d.id.clone(), d.status)).collect();
+        statuses_changed
+    }
+
+    /// 设备上线（包括掉线重连）时重新建立配置的端口转发，adb 会在设备断开时自动清除它们；之后刷新转发列表
+    /// 每条 adb 命令最长等待 5 秒，在后台进行，日志与转发列表直接发往界面
+    fn update_forwards(&mut self, devices: &[DeviceInfo], newly_online: &[String]) {
+        let rules: Vec<(String, Vec<ForwardRule>)> = newly_online
+            .iter()
+            .map(|serial| (serial.clone(), self.config.forwards(serial)))
+            .collect();
+        let online: Vec<String> = devices
+            .iter()
+            .filter(|d| is_online(&d.status))
+            .map(|d| d.id.clone())
+            .collect();
+        let (adb, tx, previous) = (self.device_monitor.adb(), self.tx.clone(), self.forwards_task.take());
+        self.forwards_task = Some(tokio::spawn(async move {
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            for (serial, rules) in rules {
+                for rule in rules {
+                    let (level, message) = match adb.apply_forward(&serial, &rule).await {
+                        Ok(()) => (LogLevel::Info, format!("设备 {} 已建立端口转发: {}", serial, rule.describe())),
+                        Err(e) => (LogLevel::Warning, format!("设备 {} 建立端口转发 {} 失败: {}", serial, rule.describe(), e)),
+                    };
+                    let _ = tx.send(TuiMessage::Log(level, message)).await;
+                }
+            }
+            let _ = tx.send(TuiMessage::Forwards(adb.list_forwards(&online).await)).await;
+        }));
+    }
+
+    /// 无线设备掉线（消失或变为离线）后按退避间隔重新 adb connect
+    async fn reconnect_wireless(&mut self, devices: &[DeviceInfo]) {
+        let now = std::time::Instant::now();
+        for device in devices.iter().filter(|d| is_online(&d.status)) {
+            self.reconnector.track(&device.id);
+        }
+        let dropped = self.reconnector.update(
+            |serial| devices.iter().any(|d| d.id == serial && is_online(&d.status)),
+            now,
+        );
+        for serial in dropped {
+            self.log(LogLevel::Warning, format!("无线设备 {} 已掉线，将自动尝试重新连接", serial)).await;
+        }
+        // 断开加重连最长要 8 秒，在后台进行，结果由 handle_event 处理
+        for (serial, attempt) in self.reconnector.due(now) {
+            let (adb, events) = (self.device_monitor.adb(), self.events_tx.clone());
+            tokio::spawn(async move {
+                let result = adb.reconnect_tcp(&serial).await;
+                let _ = events.send(BackgroundEvent::Reconnect(serial, attempt, result)).await;
+            });
+        }
+    }
+
+    /// USB 集线器不稳定等原因造成的短暂断开：scrcpy 仍在运行时先不结束它，也不切换到其他设备；
+    /// 返回是否继续等待设备重新出现
+    async fn hold_disconnected(&mut self, active_missing: bool) -> bool {
+        let grace = Duration::from_secs(self.config.disconnect_grace_secs);
+        let mut holding = false;
+        if active_missing && !grace.is_zero() && self.device_monitor.is_scrcpy_running().await {
+            let since = match self.disconnected_since {
+                Some(since) => since,
+                None => {
+                    self.log(
+                        LogLevel::Warning,
+                        format!(
+                            "设备 {} 暂时断开，{} 秒内重新连接将继续使用当前 scrcpy 窗口",
+                            self.last_device_id.as_deref().unwrap_or_default(),
+                            grace.as_secs()
+                        )
+                    ).await;
+                    *self.disconnected_since.insert(std::time::Instant::now())
+                }
+            };
+            holding = since.elapsed() < grace;
+        } else if !active_missing && self.scrcpy_started && self.disconnected_since.is_some() {
+            self.log(
+                LogLevel::Success,
+                format!("设备 {} 已重新连接，继续投屏", self.last_device_id.as_deref().unwrap_or_default())
+            ).await;
+        }
+        if !active_missing {
+            self.disconnected_since = None;
+        }
+        holding
+    }
+
+    /// 维护投屏目标的会话：处理 scrcpy 退出、记录会话状态、检查设备响应，需要时启动 scrcpy
+    async fn drive_session(&mut self, devices: &[DeviceInfo], target: &DeviceInfo, device_count_changed: bool) {
+        let current_device_id = &target.id;
+
+        // 检查scrcpy进程状态（如果认为已启动）
+        if self.scrcpy_started && !self.device_monitor.is_scrcpy_running().await {
+            self.handle_scrcpy_exit(current_device_id).await;
+        }
+
+        self.sample_session(current_device_id).await;
+
+        let target_changed = self.last_device_id.as_ref() != Some(current_device_id);
+        if target_changed {
+            // 换了设备，重新给参数重试的机会
+            self.rejected_options.clear();
+            self.flag_retry_used = false;
+            self.launch_suspended = false;
+        }
+
+        // adb devices 仍列出设备但会话可能已僵死（数据线接触不良、adbd 卡住等），定期用 adb shell 确认
+        if self.scrcpy_started && !target_changed && !self.ping_in_flight && self.health_check.due(std::time::Instant::now()) {
+            self.check_health(target);
+        }
+
+        // 在投屏目标变化或scrcpy未启动时启动；仅设备数量变化（如拔掉已切换无线的USB线）不打断当前投屏
+        let backoff_elapsed = self.restart_at.is_none_or(|t| std::time::Instant::now() >= t);
+        // 设备信息在后台查询，首次查询结束前不启动，避免按默认形态生成参数
+        let launch_allowed = ((!self.auto_launch_paused && self.stray_pids.is_empty()) || self.manual_launch)
+            && self.crash_tracker.blocked(current_device_id).is_none()
+            && self.device_monitor.device_info_ready(current_device_id);
+        if launch_allowed && ((!self.scrcpy_started && !self.launch_suspended && backoff_elapsed) || target_changed) {
+            self.restart_at = None;
+            self.manual_launch = false;
+            // 只在设备真正变化时显示发现日志
+            if target_changed || device_count_changed {
+                for device in devices {
+                    self.log(LogLevel::Device, format!("发现设备: {} ({})", device.display_name(), device.id)).await;
+                }
+            }
+            self.launch(target, target_changed).await;
+        }
+    }
+
+    /// scrcpy 已退出：报告原因，并按退出方式与重启策略决定是否以及何时重启
+    async fn handle_scrcpy_exit(&mut self, current_device_id: &str) {
+        let exit_kind = self.device_monitor.last_exit.as_ref().map(|e| e.kind()).unwrap_or(ExitKind::Crashed);
+        if exit_kind == ExitKind::Crashed {
+            let code = self.device_monitor.last_exit.as_ref().and_then(|e| e.code);
+            emit_event(&self.config, DeviceEvent::SessionCrashed { serial: current_device_id.to_string(), code }, &self.tx).await;
+            if let Some(failure) = ScrcpyFailure::detect(&self.device_monitor.scrcpy_stderr_lines()) {
+                self.log(LogLevel::Error, failure.message()).await;
+            }
+            if let Some(path) = &self.device_monitor.session_log {
+                self.log(LogLevel::Info, format!("完整输出见: {}", path.display())).await;
+            }
+        }
+        match self.device_monitor.rejected_option() {
+            Some(option) if !self.flag_retry_used => {
+                self.log(LogLevel::Warning, format!("scrcpy 不接受参数 {}，已移除该参数并重试一次", option)).await;
+                self.rejected_options.push(option);
+                self.flag_retry_used = true;
+            }
+            Some(option) => {
+                self.launch_suspended = true;
+                self.log(LogLevel::Error, format!("scrcpy 仍因参数 {} 启动失败，已停止自动重启，请检查参数设置", option)).await;
+            }
+            None if !self.active_kind.restart_on_exit() => {
+                self.launch_suspended = true;
+                self.recording_device = None;
+                clear_saved_session(&self.tx).await;
+                self.log(LogLevel::Info, format!("{}会话已结束，不会自动重启（重新连接设备后恢复）", self.active_kind.label())).await;
+            }
+            None if !self.config.restart_policy.should_restart(exit_kind) => {
+                self.launch_suspended = true;
+                clear_saved_session(&self.tx).await;
+                let reason = match exit_kind {
+                    ExitKind::UserClosed => "scrcpy 窗口已关闭",
+                    ExitKind::Disconnected => "scrcpy 与设备的连接已断开",
+                    ExitKind::Crashed => "scrcpy 异常退出",
+                };
+                self.log(LogLevel::Info, format!("{}，按重启策略不自动重启（按 Enter 可重新投屏）", reason)).await;
+            }
+            None => self.schedule_restart(current_device_id, exit_kind).await,
+        }
+        self.scrcpy_started = false; // 重置状态以触发重启
+        let _ = self.tx.send(TuiMessage::Session(None)).await;
+    }
+
+    /// 按退避时间安排重启；一小时内重启过多或连续崩溃时停止自动重启
+    async fn schedule_restart(&mut self, current_device_id: &str, exit_kind: ExitKind) {
+        // 只有异常退出计入崩溃次数，用户关闭或连接断开不会触发退避
+        let crashes = match exit_kind {
+            ExitKind::Crashed => {
+                let runtime = self.device_monitor.last_exit.as_ref().map(|e| e.runtime).unwrap_or_default();
+                self.crash_tracker.record_exit(current_device_id, runtime)
+            }
+            _ => {
+                self.crash_tracker.reset(current_device_id);
+                0
+            }
+        };
+        let restarts = self.crash_tracker.record_restart(current_device_id, std::time::Instant::now());
+        if let Some(limit) = self.config.restart_limit().filter(|limit| restarts > *limit) {
+            self.launch_suspended = true;
+            self.crash_looping = true;
+            let error = self.device_monitor.scrcpy_stderr_lines().pop().unwrap_or_else(|| {
+                match self.device_monitor.last_exit.as_ref().and_then(|e| e.code) {
+                    Some(code) => format!("scrcpy 退出码 {}", code),
+                    None => "scrcpy 已退出".to_string(),
+                }
+            });
+            self.log(
+                LogLevel::Error,
+                format!("设备 {} 一小时内已自动重启 {} 次，已停止重试，按 Enter 可重试: {}", current_device_id, limit, error)
+            ).await;
+            let _ = self.tx.send(TuiMessage::Status("⚠️ scrcpy 重启次数过多".to_string())).await;
+            self.crash_tracker.block(current_device_id, error);
+            self.force_device_check = true;
+        } else if crashes >= CRASH_LIMIT {
+            self.launch_suspended = true;
+            self.crash_looping = true;
+            let reason = self.device_monitor.scrcpy_stderr_lines().pop().unwrap_or_default();
+            self.log(
+                LogLevel::Error,
+                format!("scrcpy 持续崩溃（连续 {} 次启动后很快退出），已停止自动重启，按 Enter 可重试: {}", crashes, reason)
+            ).await;
+            let _ = self.tx.send(TuiMessage::Status("⚠️ scrcpy 持续崩溃".to_string())).await;
+        } else {
+            let delay = CrashTracker::backoff(crashes);
+            self.restart_at = Some(std::time::Instant::now() + delay);
+            let message = if delay.is_zero() {
+                "检测到scrcpy进程已结束，正在自动重启...".to_string()
             } else {
-                // 没有设备连接时，重置状态
-                if scrcpy_started {
-                    if let Some(device_id) = &last_device_id {
-                        let _ = tx.send(TuiMessage::Log(
+                format!("scrcpy 启动后很快退出（第 {} 次），{} 秒后重启...", crashes, delay.as_secs())
+            };
+            self.log(LogLevel::Warning, message).await;
+        }
+    }
+
+    /// 会话进行中的定期记录：帧率、窗口位置与录像磁盘的剩余空间
+    async fn sample_session(&mut self, current_device_id: &str) {
+        // 帧率统计有新数据时同步到界面
+        if let Some(stats) = self.device_monitor.fps_stats().filter(|s| self.scrcpy_started && s.samples != self.fps_samples) {
+            self.fps_samples = stats.samples;
+            let _ = self.tx.send(TuiMessage::Fps(Some((current_device_id.to_string(), stats)))).await;
+        }
+
+        // 定期记下窗口位置：scrcpy 退出后窗口已销毁，无法在结束时再读取
+        if self.scrcpy_started && self.active_kind.has_video() && self.window_sampled_at.elapsed() >= WINDOW_SAMPLE {
+            self.window_sampled_at = std::time::Instant::now();
+            let window = self.device_monitor.scrcpy_pid().and_then(window_layout::process_window);
+            if let Some(window) = window.filter(|_| self.config.group(current_device_id).is_none()) {
+                self.history_changed |= self.history.record_window(current_device_id, window);
+            }
+        }
+
+        if self.scrcpy_started && self.active_kind == SessionKind::Record && self.disk_checked_at.elapsed() >= DISK_SPACE_CHECK {
+            self.disk_checked_at = std::time::Instant::now();
+            self.check_disk_space().await;
+        }
+    }
+
+    /// 录屏期间定期检查剩余空间：不足下限两倍时提醒一次，低于下限时结束录制，让 scrcpy 写完文件尾
+    async fn check_disk_space(&mut self) {
+        let free = match recording_path(&self.device_monitor.last_args) {
+            Some(record_to) => free_space_for(std::path::Path::new(&record_to)).await,
+            None => None,
+        };
+        let (Some(min), Some(free)) = (self.config.recording.min_free_bytes(), free) else {
+            return;
+        };
+        if free < min {
+            self.log(
+                LogLevel::Error,
+                format!("录像目录所在磁盘剩余空间不足（剩余 {}），已结束录屏以免录像被截断", disk_space::format_size(free))
+            ).await;
+            self.recording_device = None;
+            self.stop_session().await;
+        } else if free < min.saturating_mul(2) && !self.disk_space_warned {
+            self.disk_space_warned = true;
+            self.log(
+                LogLevel::Warning,
+                format!("录像目录所在磁盘剩余空间仅 {}，低于 {} MB 时将自动结束录屏", disk_space::format_size(free), self.config.recording.min_free_mb)
+            ).await;
+        } else if free >= min.saturating_mul(2) {
+            self.disk_space_warned = false;
+        }
+    }
+
+    /// 在后台用 adb shell 确认投屏设备仍有响应，结果在之后的循环中由 record_health 处理
+    fn check_health(&mut self, target: &DeviceInfo) {
+        let timeout = self.config.form_factor(&target.id, target.details.as_ref().map(|d| d.form_factor)).adb_timeout(HEALTH_CHECK_TIMEOUT);
+        let (adb, events, serial) = (self.device_monitor.adb(), self.events_tx.clone(), target.id.clone());
+        self.ping_in_flight = true;
+        tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            let result = adb.ping(&serial, timeout).await;
+            let _ = events.send(BackgroundEvent::Ping(serial, started, result)).await;
+        });
+    }
+
+    /// 记录连接检查的结果，设备判定为无响应时重启投屏会话
+    async fn record_health(&mut self, current_device_id: &str, result: Result<(), String>) {
+        match self.health_check.record(current_device_id, result.is_ok(), std::time::Instant::now()) {
+            HealthEvent::Degraded => {
+                self.log(
+                    LogLevel::Warning,
+                    format!("设备 {} 无响应（{}），正在重启投屏会话...", current_device_id, result.err().unwrap_or_default())
+                ).await;
+                self.stop_session().await;
+                let _ = self.tx.send(TuiMessage::Fps(None)).await;
+                let _ = self.tx.send(TuiMessage::Session(None)).await;
+            }
+            HealthEvent::Recovered => {
+                self.log(LogLevel::Success, format!("设备 {} 已恢复响应", current_device_id)).await;
+            }
+            HealthEvent::Unchanged => {}
+        }
+    }
+
+    /// 投屏目标设备本次使用的会话类型：录屏切换优先，其次是恢复的会话、界面中的切换与配置文件
+    fn session_kind(&self, serial: &str) -> SessionKind {
+        if self.recording_device.as_deref() == Some(serial) {
+            return SessionKind::Record;
+        }
+        self.resumed_kind
+            .as_ref()
+            .filter(|(resumed, _)| resumed.as_str() == serial)
+            .map(|(_, kind)| *kind)
+            .or_else(|| self.session_overrides.get(serial).copied())
+            .unwrap_or_else(|| self.config.session_kind(serial))
+    }
+
+    /// 为投屏目标启动 scrcpy；会话类型不适用于该设备时暂停自动启动并说明原因
+    async fn launch(&mut self, target: &DeviceInfo, target_changed: bool) {
+        let current_device_id = &target.id;
+        let kind = self.session_kind(current_device_id);
+        self.log(LogLevel::Launch, format!("正在启动scrcpy（{} {}）...", kind.icon(), kind.label())).await;
+
+        if is_tcp_serial(current_device_id) && !kind.supports_tcp() {
+            self.launch_suspended = true;
+            self.last_device_id = Some(current_device_id.clone());
+            self.log(LogLevel::Error, format!("{}模式只支持USB连接的设备: {}", kind.label(), current_device_id)).await;
+        } else if let Some(reason) = unsupported_session(&self.device_monitor, target, kind) {
+            self.launch_suspended = true;
+            self.last_device_id = Some(current_device_id.clone());
+            self.log(LogLevel::Error, format!("设备 {} 无法使用{}模式: {}", current_device_id, kind.label(), reason)).await;
+        } else if let Some(free) = match kind {
+            SessionKind::Record => recording_space_low(&self.config.recording, target).await,
+            _ => None,
+        } {
+            // 按 v 开始的录屏取消后恢复原会话类型；会话类型本身就是录屏时暂停自动启动
+            if self.recording_device.take().is_none() {
+                self.launch_suspended = true;
+                self.last_device_id = Some(current_device_id.clone());
+            }
+            self.log(
+                LogLevel::Error,
+                format!(
+                    "录像目录所在磁盘剩余空间不足（剩余 {}，至少需要 {} MB），不开始录屏；清理磁盘或调整配置项 recording.min_free_mb 后重试",
+                    disk_space::format_size(free),
+                    self.config.recording.min_free_mb
+                )
+            ).await;
+        } else if self.device_monitor.is_scrcpy_available() {
+            let profile = active_profile(&self.config, &self.profile_overrides, current_device_id, target.server.is_some());
+            let setup = SessionSetup {
+                kind,
+                audio_enabled: self.audio_enabled,
+                user_args: &self.extra_args,
+                profile: profile.clone(),
+                show_fps: self.show_fps,
+                presentation: self.presentation,
+                window: self.history.window(current_device_id),
+                rejected_options: &self.rejected_options,
+            };
+            let launch_args = build_launch_args(&self.config, &self.device_monitor, target, &setup, &self.tx).await;
+            match self.device_monitor.start_scrcpy(Some(current_device_id), &launch_args).await {
+                Ok(unsupported) => {
+                    for item in unsupported {
+                        self.log(
                             LogLevel::Warning,
-                            format!("设备已断开连接: {}", device_id)
-                        )).await;
+                            format!(
+                                "当前 scrcpy 不支持{}（需要 {} 及以上），已忽略参数: {}",
+                                item.feature.name(),
+                                item.feature.min_version(),
+                                item.arg
+                            )
+                        ).await;
                     }
-                    device_monitor.stop_scrcpy();
-                    scrcpy_started = false;
-                    last_device_id = None;
-                }
-                
-                // 减少状态提示频率，从30秒增加到60秒
-                if last_status_update.elapsed().as_secs() >= 60 {
-                    let _ = tx.send(TuiMessage::Log(LogLevel::Info, status_waiting.clone())).await;
-                    last_status_update = std::time::Instant::now();
-                }
-            }
-        }
-        
-        // 动态调整检查间隔：更激进的优化策略
-        let check_interval = if consecutive_checks < 50 {
-            // 前12.5秒每100ms检查一次（超快响应初始连接）
-            Duration::from_millis(100)
-        } else if scrcpy_started && last_device_count > 0 {
-            // 设备已连接且scrcpy运行时，适度降低频率
-            Duration::from_millis(250)
+                    let session = SavedSession {
+                        serial: current_device_id.clone(),
+                        kind,
+                        profile,
+                        show_fps: self.show_fps,
+                        recording: recording_path(&launch_args),
+                        pid: self.device_monitor.scrcpy_pid(),
+                    };
+                    if let Err(e) = session.save() {
+                        self.log(LogLevel::Warning, e).await;
+                    }
+                    self.session_started(target, kind, target_changed).await;
+                }
+                Err(e) => {
+                    self.log(LogLevel::Error, format!("启动scrcpy失败: {}", e)).await;
+                    self.scrcpy_started = false;
+                }
+            }
+        } else {
+            self.log(LogLevel::Error, "scrcpy或adb未找到（已检查 scrcpy 目录、PATH 和常见安装位置），请确保scrcpy已正确安装").await;
+        }
+    }
+
+    /// scrcpy 启动成功后更新会话状态、历史记录与界面
+    async fn session_started(&mut self, target: &DeviceInfo, kind: SessionKind, target_changed: bool) {
+        let current_device_id = &target.id;
+        emit_event(&self.config, DeviceEvent::SessionStarted {
+            serial: current_device_id.clone(),
+            name: target.display_name(),
+            args: self.device_monitor.last_args.clone(),
+        }, &self.tx).await;
+        if let Some(path) = &self.device_monitor.session_log {
+            self.log(LogLevel::Info, format!("scrcpy 输出记录在: {}", path.display())).await;
+        }
+        self.scrcpy_started = true;
+        self.update_pending = false;
+        self.fps_samples = 0;
+        // 会话启动后再在后台探测编码器；OTG 会话不传输视频，也不需要 adb，不探测编码器
+        if kind.has_video() && self.seen_devices.insert(current_device_id.clone()) {
+            self.device_monitor.probe_video_encoders(current_device_id);
+        }
+        self.health_check.restart(std::time::Instant::now());
+        if std::mem::take(&mut self.crash_looping) {
+            let _ = self.tx.send(TuiMessage::Status("监控设备连接...".to_string())).await;
+        }
+        self.active_kind = kind;
+        let _ = self.tx.send(TuiMessage::Session(Some((current_device_id.clone(), kind)))).await;
+        self.history.record_session(current_device_id, now_secs());
+        self.history_changed = true;
+        if target_changed && !is_tcp_serial(current_device_id) {
+            self.log(LogLevel::Info, "提示: 按 'w' 可切换为无线连接，拔掉数据线后投屏不中断").await;
+        }
+        self.last_device_id = Some(current_device_id.clone());
+    }
+
+    /// 没有可启动的设备时，重置状态；断开的日志已由 DeviceEvent::Disconnected 输出
+    async fn reset_without_target(&mut self) {
+        if self.scrcpy_started {
+            self.stop_session().await;
+            self.last_device_id = None;
+            let _ = self.tx.send(TuiMessage::Fps(None)).await;
+            let _ = self.tx.send(TuiMessage::Session(None)).await;
+        }
+        self.rejected_options.clear();
+        self.flag_retry_used = false;
+        self.launch_suspended = false;
+        self.restart_at = None;
+        if std::mem::take(&mut self.crash_looping) {
+            let _ = self.tx.send(TuiMessage::Status("监控设备连接...".to_string())).await;
+        }
+
+        // 减少状态提示频率，从30秒增加到60秒
+        if self.last_status_update.elapsed().as_secs() >= 60 {
+            self.log(LogLevel::Info, "等待设备连接中...").await;
+            self.last_status_update = std::time::Instant::now();
+        }
+    }
+
+    /// 连接历史有变化时写回 history.toml 并刷新界面
+    async fn save_history(&mut self) {
+        if std::mem::take(&mut self.history_changed) {
+            if let Err(e) = self.history.save() {
+                self.log(LogLevel::Warning, e).await;
+            }
+            let _ = self.tx.send(TuiMessage::History(self.history.entries().to_vec())).await;
+        }
+    }
+
+    /// 使用电池或长时间空闲（没有投屏、设备与用户操作都无变化）时进入低功耗模式，切换时记录日志
+    async fn update_power_mode(&mut self) {
+        let idle = if self.scrcpy_started { Duration::ZERO } else { self.last_activity.elapsed() };
+        let want_low_power = self.config.polling.is_low_power(polling::on_battery(), idle);
+        if want_low_power != self.low_power {
+            self.low_power = want_low_power;
+            let message = if self.low_power { "已进入低功耗模式，降低设备检查频率" } else { "已退出低功耗模式" };
+            self.log(LogLevel::Info, message).await;
+        }
+    }
+
+    /// 动态调整检查间隔：启动初期高频检查以尽快发现设备，投屏中适度降低频率
+    async fn wait(&self, in_hotplug_burst: bool) {
+        let phase = if self.consecutive_checks < 50 || in_hotplug_burst {
+            PollPhase::Startup
+        } else if self.scrcpy_started && self.last_device_count > 0 {
+            PollPhase::Active
         } else {
-            // 等待设备连接时保持高频率
-            Duration::from_millis(150)
+            PollPhase::Waiting
         };
-        
-        sleep(check_interval).await;
+        let check_interval = self.config.polling.interval(phase, self.low_power);
+
+        match &self.hotplug {
+            Some(hotplug) => {
+                tokio::select! {
+                    _ = sleep(check_interval) => {}
+                    _ = hotplug.changed() => {}
+                }
+            }
+            None => sleep(check_interval).await,
+        }
     }
 }
 
-/// 检查连接的设备（使用传入的设备监控器实例）
-async fn check_connected_devices_with_monitor(device_monitor: &DeviceMonitor) -> Result<Vec<DeviceInfo>, String> {
-    // 检查adb是否可用
-    if !device_monitor.adb_exe.exists() {
-        return Err("ADB未找到，请确保scrcpy已正确安装".to_string());
+/// 查找上次启动器异常退出时遗留的 scrcpy 进程，并按配置结束、沿用或等待用户决定；返回仍需等待的进程
+async fn find_strays(config: &Config, tx: &mpsc::Sender<TuiMessage>) -> Vec<u32> {
+    // 只有会话记录中由启动器启动的进程才算，用户自己打开的 scrcpy 不受影响
+    let launched = SavedSession::load().ok().flatten().and_then(|session| session.pid);
+    let mut stray_pids = stray_process::find_stray_scrcpy(launched.as_slice());
+    if stray_pids.is_empty() {
+        return stray_pids;
     }
-    
-    // 使用设备监控器检查设备
-    device_monitor.check_devices().await
+    let pids = stray_pids.iter().map(|pid| pid.to_string()).collect::<Vec<_>>().join(", ");
+    match config.stray_scrcpy {
+        StrayAction::Terminate => end_stray_scrcpy(&mut stray_pids, tx).await,
+        StrayAction::Adopt => {
+            let _ = tx.send(TuiMessage::Log(
+                LogLevel::Info,
+                format!("发现上次遗留的 scrcpy 进程（PID {}），继续沿用，它们退出后再自动投屏", pids)
+            )).await;
+        }
+        StrayAction::Ask => {
+            let _ = tx.send(TuiMessage::Log(
+                LogLevel::Warning,
+                format!("发现上次遗留的 scrcpy 进程（PID {}），为免重复打开窗口暂不自动投屏；按 K 结束它们，或关闭其窗口后继续", pids)
+            )).await;
+        }
+    }
+    stray_pids
 }
 
+/// 一次启动中因调用方而异的设置：监控循环中来自按键切换的状态，`launch` 命令中来自配置与命令行
+struct SessionSetup<'a> {
+    kind: SessionKind,
+    audio_enabled: bool,
+    /// 已校验的 scrcpy_args
+    user_args: &'a [String],
+    /// 使用的配置档
+    profile: Option<String>,
+    show_fps: bool,
+    presentation: bool,
+    /// 上次关闭时的窗口位置
+    window: Option<window_layout::WindowGeometry>,
+    /// 已被当前 scrcpy 拒绝的参数
+    rejected_options: &'a [String],
+}
+
+/// 生成某台设备完整的 scrcpy 启动参数，监控循环与 `launch` 命令共用；
+/// 无效的连接方式参数或配置档会被忽略，原因通过 tx 发出
+async fn build_launch_args(
+    config: &Config,
+    device_monitor: &DeviceMonitor,
+    device: &DeviceInfo,
+    setup: &SessionSetup<'_>,
+    tx: &mpsc::Sender<TuiMessage>,
+) -> Vec<String> {
+    let serial = device.id.as_str();
+    let kind = setup.kind;
+    let record_to = config.recording.file_path(device.nickname.as_deref().unwrap_or(&device.name), serial);
+    if kind == SessionKind::Record {
+        if let Some(dir) = record_to.parent().filter(|d| !d.as_os_str().is_empty()) {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("创建录像目录失败: {}", e))).await;
+            }
+        }
+        let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("录像将保存到: {}", record_to.display()))).await;
+    }
+    let mut base_args = kind.args(&record_to);
+    if kind == SessionKind::VirtualDisplay {
+        base_args.extend(config.virtual_display(serial).args());
+    }
+    // WSA / Waydroid、电视盒子等使用各自的默认参数
+    let target_defaults = if kind.has_video() { target_default_args(config, device) } else { Vec::new() };
+    // 旧版 scrcpy 没有音频，不传音频参数，免得每次启动都提示参数被忽略
+    if audio_supported(device_monitor) && !target_defaults.iter().any(|arg| arg == "--no-audio") {
+        base_args.extend(config.audio.args(kind, setup.audio_enabled));
+    }
+    base_args.extend(target_defaults);
+    base_args.extend(setup.user_args.iter().cloned());
+    let remote = device.server.is_some();
+    // 连接方式的默认参数排在配置档之前，设备的配置档可以覆盖
+    let transport_args = &config.transport(serial, remote).args;
+    match scrcpy_compat::validate_user_args(transport_args) {
+        Ok(()) => base_args.extend(transport_args.iter().cloned()),
+        Err(e) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("连接方式的默认参数无效（{}），已忽略", e))).await;
+        }
+    }
+    if let Some(name) = &setup.profile {
+        match config.profiles.get(name) {
+            Some(profile) => match scrcpy_compat::validate_user_args(&profile.args) {
+                Ok(()) => base_args.extend(profile.args.iter().cloned()),
+                Err(e) => {
+                    let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("配置档 {} 无效（{}），已忽略", name, e))).await;
+                }
+            },
+            None => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("配置档 {} 不存在，已忽略", name))).await;
+            }
+        }
+    }
+    if setup.show_fps && kind.has_video() {
+        base_args.push("--print-fps".to_string());
+    }
+    // 关闭屏幕与保持唤醒都需要控制设备，只对镜像画面的会话生效
+    if kind.has_video() {
+        base_args.extend(config.screen_args(serial));
+    }
+    // 演示模式使用固定窗口大小，不再恢复上次的窗口位置
+    if setup.presentation && kind.has_video() {
+        let args = config.presentation.args(&base_args);
+        base_args.extend(args);
+    }
+    // 不属于设备组时在上次的位置打开窗口，用户在 scrcpy_args 中指定了窗口位置时以用户为准
+    let window = setup.window.filter(|_| config.group(serial).is_none());
+    if let Some(window) = window.filter(|_| kind.has_video() && !has_window_args(&base_args)) {
+        base_args.extend(window.args());
+    }
+    if kind.has_video() && config.wake_device(serial) {
+        wake_device(config, device_monitor, serial, tx).await;
+    }
+    // 虚拟显示器会话已经指定了要启动的应用
+    let app = config.start_app(serial).filter(|_| !base_args.iter().any(|a| a.starts_with("--start-app")));
+    if let Some(app) = app.filter(|_| kind.has_video()) {
+        start_app(device_monitor, serial, app, &mut base_args, tx).await;
+    }
+    device_launch_args(config, device_monitor, serial, kind, &base_args, setup.rejected_options, tx).await
+}
+
+/// 生成某台设备的 scrcpy 启动参数：通用参数 + 设备配置，再去掉已被 scrcpy 拒绝的参数
+async fn device_launch_args(
+    config: &Config,
+    device_monitor: &DeviceMonitor,
+    serial: &str,
+    kind: SessionKind,
+    base_args: &[String],
+    rejected_options: &[String],
+    tx: &mpsc::Sender<TuiMessage>,
+) -> Vec<String> {
+    let mut args = base_args.to_vec();
+
+    let encoder = config.device(serial).and_then(|d| d.video_encoder.as_deref()).filter(|_| kind.has_video());
+    if let Some(encoder) = encoder {
+        match device_monitor.video_encoders(serial) {
+            Some(encoders) if !encoders.is_empty() => match encoders.iter().find(|e| e.name == encoder) {
+                Some(found) => {
+                    if found.codec != "h264" {
+                        args.push(format!("--video-codec={}", found.codec));
+                    }
+                    args.push(format!("--video-encoder={}", found.name));
+                }
+                None => {
+                    let _ = tx.send(TuiMessage::Log(
+                        LogLevel::Warning,
+                        format!("设备 {} 没有编码器 {}，将使用默认编码器", serial, encoder)
+                    )).await;
+                }
+            },
+            // 尚未拿到编码器列表时按配置原样传入，交给 scrcpy 判断
+            _ => {
+                device_monitor.probe_video_encoders(serial);
+                args.push(format!("--video-encoder={}", encoder));
+            }
+        }
+    }
+
+    if let Some((name, group, index)) = config.group(serial) {
+        match group.window_args(index, &window_layout::monitors()) {
+            Ok(window_args) => args.extend(window_args),
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(
+                    LogLevel::Warning,
+                    format!("设备组 {} 的窗口位置无效，将使用默认位置: {}", name, e)
+                )).await;
+            }
+        }
+    }
+
+    for option in rejected_options {
+        args = scrcpy_compat::remove_option(&args, option);
+    }
+    args
+}
+
+/// 设备的录像将要写入的磁盘剩余空间低于配置的下限时返回剩余空间
+async fn recording_space_low(recording: &RecordingConfig, device: &DeviceInfo) -> Option<u64> {
+    let min = recording.min_free_bytes()?;
+    let record_to = recording.file_path(device.nickname.as_deref().unwrap_or(&device.name), &device.id);
+    free_space_for(&record_to).await.filter(|free| *free < min)
+}
+
+/// 录像文件所在磁盘的剩余空间；文件名模板可以包含子目录，因此按文件的上级目录而不是 save_dir 查询。
+/// 查询可能要执行 df，放到阻塞线程中进行
+async fn free_space_for(record_to: &std::path::Path) -> Option<u64> {
+    let dir = record_to.parent().map(std::path::Path::to_path_buf).unwrap_or_default();
+    tokio::task::spawn_blocking(move || disk_space::free_bytes(&dir)).await.ok().flatten()
+}
+
+/// 参数中是否已指定窗口位置或大小
+fn has_window_args(args: &[String]) -> bool {
+    args.iter().any(|arg| ["--window-x", "--window-y", "--window-width", "--window-height"].iter().any(|o| arg.starts_with(o)))
+}
+
+/// 启动投屏前唤醒设备，并执行配置的解锁命令；失败时只记录日志，不影响启动
+async fn wake_device(config: &Config, device_monitor: &DeviceMonitor, serial: &str, tx: &mpsc::Sender<TuiMessage>) {
+    if let Err(e) = device_monitor.wake_device(serial).await {
+        let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("唤醒设备 {} 失败: {}", serial, e))).await;
+        return;
+    }
+    let Some(command) = config.unlock_command(serial) else {
+        return;
+    };
+    // 等待锁屏界面出现后再输入，否则滑动或 PIN 可能被忽略
+    sleep(Duration::from_millis(500)).await;
+    match device_monitor.run_shell(serial, command).await {
+        Ok(_) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("已对设备 {} 执行解锁命令", serial))).await;
+        }
+        Err(e) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("设备 {} 解锁命令执行失败: {}", serial, e))).await;
+        }
+    }
+}
+
+/// 投屏开始时打开配置的应用：scrcpy 3.0+ 附加 --start-app，旧版本或版本未知时先通过 adb 启动
+async fn start_app(device_monitor: &DeviceMonitor, serial: &str, app: &str, args: &mut Vec<String>, tx: &mpsc::Sender<TuiMessage>) {
+    match device_monitor.start_app(serial, app).await {
+        Ok(Some(arg)) => args.push(arg),
+        Ok(None) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("已在设备 {} 上打开应用 {}", serial, app))).await;
+        }
+        Err(e) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("在设备 {} 上打开应用失败: {}", serial, e))).await;
+        }
+    }
+}
+
+/// 记录后台探测到的视频编码器，方便用户在配置中挑选；探测失败的设备下次投屏时重新探测
+async fn log_video_encoders(device_monitor: &DeviceMonitor, seen_devices: &mut HashSet<String>, tx: &mpsc::Sender<TuiMessage>) {
+    for (serial, result) in device_monitor.take_encoder_probes() {
+        match result {
+            Ok(encoders) if !encoders.is_empty() => {
+                let list: Vec<String> = encoders
+                    .iter()
+                    .map(|e| format!("{} ({}, {})", e.name, e.codec, if e.hardware { "硬件" } else { "软件" }))
+                    .collect();
+                let _ = tx.send(TuiMessage::Log(
+                    LogLevel::Info,
+                    format!("设备 {} 可用视频编码器: {}", serial, list.join(", "))
+                )).await;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                // 失败不缓存，下次投屏时重新探测
+                seen_devices.remove(&serial);
+                let _ = tx.send(TuiMessage::Log(LogLevel::Warning, format!("探测视频编码器失败: {}", e))).await;
+            }
+        }
+    }
+}
+
+/// 在 USB 与无线连接之间切换，返回之后应优先投屏的设备
+async fn toggle_wireless(
+    device_monitor: &DeviceMonitor,
+    serial: &str,
+    tx: &mpsc::Sender<TuiMessage>,
+) -> Option<String> {
+    if is_tcp_serial(serial) {
+        // 已是无线连接：断开后回到 USB（如果数据线仍插着）
+        return match device_monitor.adb().disconnect_tcp(serial).await {
+            Ok(()) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Device, format!("已断开无线连接: {}", serial))).await;
+                None
+            }
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Error, e)).await;
+                Some(serial.to_string())
+            }
+        };
+    }
+
+    let _ = tx.send(TuiMessage::Log(LogLevel::Device, format!("正在为 {} 开启无线调试...", serial))).await;
+    match device_monitor.enable_wireless(serial, WIRELESS_PORT).await {
+        Ok(address) => {
+            let _ = tx.send(TuiMessage::Log(
+                LogLevel::Success,
+                format!("已通过无线连接设备: {}，现在可以拔掉数据线", address)
+            )).await;
+            Some(address)
+        }
+        Err(e) => {
+            let _ = tx.send(TuiMessage::Log(LogLevel::Error, format!("切换无线连接失败: {}", e))).await;
+            None
+        }
+    }
+}
+
+/// 无线调试配对，配对成功后连接设备（未提供连接地址时依赖 adb 的 mDNS 自动连接）
+async fn pair_device(
+    adb: &Adb,
+    address: &str,
+    code: &str,
+    connect_address: Option<&str>,
+    tx: &mpsc::Sender<TuiMessage>,
+) {
+    let _ = tx.send(TuiMessage::Log(LogLevel::Device, format!("正在与 {} 配对...", address))).await;
+    if let Err(e) = adb.pair(address, code).await {
+        let _ = tx.send(TuiMessage::Log(LogLevel::Error, e)).await;
+        return;
+    }
+    let _ = tx.send(TuiMessage::Log(LogLevel::Success, format!("已与 {} 配对成功", address))).await;
+
+    match connect_address {
+        Some(connect_address) => match adb.connect_tcp(connect_address).await {
+            Ok(()) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Success, format!("已连接无线设备: {}", connect_address))).await;
+            }
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(LogLevel::Error, e)).await;
+            }
+        },
+        None => {
+            let _ = tx.send(TuiMessage::Log(
+                LogLevel::Info,
+                "未填写连接端口，等待 adb 自动发现设备；如未出现请填写手机上显示的连接端口重新配对".to_string()
+            )).await;
+        }
+    }
+}
+
+/// 设备当前使用的配置档：界面中的切换优先，其次是配置文件
+fn active_profile(config: &Config, overrides: &HashMap<String, Option<String>>, serial: &str, remote: bool) -> Option<String> {
+    match overrides.get(serial) {
+        Some(name) => name.clone(),
+        None => config.profile_name(serial, remote).map(String::from),
+    }
+}
+
+/// 设备是否来自远程 adb server
+fn is_remote(devices: &[DeviceInfo], serial: &str) -> bool {
+    devices.iter().any(|d| d.id == serial && d.server.is_some())
+}
+
+/// 会话类型依赖设备不支持的功能时返回原因，避免反复启动必然失败的 scrcpy
+fn unsupported_session(device_monitor: &DeviceMonitor, device: &DeviceInfo, kind: SessionKind) -> Option<String> {
+    let details = device.details.as_ref();
+    let support = match kind {
+        SessionKind::AudioOnly => details.map(|d| d.audio.clone()),
+        SessionKind::Camera => details.map(|d| d.camera.clone()),
+        SessionKind::VirtualDisplay => {
+            Some(Support::check(Feature::NewDisplay, details.and_then(|d| d.sdk), device_monitor.scrcpy_version))
+        }
+        _ => return None,
+    };
+    if let Some(Support::Unsupported(reason)) = support {
+        return Some(reason);
+    }
+    // 系统版本满足要求时再确认设备确实有摄像头；探测在后台进行，尚无结果时照常启动，交给 scrcpy 自己报错
+    match kind {
+        SessionKind::Camera if device_monitor.is_scrcpy_available() => match device_monitor.camera_count(&device.id) {
+            Some(0) => Some("设备没有可用的摄像头".to_string()),
+            Some(_) => None,
+            None => {
+                device_monitor.probe_cameras(&device.id);
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+/// 按设备类型（本机 WSA / Waydroid、设备形态）默认附加的 scrcpy 参数
+fn target_default_args(config: &Config, device: &DeviceInfo) -> Vec<String> {
+    if let Some(local_target) = LocalTarget::identify(&device.id, &device.name) {
+        return local_target.default_args();
+    }
+    let resolution = device.details.as_ref().and_then(|d| d.resolution);
+    config.form_factor(&device.id, device.details.as_ref().map(|d| d.form_factor)).default_args(resolution)
+}
+
+/// 已安装的 scrcpy 是否支持音频转发（版本未知时视为支持，交给 scrcpy 自己报错）
+fn audio_supported(device_monitor: &DeviceMonitor) -> bool {
+    device_monitor.scrcpy_version.is_none_or(|v| Feature::Audio.is_supported_by(v))
+}
+
+/// 历史中上次退出前在线的设备，以"上次在线"状态显示
+fn stale_devices(history: &DeviceHistory, config: &Config) -> Vec<DeviceInfo> {
+    history
+        .last_online()
+        .map(|entry| DeviceInfo {
+            id: entry.serial.clone(),
+            name: if entry.name.is_empty() { "Android设备".to_string() } else { entry.name.clone() },
+            status: DeviceStatus::Stale,
+            battery: None,
+            nickname: config.nickname(&entry.serial).map(str::to_string),
+            details: None,
+            product: None,
+            transport_id: None,
+            server: None,
+            last_error: None,
+        })
+        .collect()
+}
+
+/// 结束遗留的 scrcpy 进程，之后恢复自动投屏
+async fn end_stray_scrcpy(stray_pids: &mut Vec<u32>, tx: &mpsc::Sender<TuiMessage>) {
+    for pid in std::mem::take(stray_pids) {
+        // 等待进程退出期间会阻塞线程，不放在监控循环上执行
+        let _ = tokio::task::spawn_blocking(move || stray_process::terminate(pid)).await;
+        let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("已结束遗留的 scrcpy 进程 (PID {})", pid))).await;
+    }
+}
+
+/// 会话由用户结束（关闭窗口、录屏结束）后删除会话记录，下次启动不再提示恢复
+async fn clear_saved_session(tx: &mpsc::Sender<TuiMessage>) {
+    if let Err(e) = SavedSession::clear() {
+        let _ = tx.send(TuiMessage::Log(LogLevel::Warning, e)).await;
+    }
+}
+
+/// 发送设备事件，并执行事件对应的钩子命令
+async fn emit_event(config: &Config, event: DeviceEvent, tx: &mpsc::Sender<TuiMessage>) {
+    if let Some(hook) = event.hook_event() {
+        spawn_hook(config, hook, event.serial(), tx);
+    }
+    let _ = tx.send(TuiMessage::Device(event)).await;
+}
+
+/// 在后台执行设备钩子命令，命令输出写入日志
+fn spawn_hook(config: &Config, event: HookEvent, serial: &str, tx: &mpsc::Sender<TuiMessage>) {
+    let Some(command) = config.hook(serial, event) else {
+        return;
+    };
+    let command = command.to_string();
+    let serial = serial.to_string();
+    let tx = tx.clone();
+
+    tokio::spawn(async move {
+        let _ = tx.send(TuiMessage::Log(
+            LogLevel::Info,
+            format!("设备 {} 已{}，执行钩子: {}", serial, event.label(), command)
+        )).await;
+        match hooks::run_hook(&command, event, &serial).await {
+            Ok(lines) => {
+                for line in lines {
+                    let _ = tx.send(TuiMessage::Log(LogLevel::Info, format!("[钩子] {}", line))).await;
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(TuiMessage::Log(
+                    LogLevel::Error,
+                    format!("设备 {} 的{}钩子失败: {}", serial, event.label(), e)
+                )).await;
+            }
+        }
+    });
+}
+
+/// adb devices 连续失败达到该次数，且距上次成功超过 ADB_RESTART_AFTER 时重启 adb server
+const ADB_RESTART_FAILURES: u32 = 3;
+const ADB_RESTART_AFTER: Duration = Duration::from_secs(10);
+
+/// 启用 USB 热插拔通知时的兜底轮询间隔（无线设备断开等不会产生 USB 事件）
+const HOTPLUG_FALLBACK_POLL: Duration = Duration::from_secs(3);
+
+/// 收到 USB 插拔事件后按启动初期的频率轮询 adb 的时长
+const HOTPLUG_BURST: Duration = Duration::from_secs(5);
+
+/// 扫码配对查询 mDNS 服务的间隔与最长等待时间
+const QR_PAIRING_POLL: Duration = Duration::from_secs(1);
+const QR_PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 检查遗留 scrcpy 是否已退出的间隔（需要遍历进程列表）
+const STRAY_CHECK: Duration = Duration::from_secs(3);
+
+/// 查找本机 WSA / Waydroid 的间隔
+const LOCAL_TARGET_SCAN: Duration = Duration::from_secs(30);
+
+/// 投屏期间读取 scrcpy 窗口位置的间隔
+const WINDOW_SAMPLE: Duration = Duration::from_secs(2);
+
+/// 录屏期间检查磁盘剩余空间的间隔
+const DISK_SPACE_CHECK: Duration = Duration::from_secs(10);
+
+/// adb tcpip 使用的端口
+const WIRELESS_PORT: u16 = 5555;
 
 /// 获取scrcpy目录
 fn get_scrcpy_directory() -> PathBuf {
@@ -268,6 +2333,11 @@ fn get_scrcpy_directory() -> PathBuf {
     if program_files.exists() {
         return program_files;
     }
+
+    // 再尝试 scoop、winget、chocolatey 或注册表中已安装的 scrcpy，避免重复下载
+    if let Some(installed) = device_monitor::scrcpy_install_dirs().into_iter().next() {
+        return installed;
+    }
     
     // 默认返回当前目录下的scrcpy文件夹
     current_dir_scrcpy
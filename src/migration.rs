@@ -0,0 +1,139 @@
+//! 格式迁移模块
+//! config.toml 与 history.toml、session.toml 等状态文件用 schema_version 记录格式版本，
+//! 读取旧版本文件时按顺序执行迁移升级到当前格式，写回前把原文件备份为 <文件名>.v<旧版本>.bak
+
+use std::path::{Path, PathBuf};
+
+use toml_edit::{value, DocumentMut};
+
+/// 记录格式版本的顶层字段，缺少该字段的文件视为版本 0
+pub const VERSION_KEY: &str = "schema_version";
+
+/// 一步迁移：把文档从上一个版本升级到下一个版本，可以改名、移动或删除字段
+pub type Migration = fn(&mut DocumentMut) -> Result<(), String>;
+
+/// 一种文件的格式定义
+pub struct Schema {
+    /// 文件的中文名称，用于错误信息
+    pub name: &'static str,
+    /// 第 N 项把版本 N 升级到 N + 1，当前版本即迁移的数量
+    pub migrations: &'static [Migration],
+}
+
+/// 一个已读取的文件
+pub struct Loaded {
+    /// 升级到当前格式后的内容
+    pub content: String,
+    /// 发生了升级时原文件的备份位置
+    pub backup: Option<PathBuf>,
+}
+
+/// 版本 0 → 1：引入版本号之前的文件格式没有变化，只记录版本
+pub fn add_version(_: &mut DocumentMut) -> Result<(), String> {
+    Ok(())
+}
+
+impl Schema {
+    /// 当前程序使用的格式版本
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// 在序列化结果前加上当前版本号，供 toml::to_string 写出的状态文件使用
+    pub fn stamp(&self, content: &str) -> String {
+        format!("{} = {}\n{}", VERSION_KEY, self.version(), content)
+    }
+
+    /// 把内容升级到当前格式，返回 (原版本, 升级后的内容)；已是当前格式时返回 None
+    pub fn migrate(&self, content: &str) -> Result<Option<(u32, String)>, String> {
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| format!("{}格式错误: {}", self.name, e))?;
+        let version = match doc.get(VERSION_KEY) {
+            None => 0,
+            Some(item) => item
+                .as_integer()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| format!("{}中的 {} 不是有效的版本号", self.name, VERSION_KEY))?,
+        };
+        if version == self.version() {
+            return Ok(None);
+        }
+        if version > self.version() {
+            return Err(format!(
+                "{}的格式版本为 {}，当前程序只支持到版本 {}，请升级启动器",
+                self.name,
+                version,
+                self.version()
+            ));
+        }
+
+        for (step, migration) in self.migrations.iter().enumerate().skip(version as usize) {
+            migration(&mut doc).map_err(|e| format!("{}从版本 {} 升级失败: {}", self.name, step, e))?;
+        }
+        doc[VERSION_KEY] = value(i64::from(self.version()));
+        Ok(Some((version, doc.to_string())))
+    }
+
+    /// 读取文件并升级到当前格式，发生升级时先备份原文件再写回；文件不存在时返回 None
+    pub fn load(&self, path: &Path) -> Result<Option<Loaded>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取{}失败 {}: {}", self.name, path.display(), e))?;
+        let Some((version, upgraded)) = self.migrate(&content)? else {
+            return Ok(Some(Loaded { content, backup: None }));
+        };
+
+        let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let backup = path.with_file_name(format!("{}.v{}.bak", file_name, version));
+        std::fs::copy(path, &backup).map_err(|e| format!("备份{}失败 {}: {}", self.name, backup.display(), e))?;
+        std::fs::write(path, &upgraded).map_err(|e| format!("保存升级后的{}失败: {}", self.name, e))?;
+        Ok(Some(Loaded { content: upgraded, backup: Some(backup) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 版本 1 → 2：把 fps 改名为 show_fps
+    fn rename_fps(doc: &mut DocumentMut) -> Result<(), String> {
+        if let Some(item) = doc.remove("fps") {
+            doc["show_fps"] = item;
+        }
+        Ok(())
+    }
+
+    const TEST_SCHEMA: Schema = Schema { name: "测试文件", migrations: &[add_version, rename_fps] };
+
+    #[test]
+    fn upgrades_old_formats() {
+        let (version, upgraded) = TEST_SCHEMA.migrate("fps = true\n\n# 设备\n[devices.a]\n").unwrap().unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(upgraded, "show_fps = true\nschema_version = 2\n\n# 设备\n[devices.a]\n");
+
+        assert!(TEST_SCHEMA.migrate(&TEST_SCHEMA.stamp("show_fps = true\n")).unwrap().is_none());
+        assert!(TEST_SCHEMA.migrate("schema_version = 3\n").is_err());
+        assert!(TEST_SCHEMA.migrate("schema_version = \"1\"\n").is_err());
+    }
+
+    #[test]
+    fn backs_up_before_rewriting() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-migration-{}.toml", std::process::id()));
+        std::fs::write(&path, "schema_version = 1\nfps = true\n").unwrap();
+
+        let loaded = TEST_SCHEMA.load(&path).unwrap().unwrap();
+        let backup = loaded.backup.unwrap();
+        assert_eq!(backup.file_name().unwrap().to_string_lossy(), format!("{}.v1.bak", path.file_name().unwrap().to_string_lossy()));
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "schema_version = 1\nfps = true\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), loaded.content);
+
+        // 已是当前格式时不再备份
+        assert!(TEST_SCHEMA.load(&path).unwrap().unwrap().backup.is_none());
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup);
+        assert!(TEST_SCHEMA.load(&path).unwrap().is_none());
+    }
+}
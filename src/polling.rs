@@ -0,0 +1,116 @@
+//! 轮询策略模块
+//! 监控循环的检查间隔与 adb 超时，以及使用电池或长时间无事发生时的低功耗模式
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// 轮询配置，时间单位为毫秒（idle_after_secs 除外）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollingConfig {
+    /// 启动后前 50 次检查的间隔，尽快发现已连接的设备
+    pub startup_ms: u64,
+    /// 等待设备连接时的间隔
+    pub waiting_ms: u64,
+    /// scrcpy 运行中的间隔
+    pub active_ms: u64,
+    /// 每次循环等待 adb devices 结果的时间，未返回时先处理其他事务，查询在后台继续
+    pub adb_timeout_ms: u64,
+    /// 使用电池或长时间空闲时降低轮询频率
+    pub low_power: bool,
+    /// 低功耗模式下的间隔
+    pub low_power_ms: u64,
+    /// 设备列表与用户操作持续无变化多久后视为空闲
+    pub idle_after_secs: u64,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self {
+            startup_ms: 100,
+            waiting_ms: 150,
+            active_ms: 250,
+            adb_timeout_ms: 50,
+            low_power: false,
+            low_power_ms: 3000,
+            idle_after_secs: 600,
+        }
+    }
+}
+
+/// 监控循环当前所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollPhase {
+    Startup,
+    Waiting,
+    Active,
+}
+
+/// 间隔下限，避免误配置为 0 时占满 CPU
+const MIN_INTERVAL_MS: u64 = 10;
+/// 配置了远程 adb server 时 adb devices 超时的下限，网络往返通常超过本机的默认超时
+const REMOTE_ADB_TIMEOUT_MS: u64 = 1000;
+
+impl PollingConfig {
+    /// 本轮检查结束后的等待时间，低功耗模式只放慢等待与投屏阶段
+    pub fn interval(&self, phase: PollPhase, low_power: bool) -> Duration {
+        let ms = match phase {
+            PollPhase::Startup => self.startup_ms,
+            _ if low_power => self.low_power_ms,
+            PollPhase::Waiting => self.waiting_ms,
+            PollPhase::Active => self.active_ms,
+        };
+        Duration::from_millis(ms.max(MIN_INTERVAL_MS))
+    }
+
+    /// adb devices 的超时，remote 表示需要同时查询远程 adb server
+    pub fn adb_timeout(&self, remote: bool) -> Duration {
+        let min = if remote { REMOTE_ADB_TIMEOUT_MS } else { MIN_INTERVAL_MS };
+        Duration::from_millis(self.adb_timeout_ms.max(min))
+    }
+
+    /// 是否应进入低功耗模式
+    pub fn is_low_power(&self, on_battery: bool, idle: Duration) -> bool {
+        self.low_power && (on_battery || idle >= Duration::from_secs(self.idle_after_secs))
+    }
+}
+
+/// 电脑当前是否使用电池供电（无法判断时视为接通电源）
+#[cfg(windows)]
+pub fn on_battery() -> bool {
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    // ACLineStatus: 0 为电池供电，1 为接通电源，255 为未知
+    unsafe { GetSystemPowerStatus(&mut status) != 0 && status.ACLineStatus == 0 }
+}
+
+#[cfg(not(windows))]
+pub fn on_battery() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_interval_by_phase_and_power() {
+        let config = PollingConfig { low_power: true, ..PollingConfig::default() };
+        assert_eq!(config.interval(PollPhase::Startup, true), Duration::from_millis(100));
+        assert_eq!(config.interval(PollPhase::Waiting, false), Duration::from_millis(150));
+        assert_eq!(config.interval(PollPhase::Active, true), Duration::from_secs(3));
+
+        assert!(config.is_low_power(true, Duration::ZERO));
+        assert!(config.is_low_power(false, Duration::from_secs(600)));
+        assert!(!config.is_low_power(false, Duration::from_secs(60)));
+        assert!(!PollingConfig::default().is_low_power(true, Duration::from_secs(3600)));
+
+        let zero = PollingConfig { waiting_ms: 0, ..PollingConfig::default() };
+        assert_eq!(zero.interval(PollPhase::Waiting, false), Duration::from_millis(MIN_INTERVAL_MS));
+
+        assert_eq!(config.adb_timeout(false), Duration::from_millis(50));
+        assert_eq!(config.adb_timeout(true), Duration::from_millis(REMOTE_ADB_TIMEOUT_MS));
+    }
+}
@@ -0,0 +1,116 @@
+//! 端口转发模块
+//! 描述 adb forward / adb reverse 规则，设备每次上线时由监控循环重新应用
+
+use serde::{Deserialize, Serialize};
+
+/// 转发方向
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardDirection {
+    /// 电脑端口转发到设备（adb forward），例如调试设备上的网页
+    #[default]
+    Forward,
+    /// 设备端口转发到电脑（adb reverse），例如让设备访问电脑上的开发服务器
+    Reverse,
+}
+
+impl ForwardDirection {
+    pub fn label(self) -> &'static str {
+        match self {
+            ForwardDirection::Forward => "forward",
+            ForwardDirection::Reverse => "reverse",
+        }
+    }
+}
+
+/// 一条转发规则，local 为电脑端，remote 为设备端，格式与 adb 相同（tcp:9222、localabstract:xxx 等）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardRule {
+    #[serde(default)]
+    pub direction: ForwardDirection,
+    pub local: String,
+    pub remote: String,
+}
+
+impl ForwardRule {
+    /// 界面与日志中显示的文本，箭头指向数据流向
+    pub fn describe(&self) -> String {
+        match self.direction {
+            ForwardDirection::Forward => format!("电脑 {} → 设备 {}", self.local, self.remote),
+            ForwardDirection::Reverse => format!("设备 {} → 电脑 {}", self.remote, self.local),
+        }
+    }
+
+    /// 建立该规则的 adb 参数（不含 -s）
+    pub fn adb_args(&self) -> [&str; 3] {
+        match self.direction {
+            ForwardDirection::Forward => ["forward", &self.local, &self.remote],
+            // adb reverse 的参数顺序是 设备端 电脑端
+            ForwardDirection::Reverse => ["reverse", &self.remote, &self.local],
+        }
+    }
+}
+
+/// adb 报告的一条生效中的转发
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveForward {
+    pub serial: String,
+    pub rule: ForwardRule,
+}
+
+/// 解析 `adb forward --list` 的输出，每行为 "序列号 电脑端 设备端"
+pub fn parse_forward_list(output: &str) -> Vec<ActiveForward> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (serial, local, remote) = (fields.next()?, fields.next()?, fields.next()?);
+            Some(ActiveForward {
+                serial: serial.to_string(),
+                rule: ForwardRule {
+                    direction: ForwardDirection::Forward,
+                    local: local.to_string(),
+                    remote: remote.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// 解析 `adb -s <serial> reverse --list` 的输出，每行为 "连接名 设备端 电脑端"
+pub fn parse_reverse_list(serial: &str, output: &str) -> Vec<ActiveForward> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_, remote, local) = (fields.next()?, fields.next()?, fields.next()?);
+            Some(ActiveForward {
+                serial: serial.to_string(),
+                rule: ForwardRule {
+                    direction: ForwardDirection::Reverse,
+                    local: local.to_string(),
+                    remote: remote.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_forward_and_reverse_lists() {
+        let forwards = parse_forward_list("R58M123456 tcp:9222 localabstract:chrome_devtools_remote\n\n");
+        assert_eq!(forwards.len(), 1);
+        assert_eq!(forwards[0].serial, "R58M123456");
+        assert_eq!(forwards[0].rule.adb_args(), ["forward", "tcp:9222", "localabstract:chrome_devtools_remote"]);
+
+        let reverses = parse_reverse_list("R58M123456", "UsbFfs tcp:8081 tcp:3000\n");
+        assert_eq!(reverses[0].rule.remote, "tcp:8081");
+        assert_eq!(reverses[0].rule.local, "tcp:3000");
+        assert_eq!(reverses[0].rule.adb_args(), ["reverse", "tcp:8081", "tcp:3000"]);
+        assert_eq!(reverses[0].rule.describe(), "设备 tcp:8081 → 电脑 tcp:3000");
+    }
+}
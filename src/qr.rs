@@ -0,0 +1,373 @@
+//! 二维码模块
+//! 生成字节模式、纠错等级 L 的二维码（版本 1-5），用于在终端中显示无线调试配对码
+
+/// 各版本（1-5）纠错等级 L 的数据码字数与纠错码字数，均为单个数据块
+const CAPACITY: [(usize, usize); 5] = [(19, 7), (34, 10), (55, 15), (80, 20), (108, 26)];
+
+/// 纠错等级 L 在格式信息中的编码
+const FORMAT_EC_LOW: u32 = 0b01;
+
+/// 二维码矩阵
+#[derive(Debug, Clone)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    /// 定位图形、时序图形、格式信息等不参与数据填充与掩码的模块
+    function: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    /// 编码一段字节数据，自动选择能容纳数据的最小版本
+    pub fn encode(data: &[u8]) -> Result<Self, String> {
+        let (version, (data_len, ec_len)) = CAPACITY
+            .iter()
+            .enumerate()
+            .map(|(i, capacity)| (i + 1, *capacity))
+            // 模式指示符 4 位 + 字符数 8 位
+            .find(|(_, (data_len, _))| data.len() + 2 <= *data_len)
+            .ok_or_else(|| format!("二维码内容过长（{} 字节）", data.len()))?;
+
+        let mut codewords = data_codewords(data, data_len);
+        let ec = reed_solomon_remainder(&codewords, &reed_solomon_divisor(ec_len));
+        codewords.extend(ec);
+
+        let size = version * 4 + 17;
+        let mut qr = Self {
+            size,
+            modules: vec![vec![false; size]; size],
+            function: vec![vec![false; size]; size],
+        };
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&codewords);
+
+        // 选择惩罚分最低的掩码，提高扫描成功率
+        let mut best: Option<(u32, u8)> = None;
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty();
+            if best.is_none_or(|(lowest, _)| penalty < lowest) {
+                best = Some((penalty, mask));
+            }
+            // 掩码是异或操作，再应用一次即可还原
+            qr.apply_mask(mask);
+        }
+        let (_, mask) = best.unwrap_or_default();
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        Ok(qr)
+    }
+
+    /// 指定坐标的模块是否为深色，x 为列，y 为行
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y][x]
+    }
+
+    /// 用半高方块字符渲染，每个字符表示上下两个模块，quiet 为四周留白的模块数
+    ///
+    /// 字符的前景色代表深色模块，显示时应使用深色前景、浅色背景
+    pub fn to_half_blocks(&self, quiet: usize) -> Vec<String> {
+        let total = self.size + quiet * 2;
+        let dark = |x: usize, y: usize| {
+            x >= quiet && y >= quiet && x - quiet < self.size && y - quiet < self.size && self.is_dark(x - quiet, y - quiet)
+        };
+        (0..total)
+            .step_by(2)
+            .map(|y| {
+                (0..total)
+                    .map(|x| match (dark(x, y), dark(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.function[y][x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+        // 版本 2-6 只有右下角一个校正图形
+        if version >= 2 {
+            self.draw_alignment(size - 7, size - 7);
+        }
+        // 先占位格式信息区域，数据填充时跳过
+        self.draw_format_bits(0);
+    }
+
+    /// 定位图形及其分隔符，(cx, cy) 为中心
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if (0..self.size as i32).contains(&x) && (0..self.size as i32).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let (x, y) = ((cx as i32 + dx) as usize, (cy as i32 + dy) as usize);
+                self.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    /// 写入两份格式信息（纠错等级 + 掩码编号，BCH 编码）
+    fn draw_format_bits(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // 固定的深色模块
+        self.set_function(8, size - 8, true);
+    }
+
+    /// 从右下角开始，以两列为一组上下交替填充数据位
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            // 跳过竖直的时序图形
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.function[y][x] && i < codewords.len() * 8 {
+                        self.modules[y][x] = (codewords[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 3 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.function[y][x] {
+                    self.modules[y][x] = !self.modules[y][x];
+                }
+            }
+        }
+    }
+
+    /// 按标准的四条规则计算惩罚分
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines: Vec<Vec<bool>> = (0..size)
+            .map(|y| self.modules[y].clone())
+            .chain((0..size).map(|x| (0..size).map(|y| self.modules[y][x]).collect()))
+            .collect();
+
+        for line in &lines {
+            // 规则 1：同色连续 5 个及以上
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            // 规则 3：类似定位图形的 1:1:3:1:1 图案
+            const PATTERN: [bool; 7] = [true, false, true, true, true, false, true];
+            for i in 0..=size.saturating_sub(7) {
+                if line[i..i + 7] == PATTERN {
+                    let light = |range: std::ops::Range<usize>| range.clone().all(|j| !line[j]);
+                    if (i >= 4 && light(i - 4..i)) || (i + 11 <= size && light(i + 7..i + 11)) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // 规则 2：2x2 同色块
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.modules[y][x];
+                if self.modules[y][x + 1] == color && self.modules[y + 1][x] == color && self.modules[y + 1][x + 1] == color {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // 规则 4：深色模块比例偏离 50%
+        let dark = self.modules.iter().flatten().filter(|m| **m).count();
+        let total = size * size;
+        let k = (dark * 20).abs_diff(total * 10).div_ceil(total).saturating_sub(1);
+        penalty + k as u32 * 10
+    }
+}
+
+/// 字节模式的数据码字：模式指示符、字符数、数据、终止符与填充
+fn data_codewords(data: &[u8], capacity: usize) -> Vec<u8> {
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity * 8);
+    let mut push = |value: u32, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 != 0);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len() as u32, 8);
+    for byte in data {
+        push(*byte as u32, 8);
+    }
+    let terminator = (capacity * 8 - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.resize(bits.len().div_ceil(8) * 8, false);
+
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|chunk| chunk.iter().fold(0u8, |byte, bit| (byte << 1) | *bit as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() >= capacity {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// 格式信息：纠错等级与掩码编号经 BCH(15,5) 编码后再异或固定掩码
+fn format_bits(mask: u8) -> u32 {
+    let data = (FORMAT_EC_LOW << 3) | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+/// GF(256) 乘法，既约多项式 x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+/// Reed-Solomon 生成多项式（最高次项系数省略）
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+/// 计算纠错码字
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(*d, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_error_correction_and_format_bits() {
+        // 常用示例 "HELLO WORLD"（版本 1、纠错等级 M）的数据码字与纠错码字
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+        assert_eq!(format_bits(0), 0b111011111000100);
+        assert_eq!(format_bits(7), 0b110100101110110);
+    }
+
+    #[test]
+    fn fills_every_data_module() {
+        let payload = b"WIFI:T:ADB;S:adb-1a2b;P:123456;;";
+        let qr = QrCode::encode(payload).unwrap();
+        assert_eq!(qr.size, 25);
+
+        // 除功能图形外的模块数 = 码字位数 + 7 个剩余位
+        let free = qr.function.iter().flatten().filter(|f| !**f).count();
+        assert_eq!(free, (34 + 10) * 8 + 7);
+
+        // 三个定位图形的中心均为深色，外圈为浅色分隔符
+        assert!(qr.is_dark(3, 3) && qr.is_dark(21, 3) && qr.is_dark(3, 21));
+        assert!(!qr.is_dark(7, 7));
+
+        let rows = qr.to_half_blocks(2);
+        assert_eq!(rows.len(), 15);
+        assert!(rows.iter().all(|row| row.chars().count() == 29));
+        assert!(QrCode::encode(&[b'x'; 120]).is_err());
+    }
+}
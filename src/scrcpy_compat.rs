@@ -0,0 +1,386 @@
+//! scrcpy 版本兼容性模块
+//! 维护 scrcpy 版本与命令行参数的对照表，避免向旧版本传入它不认识的参数
+
+use std::fmt;
+
+/// scrcpy 版本号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScrcpyVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ScrcpyVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// 解析 `scrcpy --version` 的输出
+    /// 首行形如 "scrcpy 2.4 <https://github.com/Genymobile/scrcpy>"，补丁号可省略
+    pub fn parse(output: &str) -> Option<Self> {
+        let first_line = output.lines().find(|line| !line.trim().is_empty())?;
+        let mut words = first_line.split_whitespace();
+        words.find(|word| word.eq_ignore_ascii_case("scrcpy"))?;
+        let version = words.next()?.trim_start_matches('v');
+
+        let mut numbers = version.split('.').map(|part| {
+            // 容忍 "3.1-dirty" 之类的后缀，只取开头的数字
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u32>().ok()
+        });
+
+        let major = numbers.next()??;
+        let minor = numbers.next().flatten().unwrap_or(0);
+        let patch = numbers.next().flatten().unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for ScrcpyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.patch == 0 {
+            write!(f, "{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+/// 需要较新 scrcpy 才支持的功能
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Audio,
+    /// 只转发音频、不传输视频（--no-video）
+    NoVideo,
+    /// 转发音频的同时在设备上继续播放（--audio-dup）
+    AudioDup,
+    Camera,
+    Gamepad,
+    NewDisplay,
+    /// 投屏开始时打开应用（--start-app），主显示器与虚拟显示器都可以使用
+    StartApp,
+    /// 选择键盘/鼠标的注入方式（--keyboard=sdk|uhid|aoa）
+    InputMode,
+}
+
+impl Feature {
+    /// 首个支持该功能的 scrcpy 版本
+    pub fn min_version(self) -> ScrcpyVersion {
+        match self {
+            Feature::Audio => ScrcpyVersion::new(2, 0, 0),
+            Feature::NoVideo => ScrcpyVersion::new(2, 1, 0),
+            Feature::AudioDup => ScrcpyVersion::new(2, 6, 0),
+            Feature::Camera => ScrcpyVersion::new(2, 2, 0),
+            Feature::Gamepad => ScrcpyVersion::new(2, 7, 0),
+            Feature::InputMode => ScrcpyVersion::new(2, 4, 0),
+            Feature::NewDisplay | Feature::StartApp => ScrcpyVersion::new(3, 0, 0),
+        }
+    }
+
+    /// 设备端需要的最低 Android 版本：(SDK, 系统版本)，与设备无关的功能返回 None
+    pub fn min_android(self) -> Option<(u32, &'static str)> {
+        match self {
+            Feature::Audio | Feature::NoVideo => Some((30, "11")),
+            Feature::AudioDup => Some((33, "13")),
+            Feature::Camera => Some((31, "12")),
+            Feature::NewDisplay => Some((29, "10")),
+            Feature::Gamepad | Feature::InputMode | Feature::StartApp => None,
+        }
+    }
+
+    /// 功能的中文名称，用于日志提示
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Audio => "音频转发",
+            Feature::NoVideo => "仅转发音频",
+            Feature::AudioDup => "音频同时在设备上播放",
+            Feature::Camera => "摄像头镜像",
+            Feature::Gamepad => "游戏手柄",
+            Feature::InputMode => "键盘/鼠标模式选择",
+            Feature::NewDisplay => "虚拟显示器",
+            Feature::StartApp => "启动时打开应用",
+        }
+    }
+
+    /// 判断某个参数属于哪个功能（不属于受限功能时返回 None）
+    pub fn for_arg(arg: &str) -> Option<Self> {
+        let option = arg.split('=').next().unwrap_or(arg);
+        match option {
+            "--no-audio" | "--no-audio-playback" | "--require-audio" => Some(Feature::Audio),
+            "--no-video" => Some(Feature::NoVideo),
+            "--audio-dup" => Some(Feature::AudioDup),
+            o if o.starts_with("--audio-") => Some(Feature::Audio),
+            "--list-cameras" | "--list-camera-sizes" => Some(Feature::Camera),
+            o if o.starts_with("--camera-") => Some(Feature::Camera),
+            // --video-source 与摄像头镜像同时加入，"--video-source camera" 的值在下一个参数里，不能只看等号后面
+            "--video-source" => Some(Feature::Camera),
+            "--gamepad" | "-G" => Some(Feature::Gamepad),
+            "--keyboard" | "--mouse" => Some(Feature::InputMode),
+            "--new-display" | "--no-vd-destroy-content" | "--no-vd-system-decorations" => Some(Feature::NewDisplay),
+            "--start-app" => Some(Feature::StartApp),
+            _ => None,
+        }
+    }
+
+    /// 检查指定版本是否支持该功能
+    pub fn is_supported_by(self, version: ScrcpyVersion) -> bool {
+        version >= self.min_version()
+    }
+}
+
+/// 被过滤掉的参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedArg {
+    pub arg: String,
+    pub feature: Feature,
+}
+
+/// scrcpy 2.0 起重命名的参数：(新名称, 旧名称)
+const RENAMED_IN_2_0: &[(&str, &str)] = &[
+    ("--video-bit-rate", "--bit-rate"),
+    ("--video-codec-options", "--codec-options"),
+    ("--video-encoder", "--encoder"),
+];
+
+/// 按已安装的 scrcpy 版本调整参数列表
+///
+/// 不支持的参数（连同紧随其后的参数值）会被移除并返回，便于调用方记录警告；
+/// 对旧版本改名的参数会自动换回旧名称。版本未知时原样返回，交给 scrcpy 自己报错。
+pub fn filter_args(
+    args: &[String],
+    version: Option<ScrcpyVersion>,
+) -> (Vec<String>, Vec<UnsupportedArg>) {
+    let Some(version) = version else {
+        return (args.to_vec(), Vec::new());
+    };
+
+    let mut kept = Vec::with_capacity(args.len());
+    let mut dropped = Vec::new();
+    let mut iter = args.iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        if let Some(feature) = Feature::for_arg(arg) {
+            if !feature.is_supported_by(version) {
+                let mut full_arg = arg.clone();
+                // "--audio-codec opus" 这类写法需要把参数值一起移除
+                if !arg.contains('=') {
+                    if let Some(value) = iter.next_if(|next| !next.starts_with('-')) {
+                        full_arg = format!("{} {}", arg, value);
+                    }
+                }
+                dropped.push(UnsupportedArg { arg: full_arg, feature });
+                continue;
+            }
+        }
+
+        kept.push(rename_for_version(arg, version));
+    }
+
+    (kept, dropped)
+}
+
+/// 对 2.0 之前的版本使用旧参数名
+fn rename_for_version(arg: &str, version: ScrcpyVersion) -> String {
+    if version >= ScrcpyVersion::new(2, 0, 0) {
+        return arg.to_string();
+    }
+
+    let (option, value) = match arg.split_once('=') {
+        Some((option, value)) => (option, Some(value)),
+        None => (arg, None),
+    };
+
+    match RENAMED_IN_2_0.iter().find(|(new, _)| *new == option) {
+        Some((_, old)) => match value {
+            Some(value) => format!("{}={}", old, value),
+            None => old.to_string(),
+        },
+        None => arg.to_string(),
+    }
+}
+
+/// 从 scrcpy 的 stderr 中找出被拒绝的参数名
+///
+/// 识别 getopt 风格的报错，例如 "scrcpy: unrecognized option '--foo'"、
+/// "option '--max-size' requires an argument" 以及 "invalid option -- 'x'"
+pub fn rejected_option_from_stderr(lines: &[String]) -> Option<String> {
+    lines.iter().find_map(|line| {
+        let lower = line.to_ascii_lowercase();
+        if lower.contains("unrecognized option")
+            || lower.contains("requires an argument")
+            || lower.contains("doesn't allow an argument")
+        {
+            let option = quoted(line)?;
+            // 去掉 "--foo=bar" 中的参数值
+            Some(option.split('=').next().unwrap_or(option).to_string())
+        } else if lower.contains("invalid option --") {
+            quoted(line).map(|short| format!("-{}", short))
+        } else {
+            None
+        }
+    })
+}
+
+/// 取出第一对引号（'...' 或 `...'）之间的内容
+fn quoted(line: &str) -> Option<&str> {
+    let start = line.find(['\'', '`'])? + 1;
+    let len = line[start..].find('\'')?;
+    Some(&line[start..start + len])
+}
+
+/// 由启动器自己管理、不允许在配置中指定的参数
+const RESERVED_OPTIONS: &[&str] = &["-s", "--serial", "--print-fps"];
+
+/// 检查配置中的自定义参数：不能为空、必须以选项开头，且不能包含由启动器管理的参数
+pub fn validate_user_args(args: &[String]) -> Result<(), String> {
+    if let Some(first) = args.first().filter(|a| !a.starts_with('-')) {
+        return Err(format!("参数 {} 前缺少选项名", first));
+    }
+    for arg in args {
+        if arg.trim().is_empty() {
+            return Err("存在空参数".to_string());
+        }
+        let option = arg.split('=').next().unwrap_or(arg);
+        if RESERVED_OPTIONS.contains(&option) {
+            return Err(format!("{} 由启动器自动设置，不能在配置中指定", option));
+        }
+    }
+    Ok(())
+}
+
+/// 从参数列表中移除指定参数（连同 "--opt value" 形式的参数值）
+pub fn remove_option(args: &[String], option: &str) -> Vec<String> {
+    let mut kept = Vec::with_capacity(args.len());
+    let mut iter = args.iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        let name = arg.split('=').next().unwrap_or(arg);
+        if name == option {
+            if !arg.contains('=') {
+                iter.next_if(|next| !next.starts_with('-'));
+            }
+            continue;
+        }
+        kept.push(arg.clone());
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_version_output() {
+        let output = "scrcpy 2.4 <https://github.com/Genymobile/scrcpy>\n\nDependencies (compiled / linked):\n";
+        assert_eq!(ScrcpyVersion::parse(output), Some(ScrcpyVersion::new(2, 4, 0)));
+        assert_eq!(ScrcpyVersion::parse("scrcpy v1.25.1"), Some(ScrcpyVersion::new(1, 25, 1)));
+        assert_eq!(ScrcpyVersion::parse("scrcpy 3.1-dirty"), Some(ScrcpyVersion::new(3, 1, 0)));
+        assert_eq!(ScrcpyVersion::parse("adb: not found"), None);
+    }
+
+    #[test]
+    fn drops_unsupported_flags_with_values() {
+        let (kept, dropped) = filter_args(
+            &args(&["--max-size", "1024", "--audio-codec", "opus", "--new-display=1920x1080"]),
+            Some(ScrcpyVersion::new(2, 4, 0)),
+        );
+        assert_eq!(kept, args(&["--max-size", "1024", "--audio-codec", "opus"]));
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].feature, Feature::NewDisplay);
+
+        let (kept, dropped) = filter_args(
+            &args(&["--audio-codec", "opus", "--no-audio"]),
+            Some(ScrcpyVersion::new(1, 25, 0)),
+        );
+        assert!(kept.is_empty());
+        assert_eq!(dropped[0].arg, "--audio-codec opus");
+        assert_eq!(dropped[1].arg, "--no-audio");
+
+        let (_, dropped) = filter_args(&args(&["--keyboard=sdk"]), Some(ScrcpyVersion::new(2, 3, 0)));
+        assert_eq!(dropped[0].feature, Feature::InputMode);
+
+        let (_, dropped) = filter_args(&args(&["--start-app=org.mozilla.firefox"]), Some(ScrcpyVersion::new(2, 7, 0)));
+        assert_eq!(dropped[0].feature, Feature::StartApp);
+
+        let (kept, dropped) = filter_args(
+            &args(&["--video-source", "camera", "--camera-facing=back"]),
+            Some(ScrcpyVersion::new(2, 1, 0)),
+        );
+        assert!(kept.is_empty());
+        assert_eq!(dropped[0].arg, "--video-source camera");
+        assert_eq!(dropped[1].feature, Feature::Camera);
+    }
+
+    #[test]
+    fn gates_audio_options_by_their_own_version() {
+        let (kept, dropped) = filter_args(
+            &args(&["--audio-codec=opus", "--audio-dup", "--no-video"]),
+            Some(ScrcpyVersion::new(2, 0, 0)),
+        );
+        assert_eq!(kept, args(&["--audio-codec=opus"]));
+        assert_eq!(dropped.iter().map(|d| d.feature).collect::<Vec<_>>(), [Feature::AudioDup, Feature::NoVideo]);
+
+        let (kept, dropped) = filter_args(&args(&["--audio-dup", "--no-video"]), Some(ScrcpyVersion::new(2, 6, 0)));
+        assert_eq!(kept.len(), 2);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn renames_options_for_old_versions() {
+        let (kept, _) = filter_args(
+            &args(&["--video-bit-rate=4M", "--video-encoder", "OMX.google.h264.encoder"]),
+            Some(ScrcpyVersion::new(1, 24, 0)),
+        );
+        assert_eq!(kept, args(&["--bit-rate=4M", "--encoder", "OMX.google.h264.encoder"]));
+    }
+
+    #[test]
+    fn unknown_version_keeps_everything() {
+        let input = args(&["--new-display", "--gamepad=uhid"]);
+        let (kept, dropped) = filter_args(&input, None);
+        assert_eq!(kept, input);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn finds_rejected_option_in_stderr() {
+        let stderr = args(&[
+            "scrcpy 1.25 <https://github.com/Genymobile/scrcpy>",
+            "scrcpy.exe: unrecognized option '--video-bit-rate=8M'",
+        ]);
+        assert_eq!(rejected_option_from_stderr(&stderr).as_deref(), Some("--video-bit-rate"));
+
+        let stderr = args(&["scrcpy: invalid option -- 'G'"]);
+        assert_eq!(rejected_option_from_stderr(&stderr).as_deref(), Some("-G"));
+
+        let stderr = args(&["ERROR: Could not find any ADB device"]);
+        assert_eq!(rejected_option_from_stderr(&stderr), None);
+    }
+
+    #[test]
+    fn validates_user_args() {
+        assert!(validate_user_args(&args(&["--max-size", "1280", "--video-bit-rate=4M"])).is_ok());
+        assert!(validate_user_args(&[]).is_ok());
+        assert!(validate_user_args(&args(&["1280", "--max-size"])).is_err());
+        assert!(validate_user_args(&args(&["--max-size", ""])).is_err());
+        assert!(validate_user_args(&args(&["--serial=R58M123456"])).is_err());
+        assert!(validate_user_args(&args(&["--print-fps"])).is_err());
+    }
+
+    #[test]
+    fn removes_option_and_its_value() {
+        let input = args(&["--max-size", "1024", "--audio-codec", "opus", "--video-bit-rate=8M"]);
+        assert_eq!(
+            remove_option(&input, "--audio-codec"),
+            args(&["--max-size", "1024", "--video-bit-rate=8M"])
+        );
+        assert_eq!(remove_option(&input, "--video-bit-rate"), args(&["--max-size", "1024", "--audio-codec", "opus"]));
+    }
+}
@@ -0,0 +1,290 @@
+//! scrcpy 设置导入模块
+//! 从用户自己的 scrcpy 启动脚本（.bat / .cmd）、快捷方式（.lnk）与 SCRCPY_ 环境变量中收集参数，
+//! 供 --import-scrcpy 导入到 config.toml，方便长期使用 scrcpy 命令行的用户迁移
+
+use std::path::{Path, PathBuf};
+
+/// 启动器自己管理、导入时需要去掉的参数（带值的参数连同其值一起去掉）
+const MANAGED_OPTIONS: &[&str] = &["-s", "--serial", "--print-fps", "--pause-on-exit", "--tcpip"];
+
+/// 一处找到的 scrcpy 参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportSource {
+    /// 脚本或快捷方式的路径
+    pub path: PathBuf,
+    /// 去掉启动器管理的参数之后剩下的参数
+    pub args: Vec<String>,
+}
+
+/// 快捷方式中的目标程序与参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shortcut {
+    target: String,
+    arguments: String,
+}
+
+/// 在 scrcpy 目录、桌面与开始菜单中查找带参数启动 scrcpy 的脚本和快捷方式
+pub fn find_sources(scrcpy_dir: &Path) -> Vec<ImportSource> {
+    let mut dirs = vec![scrcpy_dir.to_path_buf()];
+    dirs.extend(dirs::desktop_dir());
+    if let Some(data_dir) = dirs::data_dir() {
+        let programs = data_dir.join("Microsoft").join("Windows").join("Start Menu").join("Programs");
+        // 安装程序通常把快捷方式放在以程序命名的子目录中
+        dirs.push(programs.join("scrcpy"));
+        dirs.push(programs);
+    }
+
+    let mut sources = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+            let args = match extension.as_str() {
+                "bat" | "cmd" => std::fs::read_to_string(&path).ok().and_then(|content| script_args(&content)),
+                "lnk" => std::fs::read(&path).ok().and_then(|data| parse_lnk(&data)).and_then(|shortcut| {
+                    is_scrcpy(&shortcut.target).then(|| split_command_line(&shortcut.arguments))
+                }),
+                _ => None,
+            };
+            let Some(args) = args.map(|args| strip_managed(&args)).filter(|args| !args.is_empty()) else {
+                continue;
+            };
+            if !sources.iter().any(|s: &ImportSource| s.path == path) {
+                sources.push(ImportSource { path, args });
+            }
+        }
+    }
+    sources
+}
+
+/// 当前进程环境中以 SCRCPY_ 开头的变量（如 SCRCPY_SERVER_PATH），按名称排序
+pub fn scrcpy_env_vars() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars().filter(|(name, _)| name.starts_with("SCRCPY_")).collect();
+    vars.sort();
+    vars
+}
+
+/// 合并多处找到的参数，同一选项只保留首次出现的那一处（连同其值），保持首次出现的顺序；
+/// 长选项的值并入同一项（"--max-size 1280" 写为 "--max-size=1280"），配置中一项就是一个完整选项
+pub fn merge_args(sources: &[ImportSource]) -> Vec<String> {
+    let mut merged: Vec<Vec<String>> = Vec::new();
+    for group in sources.iter().flat_map(|s| group_options(&s.args)) {
+        if !merged.iter().any(|existing| option_name(&existing[0]) == option_name(&group[0])) {
+            merged.push(join_long_value(group));
+        }
+    }
+    merged.into_iter().flatten().collect()
+}
+
+/// 把参数按选项分组：选项与紧随其后的值（不以 - 开头的参数）为一组，scrcpy 没有位置参数
+pub fn group_options(args: &[String]) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for arg in args {
+        match groups.last_mut() {
+            Some(group) if !arg.starts_with('-') => group.push(arg.clone()),
+            _ => groups.push(vec![arg.clone()]),
+        }
+    }
+    groups
+}
+
+/// 选项名，"--max-size=1280" 为 "--max-size"
+pub fn option_name(arg: &str) -> &str {
+    arg.split('=').next().unwrap_or(arg)
+}
+
+/// 长选项与其单独成项的值合并为 "--option=value"，短选项（-m 1280）保持原样
+fn join_long_value(group: Vec<String>) -> Vec<String> {
+    match group.as_slice() {
+        [option, value] if option.starts_with("--") && !option.contains('=') => vec![format!("{}={}", option, value)],
+        _ => group,
+    }
+}
+
+/// 目标程序是否为 scrcpy（scrcpy.exe 或随附的 scrcpy-noconsole.vbs 等）
+fn is_scrcpy(target: &str) -> bool {
+    let name = target.rsplit(['\\', '/']).next().unwrap_or(target).to_ascii_lowercase();
+    name == "scrcpy" || name.starts_with("scrcpy.") || name.starts_with("scrcpy-")
+}
+
+/// 取出脚本中调用 scrcpy 的那一行的参数，不调用 scrcpy 的脚本返回 None
+fn script_args(content: &str) -> Option<Vec<String>> {
+    content.lines().find_map(|line| {
+        let line = line.trim().trim_start_matches('@');
+        let lower = line.to_ascii_lowercase();
+        if lower.starts_with("rem ") || lower.starts_with("::") || lower.starts_with("echo") {
+            return None;
+        }
+        let mut tokens = split_command_line(line);
+        // 允许 "start "" scrcpy.exe ..." 之类的写法
+        let position = tokens.iter().position(|token| is_scrcpy(token) && !token.to_ascii_lowercase().ends_with(".bat"))?;
+        Some(tokens.split_off(position + 1))
+    })
+}
+
+/// 去掉启动器管理的参数与脚本的参数占位符（%*、%1 等）
+fn strip_managed(args: &[String]) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut skip_value = false;
+    for arg in args {
+        if std::mem::take(&mut skip_value) && !arg.starts_with('-') {
+            continue;
+        }
+        if arg.starts_with('%') {
+            continue;
+        }
+        let name = option_name(arg);
+        if MANAGED_OPTIONS.contains(&name) {
+            // -s SERIAL、--serial SERIAL 的值单独成项
+            skip_value = !arg.contains('=') && matches!(name, "-s" | "--serial");
+            continue;
+        }
+        result.push(arg.clone());
+    }
+    result
+}
+
+/// 按 Windows 命令行规则拆分参数：空白分隔，双引号内的空白不分隔
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut has_token = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if std::mem::take(&mut has_token) {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    args
+}
+
+/// 解析 Windows 快捷方式（MS-SHLLINK 格式），取出目标路径与参数
+fn parse_lnk(data: &[u8]) -> Option<Shortcut> {
+    const HAS_ID_LIST: usize = 0x1;
+    const HAS_LINK_INFO: usize = 0x2;
+    const HAS_NAME: usize = 0x4;
+    const HAS_RELATIVE_PATH: usize = 0x8;
+    const HAS_WORKING_DIR: usize = 0x10;
+    const HAS_ARGUMENTS: usize = 0x20;
+    const IS_UNICODE: usize = 0x80;
+
+    let u16_at = |pos: usize| data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let u32_at = |pos: usize| data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+
+    if u32_at(0)? != 0x4C {
+        return None;
+    }
+    let flags = u32_at(0x14)?;
+    let mut pos = 0x4C;
+    if flags & HAS_ID_LIST != 0 {
+        pos += 2 + u16_at(pos)?;
+    }
+
+    let mut target = String::new();
+    if flags & HAS_LINK_INFO != 0 {
+        let size = u32_at(pos)?;
+        // LinkInfo 中的 LocalBasePath 为以 0 结尾的 ANSI 字符串
+        let base_path = u32_at(pos + 16)?;
+        if base_path != 0 {
+            let bytes = data.get(pos + base_path..pos + size)?;
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            target = String::from_utf8_lossy(&bytes[..end]).into_owned();
+        }
+        pos += size;
+    }
+
+    let unicode = flags & IS_UNICODE != 0;
+    let mut arguments = String::new();
+    for flag in [HAS_NAME, HAS_RELATIVE_PATH, HAS_WORKING_DIR, HAS_ARGUMENTS] {
+        if flags & flag == 0 {
+            continue;
+        }
+        let count = u16_at(pos)?;
+        let len = if unicode { count * 2 } else { count };
+        let bytes = data.get(pos + 2..pos + 2 + len)?;
+        pos += 2 + len;
+        let text = if unicode {
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        match flag {
+            HAS_RELATIVE_PATH if target.is_empty() => target = text,
+            HAS_ARGUMENTS => arguments = text,
+            _ => {}
+        }
+    }
+    Some(Shortcut { target, arguments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn extracts_script_arguments() {
+        let script = "@echo off\r\nrem 工作机\r\n\"C:\\Tools\\scrcpy\\scrcpy.exe\" -s R58M123456 --max-size 1280 --window-title \"工作 机\" %*\r\n";
+        let found = strip_managed(&script_args(script).unwrap());
+        assert_eq!(found, args(&["--max-size", "1280", "--window-title", "工作 机"]));
+
+        // scrcpy 自带的 scrcpy-console.bat 只有启动器管理的参数
+        let console = "@echo off\r\nscrcpy.exe --pause-on-exit=if-error %*\r\n";
+        assert!(strip_managed(&script_args(console).unwrap()).is_empty());
+        assert_eq!(script_args("@echo off\r\nadb devices\r\n"), None);
+
+        let sources = [
+            ImportSource { path: PathBuf::from("a.bat"), args: args(&["--turn-screen-off", "--max-fps=60"]) },
+            ImportSource { path: PathBuf::from("b.lnk"), args: args(&["--max-fps=60", "--stay-awake"]) },
+        ];
+        assert_eq!(merge_args(&sources), args(&["--turn-screen-off", "--max-fps=60", "--stay-awake"]));
+
+        // 同一选项在不同来源中取值不同时只保留第一处，值不会被单独留下
+        let sources = [
+            ImportSource { path: PathBuf::from("a.bat"), args: args(&["--max-size", "1280", "-b", "8M"]) },
+            ImportSource { path: PathBuf::from("b.lnk"), args: args(&["--max-size", "1024", "--max-size=800", "-b", "4M", "--no-audio"]) },
+        ];
+        assert_eq!(merge_args(&sources), args(&["--max-size=1280", "-b", "8M", "--no-audio"]));
+    }
+
+    #[test]
+    fn parses_shortcut_files() {
+        let mut data = vec![0u8; 0x4C];
+        data[0] = 0x4C;
+        // HasRelativePath | HasArguments | IsUnicode
+        data[0x14] = 0x08 | 0x20 | 0x80;
+        for text in ["..\\scrcpy\\scrcpy.exe", "--video-bit-rate=8M --no-audio"] {
+            let units: Vec<u16> = text.encode_utf16().collect();
+            data.extend((units.len() as u16).to_le_bytes());
+            data.extend(units.iter().flat_map(|u| u.to_le_bytes()));
+        }
+
+        let shortcut = parse_lnk(&data).unwrap();
+        assert!(is_scrcpy(&shortcut.target));
+        assert_eq!(split_command_line(&shortcut.arguments), args(&["--video-bit-rate=8M", "--no-audio"]));
+        assert_eq!(parse_lnk(&data[..0x50]), None);
+        assert_eq!(parse_lnk(b"not a shortcut"), None);
+        assert!(!is_scrcpy("C:\\Windows\\notepad.exe"));
+    }
+}
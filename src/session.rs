@@ -0,0 +1,513 @@
+//! 会话模块
+//! 定义 scrcpy 会话类型，集中管理各类型的启动参数、状态图标与生命周期规则
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::migration::{add_version, Schema};
+
+/// scrcpy 会话类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    /// 屏幕镜像（默认）
+    #[default]
+    Mirror,
+    /// 镜像的同时录制屏幕
+    Record,
+    /// 只转发音频
+    AudioOnly,
+    /// OTG 模式，仅用作键鼠，不镜像画面
+    OtgControl,
+    /// 镜像设备摄像头
+    Camera,
+    /// 在新建的虚拟显示器上运行应用，不影响设备的物理屏幕
+    VirtualDisplay,
+}
+
+impl SessionKind {
+    /// 界面中切换会话类型的顺序
+    const ALL: [SessionKind; 6] = [
+        SessionKind::Mirror,
+        SessionKind::Record,
+        SessionKind::AudioOnly,
+        SessionKind::OtgControl,
+        SessionKind::Camera,
+        SessionKind::VirtualDisplay,
+    ];
+
+    /// 下一个会话类型，最后一个之后回到镜像
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// 该类型会话需要的 scrcpy 参数，record_to 为录屏会话的录像文件
+    pub fn args(self, record_to: &Path) -> Vec<String> {
+        match self {
+            SessionKind::Mirror => Vec::new(),
+            SessionKind::Record => vec![format!("--record={}", record_to.display())],
+            SessionKind::AudioOnly => vec!["--no-video".to_string()],
+            SessionKind::OtgControl => vec!["--otg".to_string()],
+            SessionKind::Camera => vec!["--video-source=camera".to_string()],
+            // 分辨率与启动的应用可按设备配置，参数由 VirtualDisplayConfig 生成
+            SessionKind::VirtualDisplay => Vec::new(),
+        }
+    }
+
+    /// 状态图标
+    pub fn icon(self) -> &'static str {
+        match self {
+            SessionKind::Mirror => "🪞",
+            SessionKind::Record => "⏺️",
+            SessionKind::AudioOnly => "🔊",
+            SessionKind::OtgControl => "⌨️",
+            SessionKind::Camera => "📷",
+            SessionKind::VirtualDisplay => "🖼️",
+        }
+    }
+
+    /// 中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionKind::Mirror => "镜像",
+            SessionKind::Record => "录屏",
+            SessionKind::AudioOnly => "仅音频",
+            SessionKind::OtgControl => "OTG控制",
+            SessionKind::Camera => "摄像头",
+            SessionKind::VirtualDisplay => "虚拟显示器",
+        }
+    }
+
+    /// 会话是否传输视频画面（决定帧率统计等视频相关参数是否有意义）
+    pub fn has_video(self) -> bool {
+        !matches!(self, SessionKind::AudioOnly | SessionKind::OtgControl)
+    }
+
+    /// 会话是否可以转发音频
+    pub fn has_audio(self) -> bool {
+        !matches!(self, SessionKind::OtgControl)
+    }
+
+    /// scrcpy 退出后是否自动重启
+    /// 录屏结束通常是用户主动关闭窗口，自动重启会意外开始一段新录像
+    pub fn restart_on_exit(self) -> bool {
+        !matches!(self, SessionKind::Record)
+    }
+
+    /// 是否支持无线连接的设备；OTG 模式直接通过 USB 模拟键鼠，只能用于 USB 设备
+    pub fn supports_tcp(self) -> bool {
+        !matches!(self, SessionKind::OtgControl)
+    }
+}
+
+/// session.toml 的格式版本与迁移步骤
+const SCHEMA: Schema = Schema { name: "会话记录", migrations: &[add_version] };
+
+/// 启动器退出时仍在进行的会话，保存在配置目录的 session.toml，下次启动时据此恢复
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub serial: String,
+    pub kind: SessionKind,
+    /// 使用的启动配置档
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub show_fps: bool,
+    /// 录屏会话写入的录像文件
+    #[serde(default)]
+    pub recording: Option<String>,
+    /// 启动器启动的 scrcpy 进程，启动器异常退出后据此识别遗留的进程
+    #[serde(default)]
+    pub pid: Option<u32>,
+}
+
+impl SavedSession {
+    /// 会话文件路径，与 config.toml 位于同一目录
+    pub fn path() -> PathBuf {
+        Config::path().with_file_name("session.toml")
+    }
+
+    /// 读取上次的会话，文件不存在时返回 None
+    pub fn load() -> Result<Option<Self>, String> {
+        Self::load_from(&Self::path())
+    }
+
+    /// 记录当前会话
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(&Self::path())
+    }
+
+    /// 会话正常结束后删除记录，下次启动不再恢复
+    pub fn clear() -> Result<(), String> {
+        Self::clear_at(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Result<Option<Self>, String> {
+        match SCHEMA.load(path)? {
+            Some(loaded) => toml::from_str(&loaded.content).map(Some).map_err(|e| format!("会话记录格式错误: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|e| format!("序列化会话记录失败: {}", e))?;
+        let content = SCHEMA.stamp(&content);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        }
+        std::fs::write(path, content).map_err(|e| format!("保存会话记录失败: {}", e))
+    }
+
+    fn clear_at(path: &Path) -> Result<(), String> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("删除会话记录失败: {}", e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 从启动参数中取出录像文件路径
+pub fn recording_path(args: &[String]) -> Option<String> {
+    args.iter()
+        .find_map(|arg| arg.strip_prefix("--record="))
+        .map(String::from)
+}
+
+/// 录屏设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// 录像保存目录，不填时保存在启动器的工作目录
+    pub directory: Option<PathBuf>,
+    /// 录像文件名模板，可用 {device}（昵称或型号）、{serial}（序列号）、{date}（年月日-时分秒）
+    pub template: String,
+    /// 录像目录所在磁盘至少保留的空间（MB），低于此值时不开始录屏，录屏中途低于此值时结束录制；0 表示不检查
+    pub min_free_mb: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            template: "{device}-{date}.mp4".to_string(),
+            min_free_mb: 1024,
+        }
+    }
+}
+
+impl RecordingConfig {
+    /// 至少保留的磁盘空间（字节），不检查时返回 None
+    pub fn min_free_bytes(&self) -> Option<u64> {
+        (self.min_free_mb > 0).then(|| self.min_free_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// 按模板生成录像文件路径
+    pub fn file_path(&self, device: &str, serial: &str) -> PathBuf {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.file_path_at(device, serial, secs)
+    }
+
+    fn file_path_at(&self, device: &str, serial: &str, secs: u64) -> PathBuf {
+        let name = self.template
+            .replace("{device}", &sanitize_file_name(device))
+            .replace("{serial}", &sanitize_file_name(serial))
+            .replace("{date}", &format_date(secs));
+        match &self.directory {
+            Some(directory) => directory.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+}
+
+/// 音频转发设置（scrcpy 2.0 起支持，旧版本会自动去掉音频参数）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// 是否转发音频，也可在界面中按 m 切换
+    pub enabled: bool,
+    /// 音频编码：opus / aac / flac / raw，不填时由 scrcpy 决定
+    pub codec: Option<String>,
+    /// 音频来源：output（设备播放的声音）/ mic（麦克风）等，不填时由 scrcpy 决定
+    pub source: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { enabled: true, codec: None, source: None }
+    }
+}
+
+impl AudioConfig {
+    /// 音频相关的 scrcpy 参数，enabled 为当前是否转发音频（仅音频会话始终转发）
+    pub fn args(&self, kind: SessionKind, enabled: bool) -> Vec<String> {
+        if !kind.has_audio() {
+            return Vec::new();
+        }
+        if !enabled && kind != SessionKind::AudioOnly {
+            return vec!["--no-audio".to_string()];
+        }
+        let mut args = Vec::new();
+        if let Some(codec) = &self.codec {
+            args.push(format!("--audio-codec={}", codec));
+        }
+        if let Some(source) = &self.source {
+            args.push(format!("--audio-source={}", source));
+        }
+        args
+    }
+}
+
+/// 虚拟显示器会话的设置（scrcpy 3.0+，设备需要 Android 10+）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VirtualDisplayConfig {
+    /// 分辨率与密度，如 "1920x1080" 或 "1920x1080/240"，不填时与设备主屏相同
+    pub size: Option<String>,
+    /// 在虚拟显示器上启动的应用包名，如 "org.mozilla.firefox"
+    pub app: Option<String>,
+}
+
+impl VirtualDisplayConfig {
+    /// 创建虚拟显示器并启动应用的 scrcpy 参数
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![match &self.size {
+            Some(size) => format!("--new-display={}", size),
+            None => "--new-display".to_string(),
+        }];
+        if let Some(app) = &self.app {
+            args.push(format!("--start-app={}", app));
+        }
+        args
+    }
+}
+
+/// 演示模式：显示触摸点、保持唤醒、窗口置顶并使用固定大小的窗口，适合演示与会议共享屏幕
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresentationConfig {
+    /// 窗口宽度，不填时按画面比例由高度决定
+    pub window_width: Option<u32>,
+    /// 窗口高度
+    pub window_height: Option<u32>,
+}
+
+impl Default for PresentationConfig {
+    fn default() -> Self {
+        Self { window_width: None, window_height: Some(960) }
+    }
+}
+
+impl PresentationConfig {
+    /// 演示模式的 scrcpy 参数，existing 中已有的开关不重复添加
+    pub fn args(&self, existing: &[String]) -> Vec<String> {
+        let mut args: Vec<String> = ["--show-touches", "--stay-awake", "--always-on-top"]
+            .into_iter()
+            .filter(|flag| !existing.iter().any(|arg| arg == flag))
+            .map(str::to_string)
+            .collect();
+        if let Some(width) = self.window_width {
+            args.push(format!("--window-width={}", width));
+        }
+        if let Some(height) = self.window_height {
+            args.push(format!("--window-height={}", height));
+        }
+        args
+    }
+}
+
+/// 最多保留的 scrcpy 会话日志数量，超出时删除最旧的
+const SESSION_LOG_LIMIT: usize = 30;
+
+/// scrcpy 会话日志目录：配置目录下的 logs
+pub fn session_log_dir() -> PathBuf {
+    Config::path().with_file_name("logs")
+}
+
+/// 为设备的新会话创建日志文件（<序列号>-<日期>.log），并删除超出数量的旧日志
+pub fn create_session_log(dir: &Path, serial: &str) -> Result<(PathBuf, File), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+    prune_session_logs(dir, SESSION_LOG_LIMIT - 1);
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("{}-{}.log", sanitize_file_name(serial), format_date(secs)));
+    // 同一秒内重启的会话写入同一个文件
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("创建会话日志失败 {}: {}", path.display(), e))?;
+    Ok((path, file))
+}
+
+/// 只保留最近修改的 keep 个日志文件
+fn prune_session_logs(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut logs: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .map(|path| (std::fs::metadata(&path).and_then(|m| m.modified()).unwrap_or(UNIX_EPOCH), path))
+        .collect();
+    logs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in logs.into_iter().skip(keep) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// 替换 Windows 文件名中不允许的字符（序列号中的 ':'、型号中的 '/' 等）
+fn sanitize_file_name(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() || r#"<>:"/\|?* "#.contains(c) { '_' } else { c })
+        .collect()
+}
+
+/// 把 Unix 时间格式化为 20240102-030405（UTC+8，与界面时间一致）
+pub fn format_date(secs: u64) -> String {
+    let secs = secs + 8 * 3600;
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// 1970-01-01 起的天数转换为公历日期
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_kind_specific_args() {
+        let file = Path::new("screen.mp4");
+        assert!(SessionKind::Mirror.args(file).is_empty());
+        assert_eq!(SessionKind::OtgControl.args(file), vec!["--otg"]);
+        assert_eq!(SessionKind::Record.args(file), vec!["--record=screen.mp4"]);
+    }
+
+    #[test]
+    fn builds_audio_args() {
+        let audio = AudioConfig { codec: Some("aac".to_string()), source: Some("mic".to_string()), ..AudioConfig::default() };
+        assert_eq!(audio.args(SessionKind::Mirror, true), ["--audio-codec=aac", "--audio-source=mic"]);
+        assert_eq!(audio.args(SessionKind::Mirror, false), ["--no-audio"]);
+        // 仅音频会话关闭音频没有意义，OTG 会话没有音频
+        assert_eq!(audio.args(SessionKind::AudioOnly, false).len(), 2);
+        assert!(audio.args(SessionKind::OtgControl, true).is_empty());
+        assert!(AudioConfig::default().args(SessionKind::Camera, true).is_empty());
+    }
+
+    #[test]
+    fn builds_virtual_display_args() {
+        assert_eq!(VirtualDisplayConfig::default().args(), ["--new-display"]);
+        let config = VirtualDisplayConfig { size: Some("1920x1080/240".to_string()), app: Some("org.mozilla.firefox".to_string()) };
+        assert_eq!(config.args(), ["--new-display=1920x1080/240", "--start-app=org.mozilla.firefox"]);
+    }
+
+    #[test]
+    fn builds_presentation_args() {
+        let existing = vec!["--stay-awake".to_string()];
+        assert_eq!(
+            PresentationConfig::default().args(&existing),
+            ["--show-touches", "--always-on-top", "--window-height=960"]
+        );
+        let config = PresentationConfig { window_width: Some(1280), window_height: Some(720) };
+        assert_eq!(config.args(&[]).len(), 5);
+    }
+
+    #[test]
+    fn builds_recording_path_from_template() {
+        let recording = RecordingConfig::default();
+        // 2024-02-29 15:04:05 UTC+8
+        assert_eq!(
+            recording.file_path_at("Pixel 7 (工作机)", "192.168.1.5:5555", 1_709_190_245),
+            PathBuf::from("Pixel_7_(工作机)-20240229-150405.mp4")
+        );
+
+        let recording = RecordingConfig {
+            directory: Some(PathBuf::from("recordings")),
+            template: "{serial}_{date}.mkv".to_string(),
+            min_free_mb: 0,
+        };
+        assert_eq!(
+            recording.file_path_at("", "192.168.1.5:5555", 0),
+            Path::new("recordings").join("192.168.1.5_5555_19700101-080000.mkv")
+        );
+    }
+
+    #[test]
+    fn saves_and_clears_last_session() {
+        let path = std::env::temp_dir().join(format!("scrcpy-launcher-session-{}.toml", std::process::id()));
+        let args = SessionKind::Record.args(Path::new("R58M123456-20240229-150405.mp4"));
+        let session = SavedSession {
+            serial: "R58M123456".to_string(),
+            kind: SessionKind::Record,
+            profile: Some("低延迟".to_string()),
+            show_fps: true,
+            recording: recording_path(&args),
+            pid: Some(4321),
+        };
+        assert!(session.recording.as_deref().is_some_and(|r| r.ends_with(".mp4")));
+
+        session.save_to(&path).unwrap();
+        assert_eq!(SavedSession::load_from(&path).unwrap(), Some(session));
+        SavedSession::clear_at(&path).unwrap();
+        assert_eq!(SavedSession::load_from(&path).unwrap(), None);
+        assert!(SavedSession::clear_at(&path).is_ok());
+    }
+
+    #[test]
+    fn creates_and_prunes_session_logs() {
+        let dir = std::env::temp_dir().join(format!("scrcpy-launcher-logs-{}", std::process::id()));
+        let (path, _) = create_session_log(&dir, "192.168.1.5:5555").unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("192.168.1.5_5555-") && name.ends_with(".log"));
+
+        for i in 0..3 {
+            std::fs::write(dir.join(format!("old-{}.log", i)), "").unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        prune_session_logs(&dir, 2);
+        let remaining = std::fs::read_dir(&dir).unwrap().count();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(remaining, 3);
+    }
+
+    #[test]
+    fn lifecycle_rules() {
+        assert!(SessionKind::Mirror.restart_on_exit());
+        assert!(!SessionKind::Record.restart_on_exit());
+        assert!(!SessionKind::OtgControl.supports_tcp());
+        assert!(!SessionKind::AudioOnly.has_video());
+        assert_eq!(SessionKind::AudioOnly.next(), SessionKind::OtgControl);
+        assert_eq!(SessionKind::VirtualDisplay.next(), SessionKind::Mirror);
+    }
+}
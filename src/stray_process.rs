@@ -0,0 +1,165 @@
+//! 遗留 scrcpy 进程模块
+//! 启动器异常退出后，它打开的 scrcpy 窗口会继续运行。启动时根据会话记录中的 PID 找出这些父进程已不存在的 scrcpy，
+//! 由用户选择结束它们或继续沿用，避免再打开一个重复的窗口
+
+use crate::device_monitor::{request_exit, STOP_TIMEOUT};
+
+/// 进程快照中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcessEntry {
+    pid: u32,
+    parent: u32,
+    name: String,
+}
+
+/// 查找启动器先前启动、父进程已退出的 scrcpy 进程，launched 为会话记录中的 PID
+pub fn find_stray_scrcpy(launched: &[u32]) -> Vec<u32> {
+    if launched.is_empty() {
+        return Vec::new();
+    }
+    orphaned_scrcpy(&processes(), launched)
+}
+
+/// 返回仍在运行的 scrcpy 进程，PID 被其他程序复用的不算
+pub fn still_running(pids: &[u32]) -> Vec<u32> {
+    processes()
+        .into_iter()
+        .filter(|process| pids.contains(&process.pid) && is_scrcpy(&process.name))
+        .map(|process| process.pid)
+        .collect()
+}
+
+/// 结束遗留的 scrcpy：先请求正常退出（录像才能写完文件尾），超时仍未退出再强制结束；会阻塞最多 STOP_TIMEOUT
+pub fn terminate(pid: u32) {
+    if request_exit(pid) {
+        let deadline = std::time::Instant::now() + STOP_TIMEOUT;
+        while std::time::Instant::now() < deadline {
+            if still_running(&[pid]).is_empty() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    }
+    force_kill(pid);
+}
+
+/// 启动器启动过、父进程已不存在（或已被 init 收养）的 scrcpy 进程
+/// 用户自己打开的 scrcpy（脚本、快捷方式启动后父进程随即退出）不在 launched 中，不算遗留；
+/// PID 被复用时父进程通常仍在运行，也不算
+fn orphaned_scrcpy(processes: &[ProcessEntry], launched: &[u32]) -> Vec<u32> {
+    processes
+        .iter()
+        .filter(|process| launched.contains(&process.pid) && is_scrcpy(&process.name))
+        .filter(|process| process.parent <= 1 || !processes.iter().any(|parent| parent.pid == process.parent))
+        .map(|process| process.pid)
+        .collect()
+}
+
+fn is_scrcpy(name: &str) -> bool {
+    name.eq_ignore_ascii_case("scrcpy.exe") || name == "scrcpy"
+}
+
+/// 当前所有进程：PID、父进程 PID 与可执行文件名
+#[cfg(windows)]
+fn processes() -> Vec<ProcessEntry> {
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS};
+
+    let mut entries = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return entries;
+        }
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut found = Process32FirstW(snapshot, &mut entry);
+        while found != 0 {
+            let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+            entries.push(ProcessEntry {
+                pid: entry.th32ProcessID,
+                parent: entry.th32ParentProcessID,
+                name: String::from_utf16_lossy(&entry.szExeFile[..len]),
+            });
+            found = Process32NextW(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+    }
+    entries
+}
+
+/// 当前所有进程：通过 ps 获取 PID、父进程 PID 与命令名
+#[cfg(not(windows))]
+fn processes() -> Vec<ProcessEntry> {
+    std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,comm="])
+        .output()
+        .map(|output| parse_ps(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// 解析 `ps -o pid=,ppid=,comm=` 的输出，命令名可能含空格
+#[cfg_attr(windows, allow(dead_code))]
+fn parse_ps(output: &str) -> Vec<ProcessEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pid, rest) = line.trim_start().split_once(char::is_whitespace)?;
+            let (parent, name) = rest.trim_start().split_once(char::is_whitespace)?;
+            Some(ProcessEntry {
+                pid: pid.parse().ok()?,
+                parent: parent.parse().ok()?,
+                name: name.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 强制结束进程
+#[cfg(windows)]
+fn force_kill(pid: u32) -> bool {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+    use winapi::um::winnt::PROCESS_TERMINATE;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let killed = TerminateProcess(handle, 1) != 0;
+        CloseHandle(handle);
+        killed
+    }
+}
+
+/// 强制结束进程：发送 SIGKILL
+#[cfg(not(windows))]
+fn force_kill(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-KILL", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_scrcpy_without_parent() {
+        let processes = parse_ps(
+            "    1     0 systemd\n  812     1 scrcpy\n  900   850 bash\n  901   900 scrcpy\n  950  4000 scrcpy\n  960   900 scrcpy-launcher\n 1000   900 my tool\n",
+        );
+        assert_eq!(processes.last().map(|p| p.name.as_str()), Some("my tool"));
+        // 812 被 init 收养，950 的父进程已不存在；901 的父进程 bash 仍在运行
+        let launched = [812, 901, 950];
+        assert_eq!(orphaned_scrcpy(&processes, &launched), vec![812, 950]);
+        // 812 同样没有父进程（例如用户通过脚本打开），不是启动器启动的就不算遗留
+        assert_eq!(orphaned_scrcpy(&processes, &[950]), vec![950]);
+        assert!(orphaned_scrcpy(&processes, &[]).is_empty());
+        assert!(is_scrcpy("SCRCPY.EXE"));
+        assert!(!is_scrcpy("scrcpy-launcher.exe"));
+    }
+}
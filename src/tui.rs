@@ -4,19 +4,26 @@
 use std::io;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
+use crate::device_monitor::{BatteryStatus, DeviceDetails, FpsStats, QrPairing, Support, Transport};
+use crate::history::{format_age, now_secs, HistoryEntry};
+use crate::log_sink::LogSink;
+use crate::port_forward::ActiveForward;
+use crate::qr::QrCode;
+use crate::session::SessionKind;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, Borders, List, ListItem, Paragraph,
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph,
     },
     Frame, Terminal,
 };
@@ -27,7 +34,82 @@ pub struct AppState {
     pub status: String,
     pub logs: Vec<LogEntry>,
     pub devices: Vec<DeviceInfo>,
+    /// 正在投屏设备的帧率统计：(序列号, 统计)
+    pub fps: Option<(String, FpsStats)>,
+    /// 当前 scrcpy 会话：(序列号, 会话类型)
+    pub session: Option<(String, SessionKind)>,
+    /// 当前会话的开始时间，用于显示录屏时长
+    pub session_started: Option<Instant>,
+    /// 设备列表中选中的行
+    pub selected_device: usize,
+    /// 连接过的设备，最近连接的在前
+    pub history: Vec<HistoryEntry>,
+    /// 历史列表中选中的行
+    pub selected_history: usize,
+    /// 在线设备上生效中的端口转发
+    pub forwards: Vec<ActiveForward>,
+    /// 正在等待手机扫码的二维码配对
+    pub qr_pairing: Option<QrPairing>,
+    /// 左下方面板当前显示的标签页
+    pub tab: Tab,
+    /// 过长的日志折行显示，关闭时截断为一行
+    pub wrap_logs: bool,
+    /// 尚未确认的错误与警告，固定显示在日志上方直到用户清除
+    pub alerts: Vec<LogEntry>,
+    /// 启动过程中各步骤的进度，全部完成前在启动窗口中显示
+    pub startup: Vec<(StartupStep, StepProgress)>,
+    /// 用户按键提前关闭了启动窗口
+    pub startup_dismissed: bool,
     pub should_quit: bool,
+    /// 每次修改状态时递增，界面据此判断是否需要重绘
+    pub revision: u64,
+}
+
+/// 启动过程中的检查步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupStep {
+    SingleInstance,
+    LoadConfig,
+    LocateScrcpy,
+    ProbeAdb,
+}
+
+impl StartupStep {
+    pub const ALL: [StartupStep; 4] = [
+        StartupStep::SingleInstance,
+        StartupStep::LoadConfig,
+        StartupStep::LocateScrcpy,
+        StartupStep::ProbeAdb,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StartupStep::SingleInstance => "单实例检查",
+            StartupStep::LoadConfig => "读取配置",
+            StartupStep::LocateScrcpy => "查找 scrcpy 与 adb",
+            StartupStep::ProbeAdb => "查询设备",
+        }
+    }
+}
+
+/// 启动步骤的进度，附带的文本显示在步骤名称之后
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepProgress {
+    Running,
+    Passed(String),
+    Failed(String),
+}
+
+/// 左下方面板的标签页
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tab {
+    /// 当前连接的设备
+    #[default]
+    Devices,
+    /// 连接过的设备
+    History,
+    /// 生效中的端口转发
+    Forwards,
 }
 
 /// 日志条目
@@ -36,10 +118,35 @@ pub struct LogEntry {
     pub timestamp: String,
     pub level: LogLevel,
     pub message: String,
+    /// 连续重复出现的次数，重复的日志合并为一条
+    pub count: u32,
+}
+
+impl LogEntry {
+    /// 显示的正文，重复出现时附加 ×N
+    pub fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} ×{}", self.message, self.count)
+        } else {
+            self.message.clone()
+        }
+    }
+
+    /// 与上一条相同时合并：更新时间并累加次数，返回是否已合并
+    fn merge_repeat(entries: &mut [LogEntry], level: LogLevel, message: &str, timestamp: &str) -> bool {
+        match entries.last_mut().filter(|e| e.level == level && e.message == message) {
+            Some(last) => {
+                last.timestamp = timestamp.to_string();
+                last.count += 1;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 /// 日志级别
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
     Success,
@@ -49,14 +156,327 @@ pub enum LogLevel {
     Launch,
 }
 
+impl LogLevel {
+    /// 日志前缀图标
+    pub fn icon(self) -> &'static str {
+        match self {
+            LogLevel::Info => "ℹ️",
+            LogLevel::Success => "✅",
+            LogLevel::Warning => "⚠️",
+            LogLevel::Error => "❌",
+            LogLevel::Device => "📱",
+            LogLevel::Launch => "🚀",
+        }
+    }
+
+    /// 日志级别的英文名称，用于纯文本与 JSON 日志
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Info => "info",
+            LogLevel::Success => "success",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Device => "device",
+            LogLevel::Launch => "launch",
+        }
+    }
+}
+
+/// 用户在界面上触发的操作，交由业务逻辑处理
+#[derive(Debug, Clone)]
+pub enum UserAction {
+    /// 当前设备在 USB 与无线连接之间切换
+    ToggleWireless,
+    /// 开关帧率统计（需要重启 scrcpy 会话）
+    ToggleFps,
+    /// 无线调试配对：配对地址、配对码，以及可选的连接地址
+    Pair {
+        address: String,
+        code: String,
+        connect_address: Option<String>,
+    },
+    /// 将选中的设备设为投屏目标
+    SelectDevice(String),
+    /// 设置设备昵称，None 表示清除
+    SetNickname {
+        serial: String,
+        nickname: Option<String>,
+    },
+    /// 重新连接历史记录中的设备
+    ConnectKnown(String),
+    /// 连接本机正在运行的 WSA / Waydroid
+    ConnectLocalTargets,
+    /// 开始或停止录制当前投屏的设备
+    ToggleRecording,
+    /// 恢复上次退出时的会话
+    ResumeSession,
+    /// 停止并重新启动设备的投屏会话
+    RestartSession(String),
+    /// 暂停或恢复自动投屏，暂停期间仍监控设备
+    ToggleAutoLaunch,
+    /// 结束上次启动器异常退出遗留的 scrcpy 进程
+    EndStrayScrcpy,
+    /// 开关音频转发
+    ToggleAudio,
+    /// 开关演示模式（显示触摸点、保持唤醒、窗口置顶、固定窗口大小）
+    TogglePresentation,
+    /// scrcpy 更新后重启当前会话以使用新版本
+    ApplyUpdate,
+    /// 切换设备使用的启动配置档
+    CycleProfile(String),
+    /// 切换设备的会话类型（镜像、录屏、仅音频、OTG、摄像头）
+    CycleSession(String),
+    /// 遥控器按键，keycode 为 Android 按键码
+    RemoteKey { serial: String, keycode: &'static str },
+    /// 开始二维码配对，等待手机扫码
+    StartQrPairing(QrPairing),
+    /// 取消二维码配对
+    CancelQrPairing,
+}
+
+/// 弹出式输入表单的用途
+#[derive(Debug, Clone, PartialEq)]
+enum FormKind {
+    Pairing,
+    Nickname(String),
+}
+
+/// 弹出式输入表单
+#[derive(Debug, Clone)]
+struct InputForm {
+    kind: FormKind,
+    title: &'static str,
+    fields: Vec<(&'static str, String)>,
+    focus: usize,
+    error: Option<String>,
+}
+
+/// 表单处理按键后的结果
+enum FormOutcome {
+    Pending,
+    Cancelled,
+    Submitted(UserAction),
+}
+
+impl InputForm {
+    /// 无线调试配对表单（手机上 "使用配对码配对设备" 显示的信息）
+    fn pairing() -> Self {
+        Self {
+            kind: FormKind::Pairing,
+            title: "📶 无线调试配对",
+            fields: vec![
+                ("配对地址 (IP:端口)", String::new()),
+                ("配对码 (6位数字)", String::new()),
+                ("连接端口 (可选)", String::new()),
+            ],
+            focus: 0,
+            error: None,
+        }
+    }
+
+    /// 设备昵称表单，预先填入当前昵称
+    fn nickname(device: &DeviceInfo) -> Self {
+        Self {
+            kind: FormKind::Nickname(device.id.clone()),
+            title: "✏️ 设备昵称（留空则清除）",
+            fields: vec![("昵称", device.nickname.clone().unwrap_or_default())],
+            focus: 0,
+            error: None,
+        }
+    }
+
+    /// 处理按键：Tab/↑/↓ 切换字段，Enter 提交，Esc 取消
+    fn handle_key(&mut self, key: KeyEvent) -> FormOutcome {
+        match key.code {
+            KeyCode::Esc => return FormOutcome::Cancelled,
+            KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                return FormOutcome::Cancelled;
+            }
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % self.fields.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                self.focus = (self.focus + self.fields.len() - 1) % self.fields.len();
+            }
+            KeyCode::Backspace => {
+                self.fields[self.focus].1.pop();
+            }
+            KeyCode::Char(c) => self.fields[self.focus].1.push(c),
+            KeyCode::Enter => match self.submit() {
+                Ok(action) => return FormOutcome::Submitted(action),
+                Err(e) => self.error = Some(e),
+            },
+            _ => {}
+        }
+        FormOutcome::Pending
+    }
+
+    /// 校验输入并生成对应的操作
+    fn submit(&self) -> Result<UserAction, String> {
+        let value = |i: usize| self.fields[i].1.trim();
+        match &self.kind {
+            FormKind::Pairing => parse_pairing_input(value(0), value(1), value(2)),
+            FormKind::Nickname(serial) => Ok(UserAction::SetNickname {
+                serial: serial.clone(),
+                nickname: Some(value(0).to_string()).filter(|n| !n.is_empty()),
+            }),
+        }
+    }
+}
+
+/// 校验配对表单输入
+pub fn parse_pairing_input(address: &str, code: &str, connect_port: &str) -> Result<UserAction, String> {
+    let (host, port) = address
+        .rsplit_once(':')
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| "配对地址格式应为 IP:端口".to_string())?;
+    port.parse::<u16>().map_err(|_| "配对端口无效".to_string())?;
+
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("配对码应为6位数字".to_string());
+    }
+
+    let connect_address = if connect_port.is_empty() {
+        None
+    } else {
+        let port = connect_port.parse::<u16>().map_err(|_| "连接端口无效".to_string())?;
+        Some(format!("{}:{}", host, port))
+    };
+
+    Ok(UserAction::Pair {
+        address: address.to_string(),
+        code: code.to_string(),
+        connect_address,
+    })
+}
+
+/// 设备状态（对应 adb devices 的状态列）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStatus {
+    Connected,
+    Unauthorized,
+    Offline,
+    Recovery,
+    Sideload,
+    NoPermissions,
+    /// 被允许/排除名单跳过自动启动
+    Ignored,
+    /// adb devices 显示在线，但 adb shell 没有响应
+    Degraded,
+    /// 自动重启次数超过上限，已停止重试
+    Failed,
+    /// 启动后首次 adb 查询完成前，显示上次退出前在线的设备
+    Stale,
+    Unknown,
+}
+
+impl DeviceStatus {
+    /// 从 adb devices 的状态文本解析
+    pub fn from_adb(state: &str) -> Self {
+        if state.starts_with("no permissions") {
+            return DeviceStatus::NoPermissions;
+        }
+        match state.split_whitespace().next().unwrap_or_default() {
+            "device" => DeviceStatus::Connected,
+            "unauthorized" => DeviceStatus::Unauthorized,
+            "offline" => DeviceStatus::Offline,
+            "recovery" => DeviceStatus::Recovery,
+            "sideload" => DeviceStatus::Sideload,
+            _ => DeviceStatus::Unknown,
+        }
+    }
+
+    /// 状态的英文名称，用于 JSON 输出
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceStatus::Connected => "device",
+            DeviceStatus::Unauthorized => "unauthorized",
+            DeviceStatus::Offline => "offline",
+            DeviceStatus::Recovery => "recovery",
+            DeviceStatus::Sideload => "sideload",
+            DeviceStatus::NoPermissions => "no_permissions",
+            DeviceStatus::Ignored => "ignored",
+            DeviceStatus::Degraded => "degraded",
+            DeviceStatus::Failed => "failed",
+            DeviceStatus::Stale => "stale",
+            DeviceStatus::Unknown => "unknown",
+        }
+    }
+
+    /// 状态的中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            DeviceStatus::Connected => "已连接",
+            DeviceStatus::Unauthorized => "未授权",
+            DeviceStatus::Offline => "离线",
+            DeviceStatus::Recovery => "恢复模式",
+            DeviceStatus::Sideload => "线刷模式",
+            DeviceStatus::NoPermissions => "无权限",
+            DeviceStatus::Ignored => "已忽略",
+            DeviceStatus::Degraded => "无响应",
+            DeviceStatus::Failed => "重启过多",
+            DeviceStatus::Stale => "上次在线",
+            DeviceStatus::Unknown => "未知状态",
+        }
+    }
+
+    /// 针对异常状态的处理建议
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            DeviceStatus::Unauthorized => Some("请在手机上允许USB调试（勾选“始终允许”可免去下次确认）"),
+            DeviceStatus::Offline => Some("请重新插拔数据线，或在开发者选项中关闭再打开USB调试"),
+            DeviceStatus::Recovery | DeviceStatus::Sideload => Some("设备未进入系统，请重启到正常模式后再投屏"),
+            DeviceStatus::NoPermissions => Some("当前用户无权访问该USB设备，请检查驱动或系统权限"),
+            DeviceStatus::Degraded => Some("adb shell 没有响应，已重启投屏会话；如持续出现请重新插拔数据线或重启手机的USB调试"),
+            DeviceStatus::Failed => Some("scrcpy 自动重启次数过多，已停止重试；排除问题后按 Enter 或 R 重新投屏"),
+            DeviceStatus::Stale => Some("正在查询设备，这里显示的是上次退出前在线的设备"),
+            _ => None,
+        }
+    }
+
+    /// 设备是否可以启动 scrcpy（无响应的设备仍会尝试重启会话，重启过多的设备可以手动投屏）
+    pub fn is_ready(self) -> bool {
+        matches!(self, DeviceStatus::Connected | DeviceStatus::Degraded | DeviceStatus::Failed)
+    }
+}
+
 /// 设备信息
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
     pub id: String,
     pub name: String,
-    pub status: String,
+    pub status: DeviceStatus,
+    pub battery: Option<BatteryStatus>,
+    /// 用户设置的昵称
+    pub nickname: Option<String>,
+    /// 系统版本与屏幕参数，设备首次出现时获取
+    pub details: Option<DeviceDetails>,
+    /// `adb devices -l` 报告的产品代号
+    pub product: Option<String>,
+    /// `adb devices -l` 报告的 transport_id，可用于 `adb -t`
+    pub transport_id: Option<u32>,
+    /// 设备所在的远程 adb server，本机设备为 None
+    pub server: Option<String>,
+    /// 停止自动重启前 scrcpy 最后输出的错误
+    pub last_error: Option<String>,
 }
 
+impl DeviceInfo {
+    /// 是否为 Android 模拟器
+    pub fn is_emulator(&self) -> bool {
+        crate::device_monitor::is_emulator_serial(&self.id)
+    }
+
+    /// 显示名称，有昵称时形如 "小米13 (Pro工作机)"
+    pub fn display_name(&self) -> String {
+        match &self.nickname {
+            Some(nickname) => format!("{} ({})", self.name, nickname),
+            None => self.name.clone(),
+        }
+    }
+}
+
+
+/// 告警区域最多保留的告警数量，超出时丢弃最旧的
+const MAX_ALERTS: usize = 3;
 
 impl Default for AppState {
     fn default() -> Self {
@@ -64,20 +484,57 @@ impl Default for AppState {
             status: "初始化中...".to_string(),
             logs: Vec::new(),
             devices: Vec::new(),
+            fps: None,
+            session: None,
+            session_started: None,
+            selected_device: 0,
+            history: Vec::new(),
+            selected_history: 0,
+            forwards: Vec::new(),
+            qr_pairing: None,
+            tab: Tab::Devices,
+            wrap_logs: false,
+            alerts: Vec::new(),
+            startup: Vec::new(),
+            startup_dismissed: false,
             should_quit: false,
+            revision: 0,
         }
     }
 }
 
+/// 界面日志区作为日志输出：条数有上限，写入不会阻塞
+impl LogSink for AppState {
+    fn write(&mut self, level: LogLevel, message: &str) -> bool {
+        self.add_log(level, message.to_string());
+        true
+    }
+}
+
 impl AppState {
     /// 添加日志条目
     pub fn add_log(&mut self, level: LogLevel, message: String) {
+        self.touch();
         let timestamp = get_timestamp();
-        self.logs.push(LogEntry {
+        let entry = LogEntry {
             timestamp,
             level,
             message,
-        });
+            count: 1,
+        };
+        if matches!(level, LogLevel::Error | LogLevel::Warning)
+            && !LogEntry::merge_repeat(&mut self.alerts, level, &entry.message, &entry.timestamp)
+        {
+            self.alerts.push(entry.clone());
+            if self.alerts.len() > MAX_ALERTS {
+                self.alerts.remove(0);
+            }
+        }
+        // 连续重复的日志（例如空闲时反复出现的提示）只保留一条，避免挤掉有用的记录
+        if LogEntry::merge_repeat(&mut self.logs, level, &entry.message, &entry.timestamp) {
+            return;
+        }
+        self.logs.push(entry);
         
         // 保持最多100条日志
         if self.logs.len() > 100 {
@@ -85,22 +542,188 @@ impl AppState {
         }
     }
 
+    /// 清除所有告警
+    pub fn dismiss_alerts(&mut self) {
+        self.touch();
+        self.alerts.clear();
+    }
+
     /// 更新状态
     pub fn set_status(&mut self, status: String) {
+        self.touch();
         self.status = status;
     }
 
     /// 更新设备列表
     pub fn update_devices(&mut self, devices: Vec<DeviceInfo>) {
+        self.touch();
         self.devices = devices;
+        self.selected_device = self.selected_device.min(self.devices.len().saturating_sub(1));
+    }
+
+    /// 更新设备历史
+    pub fn set_history(&mut self, history: Vec<HistoryEntry>) {
+        self.touch();
+        self.history = history;
+        self.selected_history = self.selected_history.min(self.history.len().saturating_sub(1));
+    }
+
+    /// 显示或关闭二维码配对弹窗
+    pub fn set_qr_pairing(&mut self, pairing: Option<QrPairing>) {
+        self.touch();
+        self.qr_pairing = pairing;
+    }
+
+    /// 更新生效中的端口转发
+    pub fn set_forwards(&mut self, forwards: Vec<ActiveForward>) {
+        self.touch();
+        self.forwards = forwards;
+    }
+
+    /// 依次切换设备列表、历史记录与端口转发标签页
+    pub fn toggle_tab(&mut self) {
+        self.touch();
+        self.tab = match self.tab {
+            Tab::Devices => Tab::History,
+            Tab::History => Tab::Forwards,
+            Tab::Forwards => Tab::Devices,
+        };
+    }
+
+    /// 切换日志折行/截断显示
+    pub fn toggle_log_wrap(&mut self) {
+        self.touch();
+        self.wrap_logs = !self.wrap_logs;
+    }
+
+    /// 移动当前标签页列表的选中行
+    pub fn move_selection(&mut self, offset: isize) {
+        self.touch();
+        let (selected, len) = match self.tab {
+            Tab::Devices => (&mut self.selected_device, self.devices.len()),
+            Tab::History => (&mut self.selected_history, self.history.len()),
+            // 端口转发列表只用于查看，没有选中行
+            Tab::Forwards => return,
+        };
+        if len == 0 {
+            return;
+        }
+        *selected = selected.saturating_add_signed(offset).min(len - 1);
+    }
+
+    /// 当前选中的设备
+    pub fn selected(&self) -> Option<&DeviceInfo> {
+        self.devices.get(self.selected_device)
+    }
+
+    /// 历史记录中选中的设备
+    pub fn selected_history(&self) -> Option<&HistoryEntry> {
+        self.history.get(self.selected_history)
+    }
+
+    /// 更新当前会话，None 表示没有运行中的会话
+    pub fn set_session(&mut self, session: Option<(String, SessionKind)>) {
+        self.touch();
+        self.session_started = session.as_ref().map(|_| Instant::now());
+        self.session = session;
+    }
+
+    /// 更新帧率统计，None 表示清除
+    pub fn set_fps(&mut self, fps: Option<(String, FpsStats)>) {
+        self.touch();
+        self.fps = fps;
+    }
+
+    /// 更新启动步骤的进度
+    pub fn set_startup_step(&mut self, step: StartupStep, progress: StepProgress) {
+        self.touch();
+        match self.startup.iter_mut().find(|(s, _)| *s == step) {
+            Some(entry) => entry.1 = progress,
+            None => self.startup.push((step, progress)),
+        }
+    }
+
+    /// 是否还要显示启动窗口：用户关闭前，有步骤未完成或有步骤失败时一直显示，失败原因不会一闪而过
+    pub fn startup_visible(&self) -> bool {
+        let finished = StartupStep::ALL.iter().all(|step| {
+            self.startup.iter().any(|(s, progress)| s == step && *progress != StepProgress::Running)
+        });
+        let failed = self.startup.iter().any(|(_, progress)| matches!(progress, StepProgress::Failed(_)));
+        !self.startup_dismissed && (!finished || failed)
+    }
+
+    /// 关闭启动窗口
+    pub fn dismiss_startup(&mut self) {
+        self.touch();
+        self.startup_dismissed = true;
     }
 
+    /// 标记状态已变化
+    fn touch(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
+    }
+}
+
+/// 界面最高刷新率
+const MAX_FPS: u64 = 30;
+/// 两帧之间的最短间隔
+const FRAME_INTERVAL: Duration = Duration::from_millis(1000 / MAX_FPS);
+/// 没有待绘制的帧时等待输入的最长时间，也决定了后台状态变化显示到界面上的延迟
+const INPUT_POLL: Duration = Duration::from_millis(50);
+/// 即使没有变化也定期重绘（状态面板中的时钟）
+const IDLE_REDRAW: Duration = Duration::from_secs(1);
+
+/// 控制重绘时机：只在状态变化、用户输入或时钟需要更新时重绘，且不超过 MAX_FPS
+#[derive(Debug, Default)]
+struct FrameLimiter {
+    last_draw: Option<Instant>,
+    drawn_revision: Option<u64>,
+    dirty: bool,
+}
+
+impl FrameLimiter {
+    /// 用户输入或窗口变化后需要重绘
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// 当前是否应该绘制一帧
+    fn should_draw(&mut self, revision: u64, now: Instant) -> bool {
+        let Some(last_draw) = self.last_draw else {
+            return true;
+        };
+        if self.drawn_revision != Some(revision) || now.duration_since(last_draw) >= IDLE_REDRAW {
+            self.dirty = true;
+        }
+        self.dirty && now.duration_since(last_draw) >= FRAME_INTERVAL
+    }
+
+    /// 记录已绘制的帧
+    fn drawn(&mut self, revision: u64, now: Instant) {
+        self.last_draw = Some(now);
+        self.drawn_revision = Some(revision);
+        self.dirty = false;
+    }
+
+    /// 等待输入的时间：有待绘制的帧时只等到下一帧，否则按输入轮询间隔
+    fn poll_timeout(&self, now: Instant) -> Duration {
+        match self.last_draw {
+            Some(last_draw) if self.dirty => FRAME_INTERVAL.saturating_sub(now.duration_since(last_draw)),
+            _ => INPUT_POLL,
+        }
+    }
 }
 
 /// TUI 应用程序
 pub struct TuiApp {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     state: AppState,
+    /// 当前打开的输入表单
+    form: Option<InputForm>,
+    /// 是否显示选中设备的详情弹窗
+    show_details: bool,
+    /// 遥控器弹窗控制的设备
+    remote: Option<String>,
 }
 
 impl TuiApp {
@@ -119,51 +742,176 @@ impl TuiApp {
         Ok(Self {
             terminal,
             state,
+            form: None,
+            show_details: false,
+            remote: None,
         })
     }
 
     /// 使用共享状态运行 TUI 应用程序
-    pub async fn run_with_shared_state(&mut self, shared_state: Arc<Mutex<AppState>>) -> Result<(), Box<dyn std::error::Error>> {
-        let tick_rate = Duration::from_millis(100); // 提高刷新频率以获得更快响应
-        let mut last_tick = Instant::now();
+    pub async fn run_with_shared_state(
+        &mut self,
+        shared_state: Arc<Mutex<AppState>>,
+        actions: mpsc::Sender<UserAction>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // 输入轮询与重绘分开：按键立即处理，重绘按需进行且有帧率上限
+        let mut frames = FrameLimiter::default();
 
         loop {
-            // 从共享状态获取最新数据
-            let state_clone = {
-                let state = shared_state.lock().await;
-                state.clone()
-            };
-
-            self.terminal.draw(|f| draw_ui(f, &state_clone))?;
-
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
+            let revision = shared_state.lock().await.revision;
+            if frames.should_draw(revision, Instant::now()) {
+                // 从共享状态获取最新数据
+                let state_clone = {
+                    let state = shared_state.lock().await;
+                    state.clone()
+                };
+                self.draw(&state_clone)?;
+                frames.drawn(state_clone.revision, Instant::now());
+            }
 
-            if crossterm::event::poll(timeout)? {
-                if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
-                        match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                let mut state = shared_state.lock().await;
-                                state.should_quit = true;
-                                break;
+            if crossterm::event::poll(frames.poll_timeout(Instant::now()))? {
+                frames.mark_dirty();
+                match event::read()? {
+                    Event::Key(key) => {
+                        if key.kind != KeyEventKind::Press {
+                            // 忽略按键释放/重复事件
+                        } else if let Some(form) = self.form.as_mut() {
+                            // 表单打开时按键全部交给表单处理
+                            match form.handle_key(key) {
+                                FormOutcome::Pending => {}
+                                FormOutcome::Cancelled => self.form = None,
+                                FormOutcome::Submitted(action) => {
+                                    let _ = actions.try_send(action);
+                                    self.form = None;
+                                }
                             }
-                            KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
-                                let mut state = shared_state.lock().await;
-                                state.should_quit = true;
-                                break;
+                        } else if key.code != KeyCode::Char('q')
+                            && !key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL)
+                            && shared_state.lock().await.startup_visible()
+                        {
+                            shared_state.lock().await.dismiss_startup();
+                        } else if self.show_details && matches!(key.code, KeyCode::Esc | KeyCode::Char('i')) {
+                            self.show_details = false;
+                        } else if let Some(serial) = self.remote.clone() {
+                            // 遥控器打开时按键转换为设备按键
+                            if matches!(key.code, KeyCode::Esc | KeyCode::Char('k')) {
+                                self.remote = None;
+                            } else if let Some(keycode) = remote_keycode(key.code) {
+                                let _ = actions.try_send(UserAction::RemoteKey { serial, keycode });
+                            }
+                        } else if matches!(key.code, KeyCode::Esc | KeyCode::Char('p')) && shared_state.lock().await.qr_pairing.is_some() {
+                            shared_state.lock().await.set_qr_pairing(None);
+                            let _ = actions.try_send(UserAction::CancelQrPairing);
+                        } else {
+                            match key.code {
+                                KeyCode::Char('q') | KeyCode::Esc => {
+                                    let mut state = shared_state.lock().await;
+                                    state.should_quit = true;
+                                    break;
+                                }
+                                KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                                    let mut state = shared_state.lock().await;
+                                    state.should_quit = true;
+                                    break;
+                                }
+                                KeyCode::Char('c') => {
+                                    let _ = actions.try_send(UserAction::ConnectLocalTargets);
+                                }
+                                KeyCode::Char('w') => {
+                                    let _ = actions.try_send(UserAction::ToggleWireless);
+                                }
+                                KeyCode::Char('f') => {
+                                    let _ = actions.try_send(UserAction::ToggleFps);
+                                }
+                                KeyCode::Char('a') => {
+                                    self.form = Some(InputForm::pairing());
+                                }
+                                KeyCode::Char('p') => {
+                                    let pairing = QrPairing::generate();
+                                    shared_state.lock().await.set_qr_pairing(Some(pairing.clone()));
+                                    let _ = actions.try_send(UserAction::StartQrPairing(pairing));
+                                }
+                                KeyCode::Char('v') => {
+                                    let _ = actions.try_send(UserAction::ToggleRecording);
+                                }
+                                KeyCode::Char('r') => {
+                                    let _ = actions.try_send(UserAction::ResumeSession);
+                                }
+                                KeyCode::Char('P') => {
+                                    let _ = actions.try_send(UserAction::ToggleAutoLaunch);
+                                }
+                                KeyCode::Char('K') => {
+                                    let _ = actions.try_send(UserAction::EndStrayScrcpy);
+                                }
+                                KeyCode::Char('R') => {
+                                    let state = shared_state.lock().await;
+                                    if let Some(device) = state.selected().filter(|_| state.tab == Tab::Devices) {
+                                        let _ = actions.try_send(UserAction::RestartSession(device.id.clone()));
+                                    }
+                                }
+                                KeyCode::Char('m') => {
+                                    let _ = actions.try_send(UserAction::ToggleAudio);
+                                }
+                                KeyCode::Char('d') => {
+                                    let _ = actions.try_send(UserAction::TogglePresentation);
+                                }
+                                KeyCode::Char('u') => {
+                                    let _ = actions.try_send(UserAction::ApplyUpdate);
+                                }
+                                KeyCode::Char('o') => {
+                                    let state = shared_state.lock().await;
+                                    if let Some(device) = state.selected().filter(|_| state.tab == Tab::Devices) {
+                                        let _ = actions.try_send(UserAction::CycleProfile(device.id.clone()));
+                                    }
+                                }
+                                KeyCode::Char('t') => {
+                                    let state = shared_state.lock().await;
+                                    if let Some(device) = state.selected().filter(|_| state.tab == Tab::Devices) {
+                                        let _ = actions.try_send(UserAction::CycleSession(device.id.clone()));
+                                    }
+                                }
+                                KeyCode::Char('n') => {
+                                    let state = shared_state.lock().await;
+                                    if let Some(device) = state.selected().filter(|_| state.tab == Tab::Devices) {
+                                        self.form = Some(InputForm::nickname(device));
+                                    }
+                                }
+                                KeyCode::Enter => {
+                                    let state = shared_state.lock().await;
+                                    let action = match state.tab {
+                                        Tab::Devices => state.selected().map(|d| UserAction::SelectDevice(d.id.clone())),
+                                        Tab::History => state.selected_history().map(|e| UserAction::ConnectKnown(e.serial.clone())),
+                                    Tab::Forwards => None,
+                                    };
+                                    if let Some(action) = action {
+                                        let _ = actions.try_send(action);
+                                    }
+                                }
+                                KeyCode::Char('i') => {
+                                    self.show_details = shared_state.lock().await.tab == Tab::Devices;
+                                }
+                                KeyCode::Char('k') => {
+                                    let state = shared_state.lock().await;
+                                    self.remote = state
+                                        .selected()
+                                        .filter(|d| state.tab == Tab::Devices && d.status.is_ready())
+                                        .map(|d| d.id.clone());
+                                }
+                                KeyCode::Tab => shared_state.lock().await.toggle_tab(),
+                                KeyCode::Char('l') => shared_state.lock().await.toggle_log_wrap(),
+                                KeyCode::Char('x') => shared_state.lock().await.dismiss_alerts(),
+                                KeyCode::Up => shared_state.lock().await.move_selection(-1),
+                                KeyCode::Down => shared_state.lock().await.move_selection(1),
+                                _ => {}
                             }
-                            _ => {}
                         }
                     }
+                    // 窗口大小变化时立即按新尺寸重新布局，不等下一次刷新
+                    Event::Resize(_, _) => self.terminal.autoresize()?,
+                    _ => {}
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
-            }
-
             // 检查是否应该退出
             {
                 let state = shared_state.lock().await;
@@ -179,6 +927,36 @@ impl TuiApp {
 
 
 
+    /// 绘制一帧
+    fn draw(&mut self, state: &AppState) -> io::Result<()> {
+        let show_details = self.show_details;
+        let remote = &self.remote;
+        let form = &self.form;
+        self.terminal.draw(|f| {
+            if is_too_small(f.area()) {
+                draw_too_small(f);
+                return;
+            }
+            draw_ui(f, state);
+            if show_details {
+                draw_device_details(f, state);
+            }
+            if let Some(serial) = remote {
+                draw_remote(f, state, serial);
+            }
+            if let Some(pairing) = &state.qr_pairing {
+                draw_qr_pairing(f, pairing);
+            }
+            if let Some(form) = form {
+                draw_form(f, form);
+            }
+            if state.startup_visible() {
+                draw_startup(f, state);
+            }
+        })?;
+        Ok(())
+    }
+
     /// 获取应用状态的可变引用
     pub fn state_mut(&mut self) -> &mut AppState {
         &mut self.state
@@ -190,6 +968,13 @@ impl TuiApp {
     }
 }
 
+/// 不进入备用屏幕，仅检查终端能否切换原始模式并读取尺寸（供 --self-check 使用）
+pub fn probe_terminal() -> Result<(u16, u16), String> {
+    enable_raw_mode().map_err(|e| format!("无法进入原始模式: {}", e))?;
+    let _ = disable_raw_mode();
+    crossterm::terminal::size().map_err(|e| format!("无法读取终端尺寸: {}", e))
+}
+
 impl Drop for TuiApp {
     fn drop(&mut self) {
         // 恢复终端状态
@@ -204,7 +989,7 @@ impl Drop for TuiApp {
 }
 
 /// 获取当前时间戳
-fn get_timestamp() -> String {
+pub fn get_timestamp() -> String {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap();
@@ -215,6 +1000,28 @@ fn get_timestamp() -> String {
     format!("{:02}:{:02}:{:02}", (hours + 8) % 24, minutes, seconds) // UTC+8
 }
 
+/// 界面可用的最小终端尺寸
+const MIN_WIDTH: u16 = 80;
+const MIN_HEIGHT: u16 = 20;
+
+/// 终端是否小于界面可用的最小尺寸
+fn is_too_small(area: Rect) -> bool {
+    area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+/// 终端过小时只显示提示，避免面板挤压变形
+fn draw_too_small(f: &mut Frame) {
+    let area = f.area();
+    let text = vec![
+        Line::from(Span::styled("终端窗口太小", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(format!("需要至少 {}x{}，当前 {}x{}", MIN_WIDTH, MIN_HEIGHT, area.width, area.height)),
+        Line::from("请放大窗口，按 q 退出"),
+    ];
+    let top = area.height.saturating_sub(text.len() as u16) / 2;
+    let area = Rect { y: area.y + top, height: area.height - top, ..area };
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center), area);
+}
+
 /// 绘制用户界面
 fn draw_ui(f: &mut Frame, state: &AppState) {
     let size = f.area();
@@ -251,14 +1058,31 @@ fn draw_ui(f: &mut Frame, state: &AppState) {
 
     // 绘制各个组件
     draw_status_panel(f, left_chunks[0], state);
-    draw_device_list(f, left_chunks[1], state);
+    match state.tab {
+        Tab::Devices => draw_device_list(f, left_chunks[1], state),
+        Tab::History => draw_history(f, left_chunks[1], state),
+        Tab::Forwards => draw_forwards(f, left_chunks[1], state),
+    }
 
-    draw_logs(f, content_chunks[1], state);
+    // 右侧布局：有未确认的告警时固定在日志上方
+    if state.alerts.is_empty() {
+        draw_logs(f, content_chunks[1], state);
+    } else {
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(state.alerts.len() as u16 + 2), // 告警
+                Constraint::Min(0),                                // 日志
+            ])
+            .split(content_chunks[1]);
+        draw_alerts(f, right_chunks[0], state);
+        draw_logs(f, right_chunks[1], state);
+    }
 }
 
 /// 绘制标题栏
 fn draw_header(f: &mut Frame, area: Rect) {
-    let title = format!("🚀 SCRCPY 智能启动器 v{} - 按 'q' 或 Ctrl+C 退出", env!("CARGO_PKG_VERSION"));
+    let title = format!("🚀 SCRCPY 智能启动器 v{} - 'w' 无线切换 | 'a' 配对 | 'p' 扫码配对 | 'n' 昵称 | 'o' 配置档 | 't' 会话类型 | 'i' 详情 | 'k' 遥控器 | Tab 历史/转发 | Enter 投屏选中设备 | 'v' 录屏 | 'm' 音频 | 'f' 帧率 | 'q' 或 Ctrl+C 退出", env!("CARGO_PKG_VERSION"));
     let header = Paragraph::new(title)
         .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center)
@@ -268,7 +1092,7 @@ fn draw_header(f: &mut Frame, area: Rect) {
 
 /// 绘制状态面板
 fn draw_status_panel(f: &mut Frame, area: Rect, state: &AppState) {
-    let status_text = vec![
+    let mut status_text = vec![
         Line::from(vec![
             Span::styled("状态: ", Style::default().fg(Color::Yellow)),
             Span::raw(&state.status),
@@ -278,6 +1102,15 @@ fn draw_status_panel(f: &mut Frame, area: Rect, state: &AppState) {
             Span::raw(get_timestamp()),
         ]),
     ];
+    if let (Some((serial, SessionKind::Record)), Some(started)) = (&state.session, state.session_started) {
+        status_text.push(Line::from(vec![
+            Span::styled("录屏: ", Style::default().fg(Color::Yellow)),
+            Span::styled(
+                format!("⏺️ {} {}", serial, format_elapsed(started.elapsed())),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
 
     let status_panel = Paragraph::new(status_text)
         .block(Block::default()
@@ -287,6 +1120,67 @@ fn draw_status_panel(f: &mut Frame, area: Rect, state: &AppState) {
     f.render_widget(status_panel, area);
 }
 
+/// 把时长格式化为 mm:ss，超过一小时为 h:mm:ss
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// 按显示宽度截断文本，超出时以省略号结尾（中文与 emoji 占两列）
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        // 为省略号保留一列
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    if max_width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+/// 按显示宽度折行，续行用空格缩进到 prefix 之后，使正文与首行对齐
+fn wrap_with_indent(prefix: &str, text: &str, max_width: usize) -> Vec<String> {
+    let indent = prefix.width();
+    // 宽度不足以容纳缩进时退回截断
+    if indent >= max_width {
+        return vec![truncate_to_width(&format!("{}{}", prefix, text), max_width)];
+    }
+
+    let mut lines = Vec::new();
+    let mut line = prefix.to_string();
+    let mut width = indent;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width {
+            lines.push(std::mem::replace(&mut line, " ".repeat(indent)));
+            width = indent;
+        }
+        line.push(c);
+        width += char_width;
+    }
+    lines.push(line);
+    lines
+}
+
+/// 列表面板内一行可用的显示宽度（减去左右边框）
+fn inner_width(area: Rect) -> usize {
+    area.width.saturating_sub(2) as usize
+}
+
 /// 绘制设备列表
 fn draw_device_list(f: &mut Frame, area: Rect, state: &AppState) {
     let devices: Vec<ListItem> = if state.devices.is_empty() {
@@ -295,50 +1189,646 @@ fn draw_device_list(f: &mut Frame, area: Rect, state: &AppState) {
         state.devices
             .iter()
             .map(|device| {
-                ListItem::new(format!("📱 {} - {} ({})", device.name, device.id, device.status))
+                let icon = if device.is_emulator() { "🖥️" } else { "📱" };
+                let mut text = format!("{} {} - {} ({})", icon, device.display_name(), device.id, device.status.label());
+                if let Some(server) = &device.server {
+                    text.push_str(&format!(" @{}", server));
+                }
+                if let Some(battery) = device.battery {
+                    let icon = if battery.charging { "⚡" } else { "🔋" };
+                    text.push_str(&format!(" {}{}%", icon, battery.level));
+                }
+                if let Some((_, kind)) = state.session.as_ref().filter(|(serial, _)| *serial == device.id) {
+                    text.push_str(&format!(" {} {}", kind.icon(), kind.label()));
+                }
+                if let Some((_, fps)) = state.fps.as_ref().filter(|(serial, _)| *serial == device.id) {
+                    text.push_str(&format!(
+                        " | {} fps (平均 {:.1}, 最低 {}, 最高 {}, 跳帧 {})",
+                        fps.current, fps.average, fps.min, fps.max, fps.skipped
+                    ));
+                }
+                if let Some(error) = &device.last_error {
+                    text.push_str(&format!(" | ❌ {}", error));
+                }
+                let color = match device.status {
+                    DeviceStatus::Connected => Color::White,
+                    DeviceStatus::Ignored | DeviceStatus::Stale => Color::DarkGray,
+                    DeviceStatus::Failed => Color::Red,
+                    _ => Color::Yellow,
+                };
+                ListItem::new(truncate_to_width(&text, inner_width(area))).style(Style::default().fg(color))
             })
             .collect()
     };
 
     let device_list = List::new(devices)
         .block(Block::default()
-            .title("📱 设备列表")
+            .title(tab_title(Tab::Devices))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default()
+        .with_selected((!state.devices.is_empty()).then_some(state.selected_device));
+    f.render_stateful_widget(device_list, area, &mut list_state);
+}
+
+/// 绘制连接过的设备
+fn draw_history(f: &mut Frame, area: Rect, state: &AppState) {
+    let now = now_secs();
+    let items: Vec<ListItem> = if state.history.is_empty() {
+        vec![ListItem::new("🕘 暂无历史记录")]
+    } else {
+        state.history
+            .iter()
+            .map(|entry| {
+                let online = state.devices.iter().any(|d| d.id == entry.serial);
+                let name = if entry.name.is_empty() { "Android设备" } else { &entry.name };
+                let text = format!(
+                    "{} {} - {} [{}] {} | 投屏 {} 次",
+                    if online { "🟢" } else { "⚪" },
+                    name,
+                    entry.serial,
+                    Transport::from_serial(&entry.serial).label(),
+                    format_age(now.saturating_sub(entry.last_seen)),
+                    entry.sessions
+                );
+                let color = if online { Color::White } else { Color::DarkGray };
+                ListItem::new(truncate_to_width(&text, inner_width(area))).style(Style::default().fg(color))
+            })
+            .collect()
+    };
+
+    let history_list = List::new(items)
+        .block(Block::default()
+            .title(tab_title(Tab::History))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default()
+        .with_selected((!state.history.is_empty()).then_some(state.selected_history));
+    f.render_stateful_widget(history_list, area, &mut list_state);
+}
+
+/// 绘制生效中的端口转发
+fn draw_forwards(f: &mut Frame, area: Rect, state: &AppState) {
+    let items: Vec<ListItem> = if state.forwards.is_empty() {
+        vec![ListItem::new("🔀 暂无端口转发（可在配置文件的 forwards 中添加）")]
+    } else {
+        state.forwards
+            .iter()
+            .map(|forward| {
+                let name = state.devices
+                    .iter()
+                    .find(|d| d.id == forward.serial)
+                    .map(|d| d.display_name())
+                    .unwrap_or_else(|| forward.serial.clone());
+                let text = format!("[{}] {} - {}", forward.rule.direction.label(), name, forward.rule.describe());
+                ListItem::new(truncate_to_width(&text, inner_width(area)))
+            })
+            .collect()
+    };
+
+    let forward_list = List::new(items)
+        .block(Block::default()
+            .title(tab_title(Tab::Forwards))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Magenta)));
-    f.render_widget(device_list, area);
+    f.render_widget(forward_list, area);
 }
 
+/// 设备面板标题，高亮当前标签页
+fn tab_title(active: Tab) -> Line<'static> {
+    let tabs = [
+        (Tab::Devices, "📱 设备列表"),
+        (Tab::History, "🕘 历史记录"),
+        (Tab::Forwards, "🔀 端口转发"),
+    ];
+    let mut spans = Vec::new();
+    for (i, (tab, label)) in tabs.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" | "));
+        }
+        spans.push(if tab == active {
+            Span::styled(label, Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
+        } else {
+            Span::styled(label, Style::default().fg(Color::DarkGray))
+        });
+    }
+    spans.push(Span::raw(match active {
+        Tab::Devices => " (↑/↓ 选择, Tab 切换)",
+        Tab::History => " (Enter 重新连接无线设备, Tab 切换)",
+        Tab::Forwards => " (设备上线时自动建立, Tab 切换)",
+    }));
+    Line::from(spans)
+}
 
 /// 绘制日志面板
 fn draw_logs(f: &mut Frame, area: Rect, state: &AppState) {
+    let mut remaining = area.height.saturating_sub(2) as usize; // 减去边框高度
     let logs: Vec<ListItem> = state.logs
         .iter()
         .rev() // 最新的日志在顶部
-        .take(area.height as usize - 2) // 减去边框高度
-        .map(|log| {
-            let (icon, color) = match log.level {
-                LogLevel::Info => ("ℹ️", Color::White),
-                LogLevel::Success => ("✅", Color::Green),
-                LogLevel::Warning => ("⚠️", Color::Yellow),
-                LogLevel::Error => ("❌", Color::Red),
-                LogLevel::Device => ("📱", Color::Magenta),
-                LogLevel::Launch => ("🚀", Color::Cyan),
+        .map_while(|log| {
+            if remaining == 0 {
+                return None;
+            }
+            let color = match log.level {
+                LogLevel::Info => Color::White,
+                LogLevel::Success => Color::Green,
+                LogLevel::Warning => Color::Yellow,
+                LogLevel::Error => Color::Red,
+                LogLevel::Device => Color::Magenta,
+                LogLevel::Launch => Color::Cyan,
             };
             
-            ListItem::new(format!("[{}] {} {}", log.timestamp, icon, log.message))
-                .style(Style::default().fg(color))
+            let prefix = format!("[{}] {} ", log.timestamp, log.level.icon());
+            let lines = if state.wrap_logs {
+                let mut lines = wrap_with_indent(&prefix, &log.text(), inner_width(area));
+                lines.truncate(remaining);
+                lines
+            } else {
+                vec![truncate_to_width(&format!("{}{}", prefix, log.text()), inner_width(area))]
+            };
+            remaining -= lines.len();
+            Some(ListItem::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+                .style(Style::default().fg(color)))
         })
         .collect();
 
+    let title = if state.wrap_logs { "📋 日志记录 ('l' 截断长日志)" } else { "📋 日志记录 ('l' 折行显示)" };
     let log_list = List::new(logs)
         .block(Block::default()
-            .title("📋 日志记录")
+            .title(title)
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Yellow)));
     f.render_widget(log_list, area);
 }
 
+/// 绘制告警区域，最新的告警在顶部
+fn draw_alerts(f: &mut Frame, area: Rect, state: &AppState) {
+    let alerts: Vec<ListItem> = state.alerts
+        .iter()
+        .rev()
+        .map(|alert| {
+            let color = if alert.level == LogLevel::Error { Color::Red } else { Color::Yellow };
+            let text = format!("[{}] {} {}", alert.timestamp, alert.level.icon(), alert.text());
+            ListItem::new(truncate_to_width(&text, inner_width(area))).style(Style::default().fg(color))
+        })
+        .collect();
+
+    let alert_list = List::new(alerts)
+        .block(Block::default()
+            .title(format!("🚨 未处理的告警 ({}) - 'x' 清除", state.alerts.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red)));
+    f.render_widget(alert_list, area);
+}
+
 // 已移除版本对比弹窗绘制函数
 
-// 已移除居中弹窗辅助函数
+/// 绘制输入表单弹窗
+fn draw_form(f: &mut Frame, form: &InputForm) {
+    let area = centered_rect(60, (form.fields.len() * 2 + 5) as u16, f.area());
+
+    let mut lines = Vec::new();
+    for (i, (label, value)) in form.fields.iter().enumerate() {
+        let focused = i == form.focus;
+        let marker = if focused { "▶ " } else { "  " };
+        lines.push(Line::from(Span::styled(
+            format!("{}{}", marker, label),
+            Style::default().fg(Color::Yellow),
+        )));
+        let cursor = if focused { "_" } else { "" };
+        lines.push(Line::from(format!("    {}{}", value, cursor)));
+    }
+    lines.push(Line::from(""));
+    match &form.error {
+        Some(error) => lines.push(Line::from(Span::styled(format!("❌ {}", error), Style::default().fg(Color::Red)))),
+        None => lines.push(Line::from(Span::styled(
+            "Tab 切换 | Enter 确认 | Esc 取消",
+            Style::default().fg(Color::DarkGray),
+        ))),
+    }
+
+    let popup = Paragraph::new(lines)
+        .block(Block::default()
+            .title(form.title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// 遥控器按键对应的 Android 按键码
+fn remote_keycode(code: KeyCode) -> Option<&'static str> {
+    Some(match code {
+        KeyCode::Up => "KEYCODE_DPAD_UP",
+        KeyCode::Down => "KEYCODE_DPAD_DOWN",
+        KeyCode::Left => "KEYCODE_DPAD_LEFT",
+        KeyCode::Right => "KEYCODE_DPAD_RIGHT",
+        KeyCode::Enter => "KEYCODE_DPAD_CENTER",
+        KeyCode::Backspace => "KEYCODE_BACK",
+        KeyCode::Char('h') => "KEYCODE_HOME",
+        KeyCode::Char('m') => "KEYCODE_MENU",
+        KeyCode::Char(' ') => "KEYCODE_MEDIA_PLAY_PAUSE",
+        KeyCode::Char('+') | KeyCode::Char('=') => "KEYCODE_VOLUME_UP",
+        KeyCode::Char('-') => "KEYCODE_VOLUME_DOWN",
+        _ => return None,
+    })
+}
+
+/// 绘制遥控器弹窗，电视盒子只用镜像窗口不便操作时使用
+fn draw_remote(f: &mut Frame, state: &AppState, serial: &str) {
+    let area = centered_rect(50, 11, f.area());
+    let name = state
+        .devices
+        .iter()
+        .find(|d| d.id == serial)
+        .map(|d| d.display_name())
+        .unwrap_or_else(|| serial.to_string());
+    let key = |keys: &str, action: &str| {
+        Line::from(vec![
+            Span::styled(format!("{:<12}", keys), Style::default().fg(Color::Yellow)),
+            Span::raw(action.to_string()),
+        ])
+    };
+    let lines = vec![
+        Line::from(format!("设备: {}", name)),
+        key("↑ ↓ ← →", "方向键"),
+        key("Enter", "确定"),
+        key("Backspace", "返回"),
+        key("h / m", "主页 / 菜单"),
+        key("空格", "播放/暂停"),
+        key("+ / -", "音量"),
+        Line::from(Span::styled("Esc 或 k 关闭", Style::default().fg(Color::DarkGray))),
+    ];
+
+    let popup = Paragraph::new(lines)
+        .block(Block::default()
+            .title("📺 遥控器")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// 绘制启动窗口：逐项显示启动步骤，卡住或失败的步骤一目了然
+fn draw_startup(f: &mut Frame, state: &AppState) {
+    let area = centered_rect(60, StartupStep::ALL.len() as u16 + 4, f.area());
+    let mut lines: Vec<Line> = StartupStep::ALL
+        .iter()
+        .map(|step| {
+            let progress = state.startup.iter().find(|(s, _)| s == step).map(|(_, p)| p);
+            let (mark, color, detail) = match progress {
+                None => ("·", Color::DarkGray, ""),
+                Some(StepProgress::Running) => ("⏳", Color::Yellow, "进行中..."),
+                Some(StepProgress::Passed(detail)) => ("✅", Color::Green, detail.as_str()),
+                Some(StepProgress::Failed(detail)) => ("❌", Color::Red, detail.as_str()),
+            };
+            // 步骤名称含中文，按显示宽度补齐，让结果列对齐
+            let padding = " ".repeat(20usize.saturating_sub(step.label().width()));
+            Line::from(vec![
+                Span::styled(format!("{} {}{}", mark, step.label(), padding), Style::default().fg(color)),
+                Span::raw(detail.to_string()),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("按任意键关闭", Style::default().fg(Color::DarkGray))));
+
+    let popup = Paragraph::new(lines)
+        .block(Block::default()
+            .title("🚀 正在启动")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// 绘制二维码配对弹窗
+fn draw_qr_pairing(f: &mut Frame, pairing: &QrPairing) {
+    let rows = match QrCode::encode(pairing.payload().as_bytes()) {
+        Ok(qr) => qr.to_half_blocks(2),
+        Err(e) => vec![e],
+    };
+    let qr_width = rows.first().map(|row| row.chars().count()).unwrap_or_default() as u16;
+
+    // 二维码需要深色模块、浅色背景才能被手机识别，不跟随终端配色
+    let qr_style = Style::default().fg(Color::Black).bg(Color::White);
+    let mut lines: Vec<Line> = rows
+        .into_iter()
+        .map(|row| Line::from(Span::styled(row, qr_style)))
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from("手机: 开发者选项 → 无线调试 → 使用二维码配对设备"));
+    lines.push(Line::from(Span::styled(
+        format!("等待扫码（服务名 {}）... Esc 或 p 取消", pairing.name),
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let area = f.area();
+    let width = (qr_width + 4).max(56).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let popup = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default()
+            .title("📷 扫码配对")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(popup, popup_area);
+}
+
+/// 绘制选中设备的详情弹窗
+fn draw_device_details(f: &mut Frame, state: &AppState) {
+    let area = centered_rect(60, 18, f.area());
+
+    let lines: Vec<Line> = match state.selected() {
+        Some(device) => {
+            let details = device.details.as_ref();
+            // 未就绪的设备无法执行 shell 命令，详情不会再更新
+            let unknown = || if device.status.is_ready() { "获取中..." } else { "不可用" }.to_string();
+            let android = details
+                .filter(|d| !d.android_version.is_empty())
+                .map(|d| match d.sdk {
+                    Some(sdk) => format!("{} (SDK {})", d.android_version, sdk),
+                    None => d.android_version.clone(),
+                })
+                .unwrap_or_else(unknown);
+            let resolution = details
+                .and_then(|d| d.resolution)
+                .map(|(width, height)| format!("{}x{}", width, height))
+                .unwrap_or_else(unknown);
+            let density = details
+                .and_then(|d| d.density)
+                .map(|dpi| format!("{} dpi", dpi))
+                .unwrap_or_else(unknown);
+            let battery = device
+                .battery
+                .map(|b| format!("{}%{}", b.level, if b.charging { " (充电中)" } else { "" }))
+                .unwrap_or_else(unknown);
+
+            let row = |label: &str, value: String| {
+                Line::from(vec![
+                    Span::styled(format!("{:<8}", label), Style::default().fg(Color::Yellow)),
+                    Span::raw(value),
+                ])
+            };
+            // 不支持的功能以灰色显示
+            let support = |label: &str, support: Option<&Support>| {
+                let support = support.cloned().unwrap_or_default();
+                let color = if matches!(support, Support::Unsupported(_)) { Color::DarkGray } else { Color::White };
+                Line::from(vec![
+                    Span::styled(format!("{:<8}", label), Style::default().fg(Color::Yellow)),
+                    Span::styled(support.label(), Style::default().fg(color)),
+                ])
+            };
+            let mut lines = vec![
+                row("名称", device.display_name()),
+                row("序列号", device.id.clone()),
+                row("状态", device.status.label().to_string()),
+                row("连接方式", match device.transport_id {
+                    Some(id) => format!("{} (transport_id {})", Transport::from_serial(&device.id).label(), id),
+                    None => Transport::from_serial(&device.id).label().to_string(),
+                }),
+                row("产品代号", device.product.clone().unwrap_or_else(|| "未知".to_string())),
+                row("Android", android),
+                row("分辨率", resolution),
+                row("屏幕密度", density),
+                row("形态", details.map(|d| d.form_factor.label().to_string()).unwrap_or_else(unknown)),
+                row("电量", battery),
+                support("音频", details.map(|d| &d.audio)),
+                support("摄像头", details.map(|d| &d.camera)),
+            ];
+            if let Some(error) = &device.last_error {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{:<8}", "最近错误"), Style::default().fg(Color::Yellow)),
+                    Span::styled(error.clone(), Style::default().fg(Color::Red)),
+                ]));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("↑/↓ 切换设备 | Esc 或 i 关闭", Style::default().fg(Color::DarkGray))));
+            lines
+        }
+        None => vec![Line::from("暂无设备")],
+    };
+
+    let popup = Paragraph::new(lines)
+        .block(Block::default()
+            .title("ℹ️ 设备详情")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// 计算居中弹窗区域（宽度为百分比，高度为行数）
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_pairing_input() {
+        match parse_pairing_input("192.168.1.5:37123", "123456", "41234") {
+            Ok(UserAction::Pair { address, code, connect_address }) => {
+                assert_eq!(address, "192.168.1.5:37123");
+                assert_eq!(code, "123456");
+                assert_eq!(connect_address.as_deref(), Some("192.168.1.5:41234"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert!(matches!(
+            parse_pairing_input("192.168.1.5:37123", "123456", ""),
+            Ok(UserAction::Pair { connect_address: None, .. })
+        ));
+        assert!(parse_pairing_input("192.168.1.5", "123456", "").is_err());
+        assert!(parse_pairing_input("192.168.1.5:37123", "12345", "").is_err());
+        assert!(parse_pairing_input("192.168.1.5:37123", "12a456", "").is_err());
+        assert!(parse_pairing_input("192.168.1.5:37123", "123456", "port").is_err());
+    }
+
+    #[test]
+    fn redraws_only_when_needed() {
+        let mut frames = FrameLimiter::default();
+        let start = Instant::now();
+        assert!(frames.should_draw(0, start));
+        frames.drawn(0, start);
+
+        // 没有变化时不重绘，只按输入轮询间隔等待
+        assert!(!frames.should_draw(0, start + FRAME_INTERVAL));
+        assert_eq!(frames.poll_timeout(start), INPUT_POLL);
+
+        // 状态变化后等到帧间隔结束再重绘
+        assert!(!frames.should_draw(1, start + Duration::from_millis(10)));
+        assert_eq!(frames.poll_timeout(start + Duration::from_millis(10)), FRAME_INTERVAL - Duration::from_millis(10));
+        assert!(frames.should_draw(1, start + FRAME_INTERVAL));
+        frames.drawn(1, start + FRAME_INTERVAL);
 
+        frames.mark_dirty();
+        assert!(frames.should_draw(1, start + FRAME_INTERVAL * 2));
+        frames.drawn(1, start + FRAME_INTERVAL * 2);
+        assert!(frames.should_draw(1, start + FRAME_INTERVAL * 2 + IDLE_REDRAW));
+    }
+
+    #[test]
+    fn truncates_by_display_width() {
+        assert_eq!(truncate_to_width("Pixel 7", 10), "Pixel 7");
+        // 中文每个字占两列，截断后加上省略号不超过宽度
+        assert_eq!(truncate_to_width("小米13 (Pro工作机)", 7), "小米13…");
+        assert_eq!(truncate_to_width("小米13 (Pro工作机)", 8).width(), 8);
+        assert_eq!(truncate_to_width("📱 设备", 3), "📱…");
+        assert_eq!(truncate_to_width("abc", 0), "");
+    }
+
+    #[test]
+    fn wraps_logs_with_hanging_indent() {
+        let lines = wrap_with_indent("[12:00:00] ❌ ", "adb: 设备未授权，请在手机上确认", 24);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "[12:00:00] ❌ adb: 设备");
+        // 续行缩进到时间戳与图标之后
+        assert!(lines[1].starts_with(&" ".repeat(14)));
+        assert!(lines.iter().all(|line| line.width() <= 24));
+        assert_eq!(wrap_with_indent("[12:00:00] ❌ ", "ok", 24), ["[12:00:00] ❌ ok"]);
+    }
+
+    #[test]
+    fn pins_recent_alerts_until_dismissed() {
+        let mut state = AppState::default();
+        state.add_log(LogLevel::Info, "等待设备连接中...".to_string());
+        for i in 0..=MAX_ALERTS {
+            state.add_log(LogLevel::Error, format!("scrcpy 启动失败 {}", i));
+        }
+        state.add_log(LogLevel::Warning, "设备离线".to_string());
+        assert_eq!(state.alerts.len(), MAX_ALERTS);
+        assert_eq!(state.alerts.last().map(|a| a.level), Some(LogLevel::Warning));
+        assert!(!state.alerts.iter().any(|a| a.message == "scrcpy 启动失败 0"));
+
+        state.dismiss_alerts();
+        assert!(state.alerts.is_empty());
+        assert_eq!(state.logs.len(), MAX_ALERTS + 3);
+    }
+
+    #[test]
+    fn collapses_repeated_logs() {
+        let mut state = AppState::default();
+        for _ in 0..3 {
+            state.add_log(LogLevel::Warning, "adb devices 超时".to_string());
+        }
+        state.add_log(LogLevel::Info, "adb devices 超时".to_string());
+        assert_eq!(state.logs.len(), 2);
+        assert_eq!(state.logs[0].text(), "adb devices 超时 ×3");
+        assert_eq!(state.logs[1].text(), "adb devices 超时");
+        assert_eq!(state.alerts.len(), 1);
+        assert_eq!(state.alerts[0].count, 3);
+
+        // 清除告警后再次出现的警告重新计数
+        state.dismiss_alerts();
+        state.add_log(LogLevel::Warning, "adb devices 超时".to_string());
+        assert_eq!(state.alerts[0].count, 1);
+    }
+
+    #[test]
+    fn formats_recording_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(83)), "01:23");
+        assert_eq!(format_elapsed(Duration::from_secs(3723)), "1:02:03");
+    }
+
+    #[test]
+    fn detects_too_small_terminal() {
+        assert!(!is_too_small(Rect::new(0, 0, 80, 20)));
+        assert!(is_too_small(Rect::new(0, 0, 79, 40)));
+        assert!(is_too_small(Rect::new(0, 0, 120, 19)));
+    }
+
+    #[test]
+    fn maps_remote_keys() {
+        assert_eq!(remote_keycode(KeyCode::Enter), Some("KEYCODE_DPAD_CENTER"));
+        assert_eq!(remote_keycode(KeyCode::Backspace), Some("KEYCODE_BACK"));
+        assert_eq!(remote_keycode(KeyCode::Char('=')), Some("KEYCODE_VOLUME_UP"));
+        assert_eq!(remote_keycode(KeyCode::Char('q')), None);
+    }
+
+    #[test]
+    fn hides_startup_when_all_steps_finish() {
+        let mut state = AppState::default();
+        assert!(state.startup_visible());
+        for step in StartupStep::ALL {
+            state.set_startup_step(step, StepProgress::Running);
+        }
+        assert!(state.startup_visible());
+        for step in StartupStep::ALL {
+            state.set_startup_step(step, StepProgress::Passed(String::new()));
+        }
+        assert!(!state.startup_visible());
+        // 有步骤失败时保持显示，直到用户关闭
+        state.set_startup_step(StartupStep::ProbeAdb, StepProgress::Failed("adb 无响应".to_string()));
+        assert!(state.startup_visible());
+        assert_eq!(state.startup.len(), StartupStep::ALL.len());
+        state.dismiss_startup();
+        assert!(!state.startup_visible());
+
+        let mut state = AppState::default();
+        state.dismiss_startup();
+        assert!(!state.startup_visible());
+    }
+
+    #[test]
+    fn keeps_selection_within_device_list() {
+        let device = |id: &str| DeviceInfo {
+            id: id.to_string(),
+            name: "Android设备".to_string(),
+            status: DeviceStatus::Connected,
+            battery: None,
+            nickname: None,
+            details: None,
+            product: None,
+            transport_id: None,
+            server: None,
+            last_error: None,
+        };
+        let mut state = AppState::default();
+        state.move_selection(1);
+        assert_eq!(state.selected_device, 0);
+
+        state.update_devices(vec![device("a"), device("b"), device("c")]);
+        state.move_selection(5);
+        assert_eq!(state.selected().map(|d| d.id.as_str()), Some("c"));
+        state.move_selection(-1);
+        assert_eq!(state.selected_device, 1);
+
+        state.update_devices(vec![device("a")]);
+        assert_eq!(state.selected_device, 0);
+
+        // 历史记录标签页有独立的选中行
+        state.set_history((0..3).map(|i| HistoryEntry {
+            serial: format!("192.168.1.{}:5555", i),
+            name: String::new(),
+            last_seen: 0,
+            sessions: 0,
+            window: None,
+            online: false,
+        }).collect());
+        state.toggle_tab();
+        state.move_selection(2);
+        assert_eq!(state.selected_history().map(|e| e.serial.as_str()), Some("192.168.1.2:5555"));
+        assert_eq!(state.selected_device, 0);
+    }
+}
@@ -0,0 +1,189 @@
+//! USB 热插拔通知模块
+//! 在 Windows 上注册 WM_DEVICECHANGE，USB 设备插拔时立即唤醒设备监控循环，
+//! 不必每个周期都执行 adb devices
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// 窗口线程与监控循环之间共享的通知状态
+#[derive(Default)]
+struct Shared {
+    changed: AtomicBool,
+    notify: Notify,
+}
+
+impl Shared {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn signal(&self) {
+        self.changed.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+}
+
+/// USB 热插拔监听器
+pub struct UsbHotplug {
+    shared: Arc<Shared>,
+}
+
+impl UsbHotplug {
+    /// 启动后台监听线程，注册失败时返回错误，调用方应退回轮询
+    pub fn start() -> Result<Self, String> {
+        let shared = Arc::new(Shared::default());
+        imp::start(shared.clone())?;
+        Ok(Self { shared })
+    }
+
+    /// 自上次调用以来是否发生过插拔，调用后清除标记
+    pub fn take_changed(&self) -> bool {
+        self.shared.changed.swap(false, Ordering::SeqCst)
+    }
+
+    /// 等待下一次插拔事件
+    pub async fn changed(&self) {
+        self.shared.notify.notified().await;
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::Shared;
+    use std::ptr;
+    use std::sync::{mpsc, Arc, OnceLock};
+    use winapi::shared::minwindef::{LPARAM, LRESULT, TRUE, UINT, WPARAM};
+    use winapi::shared::usbiodef::GUID_DEVINTERFACE_USB_DEVICE;
+    use winapi::shared::windef::HWND;
+    use winapi::um::dbt::{
+        DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+        DEV_BROADCAST_DEVICEINTERFACE_W,
+    };
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::winuser::{
+        CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+        RegisterClassW, RegisterDeviceNotificationW, TranslateMessage,
+        DEVICE_NOTIFY_WINDOW_HANDLE, HWND_MESSAGE, MSG, WM_DEVICECHANGE, WNDCLASSW,
+    };
+
+    /// 窗口过程没有用户参数，通过全局变量把事件转交给监控循环
+    static SHARED: OnceLock<Arc<Shared>> = OnceLock::new();
+
+    unsafe extern "system" fn window_proc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if msg == WM_DEVICECHANGE && (wparam == DBT_DEVICEARRIVAL || wparam == DBT_DEVICEREMOVECOMPLETE) {
+            if let Some(shared) = SHARED.get() {
+                shared.signal();
+            }
+            return TRUE as LRESULT;
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    pub fn start(shared: Arc<Shared>) -> Result<(), String> {
+        SHARED.set(shared).map_err(|_| "USB 热插拔监听已在运行".to_string())?;
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("usb-hotplug".to_string())
+            .spawn(move || unsafe {
+                match create_notification_window() {
+                    Ok(()) => {
+                        let _ = ready_tx.send(Ok(()));
+                        run_message_loop();
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                    }
+                }
+            })
+            .map_err(|e| format!("创建 USB 热插拔监听线程失败: {}", e))?;
+
+        ready_rx.recv().map_err(|_| "USB 热插拔监听线程意外退出".to_string())?
+    }
+
+    /// 创建仅消息窗口并注册 USB 设备接口通知
+    unsafe fn create_notification_window() -> Result<(), String> {
+        let class_name: Vec<u16> = "ScrcpyLauncherUsbHotplug\0".encode_utf16().collect();
+        let instance = GetModuleHandleW(ptr::null());
+
+        let mut class: WNDCLASSW = std::mem::zeroed();
+        class.lpfnWndProc = Some(window_proc);
+        class.hInstance = instance;
+        class.lpszClassName = class_name.as_ptr();
+        if RegisterClassW(&class) == 0 {
+            return Err(format!("注册窗口类失败 (错误码 {})", GetLastError()));
+        }
+
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            instance,
+            ptr::null_mut(),
+        );
+        if hwnd.is_null() {
+            return Err(format!("创建消息窗口失败 (错误码 {})", GetLastError()));
+        }
+
+        // 仅消息窗口收不到广播的 WM_DEVICECHANGE，必须显式注册设备接口通知
+        let mut filter: DEV_BROADCAST_DEVICEINTERFACE_W = std::mem::zeroed();
+        filter.dbcc_size = std::mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32;
+        filter.dbcc_devicetype = DBT_DEVTYP_DEVICEINTERFACE;
+        filter.dbcc_classguid = GUID_DEVINTERFACE_USB_DEVICE;
+
+        // 通知句柄随进程存续，不需要注销
+        let handle = RegisterDeviceNotificationW(
+            hwnd as _,
+            &mut filter as *mut _ as _,
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        );
+        if handle.is_null() {
+            let code = GetLastError();
+            DestroyWindow(hwnd);
+            return Err(format!("注册 USB 设备通知失败 (错误码 {})", code));
+        }
+
+        Ok(())
+    }
+
+    unsafe fn run_message_loop() {
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::Shared;
+    use std::sync::Arc;
+
+    pub fn start(_shared: Arc<Shared>) -> Result<(), String> {
+        Err("当前平台不支持 USB 热插拔通知".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn signal_sets_flag_and_wakes_waiter() {
+        let hotplug = UsbHotplug { shared: Arc::new(Shared::default()) };
+        assert!(!hotplug.take_changed());
+
+        hotplug.shared.signal();
+        // notify_one 会保留一个许可，信号先于等待到达也不会丢失
+        hotplug.changed().await;
+        assert!(hotplug.take_changed());
+        assert!(!hotplug.take_changed());
+    }
+}
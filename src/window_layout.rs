@@ -0,0 +1,240 @@
+//! 窗口布局模块
+//! 设备组为一组设备指定 scrcpy 窗口所在的显示器、位置与大小，多台测试机每次连接都出现在固定位置
+//! 不属于设备组的设备记住上次的窗口位置与大小，下次投屏时在原处打开；停止会话时通过关闭窗口让 scrcpy 正常退出
+
+use serde::{Deserialize, Serialize};
+
+/// 一组设备共用的窗口布局
+///
+/// 组内设备按 devices 中的顺序从左到右排列：设置了 width 时，第 N 台设备的窗口向右偏移 N 个窗口宽度
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceGroup {
+    /// 组内设备的序列号
+    pub devices: Vec<String>,
+    /// 显示器编号，1 为主显示器，其余按从左到右的顺序编号；不填时坐标相对于主显示器
+    pub monitor: Option<usize>,
+    /// 窗口左上角相对于显示器工作区的坐标
+    pub x: i32,
+    pub y: i32,
+    /// 窗口大小，不填时由 scrcpy 按设备分辨率决定
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// 一台显示器的工作区（不含任务栏），坐标为虚拟桌面坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub primary: bool,
+}
+
+/// scrcpy 窗口的位置与大小（画面区域，不含标题栏与边框），坐标为虚拟桌面坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    /// 在该位置以该大小打开窗口的 scrcpy 参数
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            format!("--window-x={}", self.x),
+            format!("--window-y={}", self.y),
+            format!("--window-width={}", self.width),
+            format!("--window-height={}", self.height),
+        ]
+    }
+}
+
+impl DeviceGroup {
+    /// 组内第 index 台设备的 scrcpy 窗口参数，monitors 为 monitors() 的结果
+    pub fn window_args(&self, index: usize, monitors: &[Monitor]) -> Result<Vec<String>, String> {
+        let (origin_x, origin_y) = match self.monitor {
+            Some(number) => match number.checked_sub(1).and_then(|i| monitors.get(i)) {
+                Some(monitor) => (monitor.x, monitor.y),
+                // 无法枚举显示器时，主显示器的原点就是虚拟桌面原点
+                None if number == 1 && monitors.is_empty() => (0, 0),
+                None => return Err(format!("显示器 {} 不存在（当前共 {} 台）", number, monitors.len())),
+            },
+            None => (0, 0),
+        };
+        let offset = self.width.map(|w| w as i32 * index as i32).unwrap_or_default();
+
+        let mut args = vec![
+            format!("--window-x={}", origin_x + self.x + offset),
+            format!("--window-y={}", origin_y + self.y),
+        ];
+        if let Some(width) = self.width {
+            args.push(format!("--window-width={}", width));
+        }
+        if let Some(height) = self.height {
+            args.push(format!("--window-height={}", height));
+        }
+        Ok(args)
+    }
+}
+
+/// 主显示器排在最前，其余按从左到右、从上到下排列
+#[cfg_attr(not(windows), allow(dead_code))]
+fn sort_monitors(monitors: &mut [Monitor]) {
+    monitors.sort_by_key(|m| (!m.primary, m.x, m.y));
+}
+
+/// 枚举当前连接的显示器，按显示器编号排序
+#[cfg(windows)]
+pub fn monitors() -> Vec<Monitor> {
+    use std::ptr;
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::{HDC, HMONITOR, LPRECT};
+    use winapi::um::winuser::{EnumDisplayMonitors, GetMonitorInfoW, MONITORINFO, MONITORINFOF_PRIMARY};
+
+    unsafe extern "system" fn collect(monitor: HMONITOR, _: HDC, _: LPRECT, data: LPARAM) -> BOOL {
+        let monitors = &mut *(data as *mut Vec<Monitor>);
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(monitor, &mut info) != 0 {
+            let work = info.rcWork;
+            monitors.push(Monitor {
+                x: work.left,
+                y: work.top,
+                width: (work.right - work.left).max(0) as u32,
+                height: (work.bottom - work.top).max(0) as u32,
+                primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+            });
+        }
+        TRUE
+    }
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            ptr::null_mut(),
+            ptr::null(),
+            Some(collect),
+            &mut monitors as *mut Vec<Monitor> as LPARAM,
+        );
+    }
+    sort_monitors(&mut monitors);
+    monitors
+}
+
+/// 非 Windows 平台无法枚举显示器，只支持相对于主显示器的坐标
+#[cfg(not(windows))]
+pub fn monitors() -> Vec<Monitor> {
+    Vec::new()
+}
+
+/// 查找进程 pid 的可见主窗口
+#[cfg(windows)]
+fn main_window(pid: u32) -> Option<winapi::shared::windef::HWND> {
+    use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{EnumWindows, GetWindow, GetWindowThreadProcessId, IsWindowVisible, GW_OWNER};
+
+    struct Search {
+        pid: u32,
+        found: Option<HWND>,
+    }
+
+    unsafe extern "system" fn find(window: HWND, data: LPARAM) -> BOOL {
+        let search = &mut *(data as *mut Search);
+        let mut owner_pid = 0;
+        GetWindowThreadProcessId(window, &mut owner_pid);
+        // 只取没有所有者的顶层可见窗口，跳过 SDL 创建的隐藏辅助窗口
+        if owner_pid == search.pid && IsWindowVisible(window) != 0 && GetWindow(window, GW_OWNER).is_null() {
+            search.found = Some(window);
+            return FALSE;
+        }
+        TRUE
+    }
+
+    let mut search = Search { pid, found: None };
+    unsafe {
+        EnumWindows(Some(find), &mut search as *mut Search as LPARAM);
+    }
+    search.found
+}
+
+/// 查找进程 pid 的可见主窗口并返回其画面区域；窗口最小化或尚未创建时返回 None
+#[cfg(windows)]
+pub fn process_window(pid: u32) -> Option<WindowGeometry> {
+    use winapi::shared::windef::{POINT, RECT};
+    use winapi::um::winuser::{ClientToScreen, GetClientRect, IsIconic};
+
+    let window = main_window(pid)?;
+    unsafe {
+        if IsIconic(window) != 0 {
+            return None;
+        }
+        let mut rect: RECT = std::mem::zeroed();
+        if GetClientRect(window, &mut rect) == 0 || rect.right <= 0 || rect.bottom <= 0 {
+            return None;
+        }
+        let mut origin = POINT { x: 0, y: 0 };
+        if ClientToScreen(window, &mut origin) == 0 {
+            return None;
+        }
+        Some(WindowGeometry { x: origin.x, y: origin.y, width: rect.right as u32, height: rect.bottom as u32 })
+    }
+}
+
+/// 非 Windows 平台不记录窗口位置
+#[cfg(not(windows))]
+pub fn process_window(_pid: u32) -> Option<WindowGeometry> {
+    None
+}
+
+/// 向进程 pid 的主窗口发送 WM_CLOSE，与用户点击关闭按钮相同；找不到窗口时返回 false
+#[cfg(windows)]
+pub fn close_process_window(pid: u32) -> bool {
+    use winapi::um::winuser::{PostMessageW, WM_CLOSE};
+
+    main_window(pid).is_some_and(|window| unsafe { PostMessageW(window, WM_CLOSE, 0, 0) != 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, primary: bool) -> Monitor {
+        Monitor { x, y: 0, width: 1920, height: 1040, primary }
+    }
+
+    #[test]
+    fn lays_out_group_windows() {
+        let mut monitors = vec![monitor(1920, false), monitor(-1920, false), monitor(0, true)];
+        sort_monitors(&mut monitors);
+        assert_eq!(monitors.iter().map(|m| m.x).collect::<Vec<_>>(), [0, -1920, 1920]);
+
+        let group = DeviceGroup {
+            devices: vec!["R58M123456".to_string(), "R58M654321".to_string()],
+            monitor: Some(3),
+            x: 10,
+            y: 20,
+            width: Some(400),
+            height: None,
+        };
+        assert_eq!(
+            group.window_args(1, &monitors).unwrap(),
+            ["--window-x=2330", "--window-y=20", "--window-width=400"]
+        );
+        assert!(DeviceGroup { monitor: Some(4), ..group.clone() }.window_args(0, &monitors).is_err());
+        assert_eq!(
+            DeviceGroup { monitor: Some(1), ..group }.window_args(0, &[]).unwrap()[0],
+            "--window-x=10"
+        );
+
+        let saved = WindowGeometry { x: -1900, y: 40, width: 360, height: 780 };
+        assert_eq!(
+            saved.args(),
+            ["--window-x=-1900", "--window-y=40", "--window-width=360", "--window-height=780"]
+        );
+    }
+}